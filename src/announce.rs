@@ -0,0 +1,61 @@
+use std::process::Stdio;
+use std::sync::Mutex;
+
+use crate::command;
+use crate::config::ModuleConfig;
+use crate::modules::ModuleStatus;
+
+/// Speech command from `daemon.announce_cmd`; `None` keeps announcements off
+static COMMAND: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn init(template: Option<&str>) {
+    *COMMAND.lock().unwrap() = template.map(str::to_string);
+}
+
+/// Speak a message through the screen reader command, if one is configured
+pub fn say(message: &str) {
+    let Some(template) = COMMAND.lock().unwrap().clone() else {
+        return;
+    };
+    let cmd = match command::build(&template, &[("message", message)], false) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            tracing::warn!("Invalid announce_cmd: {:#}", e);
+            return;
+        }
+    };
+    let result = tokio::process::Command::from(cmd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(e) = result {
+        tracing::warn!("Failed to announce {:?}: {}", message, e);
+    }
+}
+
+/// How announcements refer to a module: its `label`, else its capitalized name
+pub fn label(module: &str, config: Option<&ModuleConfig>) -> String {
+    if let Some(label) = config.and_then(|m| m.label.clone()) {
+        return label;
+    }
+    let mut chars = module.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// What to say when a module turns urgent, e.g. "Battery critical 9 percent"
+pub fn urgent_message(label: &str, status: &ModuleStatus) -> String {
+    let critical = status.class.iter().any(|c| c == "critical");
+    match (critical, status.percentage) {
+        (true, Some(percent)) => format!("{} critical {} percent", label, percent),
+        (true, None) => format!("{} critical", label),
+        _ => {
+            let plain = status.plain_tooltip();
+            let detail = plain.lines().next().filter(|l| !l.is_empty()).unwrap_or(&status.text);
+            format!("{} needs attention: {}", label, detail)
+        }
+    }
+}
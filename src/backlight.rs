@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+/// A backlight device under /sys/class/backlight
+pub struct Backlight {
+    path: PathBuf,
+}
+
+impl Backlight {
+    /// The first backlight device, by name (like brightnessctl picks it)
+    pub fn find() -> Option<Self> {
+        let mut devices: Vec<PathBuf> = std::fs::read_dir(BACKLIGHT_DIR)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .collect();
+        devices.sort();
+        devices.into_iter().next().map(|path| Self { path })
+    }
+
+    /// The sysfs node holding the current level
+    pub fn brightness_file(&self) -> PathBuf {
+        self.path.join("brightness")
+    }
+
+    fn name(&self) -> &str {
+        self.path.file_name().and_then(|n| n.to_str()).unwrap_or("")
+    }
+
+    fn read(&self, file: &str) -> Option<u32> {
+        read_number(&self.path.join(file))
+    }
+
+    /// Current level in percent of the maximum
+    pub fn percent(&self) -> Option<u32> {
+        let max = self.read("max_brightness").filter(|m| *m > 0)?;
+        let current = self.read("brightness")?;
+        Some(((current as f64 * 100.0 / max as f64).round() as u32).min(100))
+    }
+
+    /// Set the level in percent. Falls back to logind's `SetBrightness`
+    /// when the sysfs node isn't writable, as it usually isn't for users.
+    pub fn set_percent(&self, percent: u32) -> Result<()> {
+        let max = self.read("max_brightness").context("Backlight has no max_brightness")?;
+        let value = (max as f64 * percent.min(100) as f64 / 100.0).round() as u32;
+
+        match std::fs::write(self.path.join("brightness"), value.to_string()) {
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => self.set_via_logind(value),
+            result => result.with_context(|| format!("Failed to set {} brightness", self.name())),
+        }
+    }
+
+    fn set_via_logind(&self, value: u32) -> Result<()> {
+        let connection = zbus::blocking::Connection::system()?;
+        connection
+            .call_method(
+                Some("org.freedesktop.login1"),
+                "/org/freedesktop/login1/session/auto",
+                Some("org.freedesktop.login1.Session"),
+                "SetBrightness",
+                &("backlight", self.name(), value),
+            )
+            .with_context(|| format!("logind refused to set {} brightness", self.name()))?;
+        Ok(())
+    }
+}
+
+fn read_number(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use zbus::names::InterfaceName;
+use zbus::zvariant::OwnedValue;
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+const DEVICE: &str = "org.freedesktop.UPower.Device";
+
+/// Minimum time between two sysfs readings before their difference counts as a rate
+const MIN_SAMPLE_AGE: Duration = Duration::from_secs(60);
+/// Baseline readings older than this are replaced, so the rate follows the current load
+const MAX_SAMPLE_AGE: Duration = Duration::from_secs(600);
+
+/// A charge level reading for batteries that don't report their power draw
+struct Sample {
+    level: u64,
+    at: Instant,
+    charging: bool,
+    rate: Option<f64>,
+}
+
+static SAMPLE: Mutex<Option<Sample>> = Mutex::new(None);
+
+type Properties = HashMap<String, OwnedValue>;
+
+/// UPower's display device: all batteries combined, as a desktop shows them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Display {
+    pub present: bool,
+    /// UPower's device state: 1 charging, 2 discharging, 4 fully charged, ...
+    pub state: u32,
+    pub percentage: f64,
+    /// Seconds; 0 while UPower has no estimate yet
+    pub time_to_empty: i64,
+    pub time_to_full: i64,
+    /// Watts going into or out of the batteries
+    pub energy_rate: f64,
+}
+
+impl Display {
+    fn from_properties(properties: &Properties) -> Display {
+        let mut display = Display::default();
+        display.update(properties);
+        display
+    }
+
+    fn update(&mut self, properties: &Properties) {
+        let get = |key: &str| properties.get(key);
+        if let Some(present) = get("IsPresent").and_then(|v| v.downcast_ref::<bool>().ok()) {
+            self.present = present;
+        }
+        if let Some(state) = get("State").and_then(|v| v.downcast_ref::<u32>().ok()) {
+            self.state = state;
+        }
+        if let Some(percentage) = get("Percentage").and_then(|v| v.downcast_ref::<f64>().ok()) {
+            self.percentage = percentage;
+        }
+        if let Some(seconds) = get("TimeToEmpty").and_then(|v| v.downcast_ref::<i64>().ok()) {
+            self.time_to_empty = seconds;
+        }
+        if let Some(seconds) = get("TimeToFull").and_then(|v| v.downcast_ref::<i64>().ok()) {
+            self.time_to_full = seconds;
+        }
+        if let Some(rate) = get("EnergyRate").and_then(|v| v.downcast_ref::<f64>().ok()) {
+            self.energy_rate = rate;
+        }
+    }
+
+    /// What the battery module shows, at the precision it shows it, so UpdateTime
+    /// ticks and tiny rate changes don't refresh the bar
+    fn summary(&self) -> (bool, u32, i64, i64, i64, i64) {
+        (
+            self.present,
+            self.state,
+            self.percentage.round() as i64,
+            self.time_to_empty / 60,
+            self.time_to_full / 60,
+            (self.energy_rate * 10.0).round() as i64,
+        )
+    }
+}
+
+/// The running watcher's display device. It belongs to the `Watch`, so once that is
+/// dropped (watchers aborted on reload) reads query UPower directly again.
+static DISPLAY: Mutex<Weak<Mutex<Display>>> = Mutex::new(Weak::new());
+
+/// The display device: the watcher's copy, else a one-off query; `None` without UPower
+pub fn display() -> Option<Display> {
+    if let Some(display) = DISPLAY.lock().unwrap().upgrade() {
+        return Some(display.lock().unwrap().clone());
+    }
+    let connection = zbus::blocking::Connection::system().ok()?;
+    let properties = zbus::blocking::fdo::PropertiesProxy::builder(&connection)
+        .destination(UPOWER)
+        .ok()?
+        .path(DISPLAY_DEVICE)
+        .ok()?
+        .build()
+        .ok()?
+        .get_all(InterfaceName::from_static_str_unchecked(DEVICE))
+        .ok()?;
+    Some(Display::from_properties(&properties))
+}
+
+async fn load(connection: &zbus::Connection) -> Result<Display> {
+    let properties = zbus::fdo::PropertiesProxy::builder(connection)
+        .destination(UPOWER)?
+        .path(DISPLAY_DEVICE)?
+        .build()
+        .await?
+        .get_all(InterfaceName::from_static_str_unchecked(DEVICE))
+        .await?;
+    Ok(Display::from_properties(&properties))
+}
+
+/// UPower's signals, keeping the display device current for as long as this is alive
+pub struct Watch {
+    connection: zbus::Connection,
+    messages: futures::stream::SelectAll<zbus::MessageStream>,
+    display: Arc<Mutex<Display>>,
+}
+
+impl Watch {
+    pub async fn start() -> Result<Watch> {
+        let connection = zbus::Connection::system().await?;
+        let properties = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path(DISPLAY_DEVICE)?
+            .arg(0, DEVICE)?
+            .build();
+        let owner = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus")?
+            .member("NameOwnerChanged")?
+            .arg(0, UPOWER)?
+            .build();
+        let messages = futures::stream::select_all([
+            zbus::MessageStream::for_match_rule(properties, &connection, Some(16)).await?,
+            zbus::MessageStream::for_match_rule(owner, &connection, Some(4)).await?,
+        ]);
+
+        // upowerd may not be running yet; its NameOwnerChanged will load the device then
+        let display = Arc::new(Mutex::new(load(&connection).await.unwrap_or_default()));
+        *DISPLAY.lock().unwrap() = Arc::downgrade(&display);
+        Ok(Watch { connection, messages, display })
+    }
+
+    /// Wait until something the battery module shows changes. False once the system
+    /// bus connection drops.
+    pub async fn changed(&mut self) -> bool {
+        while let Some(Ok(message)) = self.messages.next().await {
+            let before = self.display.lock().unwrap().summary();
+            if let Err(e) = self.apply(&message).await {
+                tracing::debug!("Ignoring UPower signal: {:#}", e);
+                continue;
+            }
+            if self.display.lock().unwrap().summary() != before {
+                return true;
+            }
+        }
+        false
+    }
+
+    async fn apply(&self, message: &zbus::Message) -> Result<()> {
+        let header = message.header();
+        match header.member().map(|m| m.as_str()).context("Signal without member")? {
+            "PropertiesChanged" => {
+                let (_interface, changed, _invalidated): (String, Properties, Vec<String>) =
+                    message.body().deserialize()?;
+                self.display.lock().unwrap().update(&changed);
+            }
+            "NameOwnerChanged" => {
+                let (_name, _old, new): (String, String, String) = message.body().deserialize()?;
+                // upowerd started (or restarted): read the device afresh
+                let display = if new.is_empty() { Display::default() } else { load(&self.connection).await? };
+                *self.display.lock().unwrap() = display;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Estimated time until the battery is empty (or full, while charging).
+/// UPower's estimate is preferred; otherwise it's derived from sysfs.
+pub fn time_remaining(battery: &Path, charging: bool) -> Option<Duration> {
+    upower_estimate(charging).or_else(|| sysfs_estimate(battery, charging))
+}
+
+/// "2 h 15 min", or just "45 min" under an hour
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{} min", m),
+        (h, 0) => format!("{} h", h),
+        (h, m) => format!("{} h {} min", h, m),
+    }
+}
+
+fn upower_estimate(charging: bool) -> Option<Duration> {
+    let display = display()?;
+    let seconds = if charging { display.time_to_full } else { display.time_to_empty };
+    // UPower reports 0 while it has no estimate yet
+    (seconds > 0).then(|| Duration::from_secs(seconds as u64))
+}
+
+/// Power going into or out of the battery in watts: UPower's rate, else power_now,
+/// else current_now times voltage_now for batteries that report charge
+pub fn power_draw(battery: &Path) -> Option<f64> {
+    if let Some(rate) = display().map(|d| d.energy_rate).filter(|r| *r > 0.0) {
+        return Some(rate);
+    }
+    // µW, or µA and µV
+    if let Some(power) = read_number(&battery.join("power_now")).filter(|p| *p > 0) {
+        return Some(power as f64 / 1e6);
+    }
+    let current = read_number(&battery.join("current_now")).filter(|c| *c > 0)?;
+    let voltage = read_number(&battery.join("voltage_now"))?;
+    Some(current as f64 * voltage as f64 / 1e12)
+}
+
+fn sysfs_estimate(battery: &Path, charging: bool) -> Option<Duration> {
+    // Batteries report either energy_* (µWh) with power_now (µW), or charge_* (µAh) with current_now (µA)
+    let (prefix, rate_file) = if battery.join("energy_now").exists() {
+        ("energy", "power_now")
+    } else {
+        ("charge", "current_now")
+    };
+    let now = read_number(&battery.join(format!("{}_now", prefix)))?;
+    let full = read_number(&battery.join(format!("{}_full", prefix)))?;
+
+    let rate = read_number(&battery.join(rate_file))
+        .filter(|r| *r > 0)
+        .map(|r| r as f64)
+        .or_else(|| sampled_rate(now, charging))?;
+
+    let remaining = if charging { full.saturating_sub(now) } else { now };
+    let hours = remaining as f64 / rate;
+    Some(Duration::from_secs((hours * 3600.0) as u64))
+}
+
+/// Rate of change per hour between readings, for batteries without power_now/current_now
+fn sampled_rate(level: u64, charging: bool) -> Option<f64> {
+    let mut sample = SAMPLE.lock().unwrap();
+    let Some(previous) = sample.as_mut().filter(|s| s.charging == charging) else {
+        *sample = Some(Sample { level, at: Instant::now(), charging, rate: None });
+        return None;
+    };
+
+    let elapsed = previous.at.elapsed();
+    if elapsed >= MIN_SAMPLE_AGE && level != previous.level {
+        let hours = elapsed.as_secs_f64() / 3600.0;
+        previous.rate = Some(level.abs_diff(previous.level) as f64 / hours);
+        if elapsed >= MAX_SAMPLE_AGE {
+            previous.level = level;
+            previous.at = Instant::now();
+        }
+    }
+    previous.rate
+}
+
+fn read_number(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
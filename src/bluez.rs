@@ -0,0 +1,254 @@
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, Weak};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+const BLUEZ: &str = "org.bluez";
+const BLUEZ_PATH: &str = "/org/bluez";
+const ADAPTER: &str = "org.bluez.Adapter1";
+const DEVICE: &str = "org.bluez.Device1";
+const BATTERY: &str = "org.bluez.Battery1";
+
+type Properties = HashMap<String, OwnedValue>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Adapter {
+    pub powered: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Device {
+    /// The user-visible alias, which is the device name unless renamed
+    pub name: String,
+    pub address: String,
+    pub connected: bool,
+    /// Battery percentage, for devices that report one
+    pub battery: Option<u8>,
+}
+
+/// BlueZ's adapters and devices, keyed by object path
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    adapters: BTreeMap<String, Adapter>,
+    devices: BTreeMap<String, Device>,
+}
+
+/// The running watcher's model. It belongs to the `Watch`, so once that is dropped
+/// (watchers aborted on reload) status reads query BlueZ directly again.
+static MODEL: Mutex<Weak<Mutex<Model>>> = Mutex::new(Weak::new());
+
+impl Model {
+    pub fn has_adapter(&self) -> bool {
+        !self.adapters.is_empty()
+    }
+
+    pub fn powered(&self) -> bool {
+        self.adapters.values().any(|a| a.powered)
+    }
+
+    pub fn connected(&self) -> Vec<&Device> {
+        self.devices.values().filter(|d| d.connected).collect()
+    }
+
+    /// What the bluetooth module shows, to tell real changes from RSSI and discovery chatter
+    fn summary(&self) -> (bool, bool, Vec<(String, Option<u8>)>) {
+        let connected = self.connected().iter().map(|d| (d.name.clone(), d.battery)).collect();
+        (self.has_adapter(), self.powered(), connected)
+    }
+
+    fn update(&mut self, path: &str, interface: &str, properties: &Properties) {
+        let get = |key: &str| properties.get(key);
+        match interface {
+            ADAPTER => {
+                let adapter = self.adapters.entry(path.to_string()).or_default();
+                if let Some(powered) = get("Powered").and_then(|v| v.downcast_ref::<bool>().ok()) {
+                    adapter.powered = powered;
+                }
+            }
+            DEVICE => {
+                let device = self.devices.entry(path.to_string()).or_default();
+                if let Some(alias) = get("Alias").and_then(|v| v.downcast_ref::<&str>().ok()) {
+                    device.name = alias.to_string();
+                }
+                if let Some(address) = get("Address").and_then(|v| v.downcast_ref::<&str>().ok()) {
+                    device.address = address.to_string();
+                }
+                if let Some(connected) = get("Connected").and_then(|v| v.downcast_ref::<bool>().ok()) {
+                    device.connected = connected;
+                }
+            }
+            BATTERY => {
+                let device = self.devices.entry(path.to_string()).or_default();
+                if let Some(percentage) = get("Percentage").and_then(|v| v.downcast_ref::<u8>().ok()) {
+                    device.battery = Some(percentage);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn remove(&mut self, path: &str, interfaces: &[String]) {
+        for interface in interfaces {
+            match interface.as_str() {
+                ADAPTER => {
+                    self.adapters.remove(path);
+                }
+                DEVICE => {
+                    self.devices.remove(path);
+                }
+                BATTERY => {
+                    if let Some(device) = self.devices.get_mut(path) {
+                        device.battery = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The current adapters and devices: the watcher's model, else a one-off query
+pub fn state() -> Result<Model> {
+    if let Some(model) = MODEL.lock().unwrap().upgrade() {
+        return Ok(model.lock().unwrap().clone());
+    }
+    let connection = zbus::blocking::Connection::system()?;
+    let objects = zbus::blocking::fdo::ObjectManagerProxy::builder(&connection)
+        .destination(BLUEZ)?
+        .path("/")?
+        .build()?
+        .get_managed_objects()?;
+    Ok(model_from(objects))
+}
+
+async fn load(connection: &zbus::Connection) -> Result<Model> {
+    let objects = zbus::fdo::ObjectManagerProxy::builder(connection)
+        .destination(BLUEZ)?
+        .path("/")?
+        .build()
+        .await?
+        .get_managed_objects()
+        .await?;
+    Ok(model_from(objects))
+}
+
+fn model_from(objects: zbus::fdo::ManagedObjects) -> Model {
+    let mut model = Model::default();
+    for (path, interfaces) in &objects {
+        for (interface, properties) in interfaces {
+            model.update(path.as_str(), interface.as_str(), properties);
+        }
+    }
+    model
+}
+
+/// Turn the first adapter's power "on", "off" or "toggle"
+pub fn set_powered(mode: &str) -> Result<()> {
+    let model = state()?;
+    let (path, adapter) = model.adapters.iter().next().context("No Bluetooth adapter")?;
+    let powered = match mode {
+        "on" => true,
+        "off" => false,
+        "toggle" => !adapter.powered,
+        _ => bail!("Unknown bluetooth mode {:?}, expected on, off or toggle", mode),
+    };
+    let connection = zbus::blocking::Connection::system()?;
+    zbus::blocking::Proxy::new(&connection, BLUEZ, path.as_str(), ADAPTER)?.set_property("Powered", powered)?;
+    Ok(())
+}
+
+/// BlueZ's signals, keeping a model current for as long as this is alive
+pub struct Watch {
+    connection: zbus::Connection,
+    messages: futures::stream::SelectAll<zbus::MessageStream>,
+    model: Arc<Mutex<Model>>,
+}
+
+impl Watch {
+    pub async fn start() -> Result<Watch> {
+        let connection = zbus::Connection::system().await?;
+        let properties = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace(BLUEZ_PATH)?
+            .build();
+        let objects = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus.ObjectManager")?
+            .path("/")?
+            .build();
+        let owner = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus")?
+            .member("NameOwnerChanged")?
+            .arg(0, BLUEZ)?
+            .build();
+        let messages = futures::stream::select_all([
+            zbus::MessageStream::for_match_rule(properties, &connection, Some(64)).await?,
+            zbus::MessageStream::for_match_rule(objects, &connection, Some(16)).await?,
+            zbus::MessageStream::for_match_rule(owner, &connection, Some(4)).await?,
+        ]);
+
+        // bluetoothd may not be running yet; its NameOwnerChanged will load the model then
+        let model = Arc::new(Mutex::new(load(&connection).await.unwrap_or_default()));
+        *MODEL.lock().unwrap() = Arc::downgrade(&model);
+        Ok(Watch { connection, messages, model })
+    }
+
+    /// Wait until something the bluetooth module shows changes. False once the
+    /// system bus connection drops.
+    pub async fn changed(&mut self) -> bool {
+        while let Some(Ok(message)) = self.messages.next().await {
+            let before = self.model.lock().unwrap().summary();
+            if let Err(e) = self.apply(&message).await {
+                tracing::debug!("Ignoring BlueZ signal: {:#}", e);
+                continue;
+            }
+            if self.model.lock().unwrap().summary() != before {
+                return true;
+            }
+        }
+        false
+    }
+
+    async fn apply(&self, message: &zbus::Message) -> Result<()> {
+        let header = message.header();
+        let member = header.member().map(|m| m.as_str()).unwrap_or("");
+        match member {
+            "PropertiesChanged" => {
+                let path = header.path().context("Signal without path")?.to_string();
+                let (interface, changed, _invalidated): (String, Properties, Vec<String>) =
+                    message.body().deserialize()?;
+                self.model.lock().unwrap().update(&path, &interface, &changed);
+            }
+            "InterfacesAdded" => {
+                let (path, interfaces): (OwnedObjectPath, HashMap<String, Properties>) =
+                    message.body().deserialize()?;
+                if !path.as_str().starts_with(BLUEZ_PATH) {
+                    return Ok(()); // another service's object manager at "/"
+                }
+                let mut model = self.model.lock().unwrap();
+                for (interface, properties) in &interfaces {
+                    model.update(path.as_str(), interface, properties);
+                }
+            }
+            "InterfacesRemoved" => {
+                let (path, interfaces): (OwnedObjectPath, Vec<String>) = message.body().deserialize()?;
+                if !path.as_str().starts_with(BLUEZ_PATH) {
+                    return Ok(());
+                }
+                self.model.lock().unwrap().remove(path.as_str(), &interfaces);
+            }
+            "NameOwnerChanged" => {
+                let (_name, _old, new): (String, String, String) = message.body().deserialize()?;
+                // bluetoothd started (or restarted): read everything afresh
+                let model = if new.is_empty() { Model::default() } else { load(&self.connection).await? };
+                *self.model.lock().unwrap() = model;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
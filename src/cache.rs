@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Most recent status JSON per module, and when it was computed
+static CACHE: OnceLock<Mutex<HashMap<String, (Instant, String)>>> = OnceLock::new();
+
+fn cache() -> &'static Mutex<HashMap<String, (Instant, String)>> {
+    CACHE.get_or_init(Default::default)
+}
+
+/// Record a freshly computed status
+pub fn store(module: &str, json: &str) {
+    cache()
+        .lock()
+        .unwrap()
+        .insert(module.to_string(), (Instant::now(), json.to_string()));
+}
+
+/// A module's cached status, if it is younger than `max_age`
+pub fn get(module: &str, max_age: Duration) -> Option<String> {
+    let cache = cache().lock().unwrap();
+    let (at, json) = cache.get(module)?;
+    (at.elapsed() < max_age).then(|| json.clone())
+}
+
+/// Keep the cache up to date with every status the watchers broadcast
+pub async fn run(status_tx: broadcast::Sender<(String, String)>) {
+    let mut rx = status_tx.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok((module, json)) => store(&module, &json),
+            Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Lenovo's ideapad driver exposes conservation mode per platform device
+const IDEAPAD_DIR: &str = "/sys/bus/platform/drivers/ideapad_acpi";
+
+/// A vendor knob that stops charging short of full to spare the battery
+pub enum ChargeLimit {
+    /// Lenovo `conservation_mode`: on (stop around 60-80%, firmware decides) or off
+    Conservation(PathBuf),
+    /// `charge_control_end_threshold` (ASUS, ThinkPad, ...): stop at this percentage
+    Threshold(PathBuf),
+}
+
+impl ChargeLimit {
+    /// The charge limit available for a battery, if the hardware has one
+    pub fn find(battery: &Path) -> Option<Self> {
+        let threshold = battery.join("charge_control_end_threshold");
+        if threshold.exists() {
+            return Some(Self::Threshold(threshold));
+        }
+        std::fs::read_dir(IDEAPAD_DIR)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path().join("conservation_mode"))
+            .find(|p| p.exists())
+            .map(Self::Conservation)
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Self::Conservation(path) | Self::Threshold(path) => path,
+        }
+    }
+
+    fn read(&self) -> Option<u32> {
+        std::fs::read_to_string(self.path()).ok()?.trim().parse().ok()
+    }
+
+    /// Whether charging currently stops short of full
+    pub fn is_active(&self) -> bool {
+        match self {
+            Self::Conservation(_) => self.read() == Some(1),
+            Self::Threshold(_) => self.read().is_some_and(|t| t < 100),
+        }
+    }
+
+    /// One line for the battery tooltip
+    pub fn describe(&self) -> String {
+        match (self, self.read()) {
+            (Self::Conservation(_), _) => {
+                format!("Conservation mode: {}", if self.is_active() { "on" } else { "off" })
+            }
+            (Self::Threshold(_), Some(t)) if t < 100 => format!("Charge limit: {}%", t),
+            (Self::Threshold(_), _) => "Charge limit: off".to_string(),
+        }
+    }
+
+    /// Limit charging (to `threshold` percent where the hardware takes one) or charge to full
+    pub fn set(&self, active: bool, threshold: u32) -> Result<()> {
+        let value = match self {
+            Self::Conservation(_) => u32::from(active),
+            Self::Threshold(_) if active => threshold.clamp(1, 100),
+            Self::Threshold(_) => 100,
+        };
+        std::fs::write(self.path(), value.to_string()).with_context(|| {
+            format!(
+                "Failed to write {} (it needs a udev rule or group making it writable)",
+                self.path().display()
+            )
+        })
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Build a process from a command template.
+///
+/// The template is split into words the way a shell would (quotes, backslashes), then
+/// `{name}` placeholders are substituted inside each word, so a value containing spaces
+/// or quotes always stays a single argument. With `shell` the template runs under
+/// `sh -c` instead (pipes, `&&`, variables), with substituted values single-quoted.
+/// Templates written before `shell` existed that use shell syntax still run under
+/// `sh -c`, with a warning.
+pub fn build(template: &str, vars: &[(&str, &str)], shell: bool) -> Result<Command> {
+    if shell || needs_shell(template) {
+        if !shell {
+            warn_legacy_shell(template);
+        }
+        let script = substitute(&shellexpand::tilde(template), |key| lookup(vars, key).map(quote));
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", &script]);
+        return Ok(cmd);
+    }
+
+    direct(template, vars)
+}
+
+/// Like `build`, but never through a shell, so the command is the process itself
+pub fn direct(template: &str, vars: &[(&str, &str)]) -> Result<Command> {
+    let argv = argv(template, vars)?;
+    let (program, args) = argv
+        .split_first()
+        .with_context(|| format!("Empty command: {:?}", template))?;
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    Ok(cmd)
+}
+
+/// Split a command template into words and substitute placeholders in each
+pub fn argv(template: &str, vars: &[(&str, &str)]) -> Result<Vec<String>> {
+    let words = shell_words::split(template)
+        .with_context(|| format!("Unbalanced quotes in command: {:?}", template))?;
+    Ok(words
+        .into_iter()
+        .map(|word| substitute(&shellexpand::tilde(&word), |key| lookup(vars, key).map(str::to_string)))
+        .collect())
+}
+
+fn lookup<'a>(vars: &[(&str, &'a str)], key: &str) -> Option<&'a str> {
+    vars.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+/// Replace `{name}` placeholders in one pass, so text coming from a value is never
+/// scanned for placeholders again. Names `value` has nothing for are left as they are.
+pub fn substitute(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    placeholder()
+        .replace_all(template, |caps: &Captures| value(&caps[1]).unwrap_or_else(|| caps[0].to_string()))
+        .into_owned()
+}
+
+/// Matches a `{name}` placeholder, capturing the name
+pub fn placeholder() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{(\w+)\}").unwrap())
+}
+
+/// Whether a template uses shell syntax outside quotes (`|`, `&&`, `;`, redirections,
+/// `$` expansions, subshells), which splitting it into arguments can't honour
+fn needs_shell(template: &str) -> bool {
+    let mut chars = template.chars();
+    let mut double = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            // Nothing is special up to the closing quote
+            '\'' if !double && !chars.by_ref().any(|c| c == '\'') => return false,
+            '"' => double = !double,
+            '$' | '`' => return true,
+            '|' | '&' | ';' | '<' | '>' | '(' | ')' | '\n' if !double => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Templates already warned about, so each is reported once
+static WARNED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn warn_legacy_shell(template: &str) {
+    let mut warned = WARNED.lock().unwrap();
+    if !warned.iter().any(|t| t == template) {
+        tracing::warn!("Running {:?} under sh -c because it uses shell syntax; set shell = true for it", template);
+        warned.push(template.to_string());
+    }
+}
+
+/// Whether a program is on PATH
+pub fn installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Quote a value as a single `sh` word
+pub fn quote(value: &str) -> String {
+    shell_words::quote(value).into_owned()
+}
+
+/// Timeout in ms for external calls when neither the caller nor the module sets one
+static DEFAULT_TIMEOUT_MS: AtomicU64 = AtomicU64::new(2000);
+
+thread_local! {
+    /// Timeout override for calls made on this thread (see `with_timeout`)
+    static TIMEOUT: Cell<Option<Duration>> = const { Cell::new(None) };
+    /// Whether a call on this thread has timed out since `with_timeout` started
+    static TIMED_OUT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Set the default timeout for external calls (`daemon.command_timeout_ms`)
+pub fn init(default_ms: u64) {
+    DEFAULT_TIMEOUT_MS.store(default_ms, Ordering::Relaxed);
+}
+
+/// Timeout for a call made now on this thread, for clients that take one (e.g. HTTP)
+pub fn current_timeout() -> Duration {
+    TIMEOUT
+        .get()
+        .unwrap_or_else(|| Duration::from_millis(DEFAULT_TIMEOUT_MS.load(Ordering::Relaxed)))
+}
+
+/// Run `f` with calls on this thread limited to `timeout` (the default if `None`).
+/// Also returns whether any call timed out.
+pub fn with_timeout<T>(timeout: Option<Duration>, f: impl FnOnce() -> T) -> (T, bool) {
+    let previous = TIMEOUT.replace(timeout);
+    let previous_timed_out = TIMED_OUT.replace(false);
+    let result = f();
+    let timed_out = TIMED_OUT.replace(previous_timed_out);
+    TIMEOUT.set(previous);
+    (result, timed_out)
+}
+
+/// How often `output` checks whether the process has exited
+const WAIT_STEP: Duration = Duration::from_millis(5);
+
+/// Like `Command::output`, but kills the process and everything it started, and
+/// fails with `TimedOut`, if it doesn't finish within the current timeout
+pub fn output(cmd: &mut Command) -> io::Result<Output> {
+    let timeout = current_timeout();
+    let deadline = Instant::now() + timeout;
+    // In a process group of its own, so a timeout can take its children down too
+    let mut child = cmd
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain the pipes on helper threads so a chatty process can't fill them and stall
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            break None;
+        }
+        std::thread::sleep(WAIT_STEP);
+    };
+    // Something it started may still hold the pipes open
+    let output = status.and_then(|status| {
+        let remaining = || deadline.saturating_duration_since(Instant::now());
+        Some(Output {
+            status,
+            stdout: stdout.recv_timeout(remaining()).ok()?,
+            stderr: stderr.recv_timeout(remaining()).ok()?,
+        })
+    });
+
+    match output {
+        Some(output) => Ok(output),
+        None => {
+            // Kill the whole group, so whatever still holds the pipes goes too and the
+            // drain threads finish. The group's id stays taken while any member lives.
+            unsafe {
+                libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+            }
+            // Reap it now rather than leaving a zombie for the daemon's lifetime
+            let _ = child.kill();
+            let _ = child.wait();
+            TIMED_OUT.set(true);
+            let program = cmd.get_program().to_string_lossy().into_owned();
+            tracing::warn!("{} timed out after {:?}", program, timeout);
+            Err(io::Error::new(io::ErrorKind::TimedOut, format!("{} timed out", program)))
+        }
+    }
+}
+
+/// Read a pipe to its end on a helper thread
+fn drain(pipe: Option<impl Read + Send + 'static>) -> mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut data = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut data);
+        }
+        let _ = tx.send(data);
+    });
+    rx
+}
+
+/// Like `Command::status`, with the same timeout as `output`
+pub fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    output(cmd).map(|o| o.status)
+}
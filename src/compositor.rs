@@ -0,0 +1,90 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::config::Config;
+use crate::geometry::Geometry;
+use crate::hypr::Hyprland;
+use crate::sway::Sway;
+use crate::wlr::Wlroots;
+
+/// A toplevel window as the compositor reports it
+#[derive(Debug, Clone)]
+pub struct Window {
+    /// Compositor handle: a Hyprland address ("0x55d3...") or a Sway container id
+    pub id: String,
+    pub pid: i32,
+    pub title: String,
+    /// Window class, or app_id for native Wayland clients on Sway
+    pub class: String,
+    pub geometry: Option<Geometry>,
+    /// Whether the window has keyboard focus
+    pub focused: bool,
+}
+
+/// An output and its logical (scaled, rotated) area in layout coordinates
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    /// Output name, e.g. "DP-1"
+    pub name: String,
+    pub geometry: Geometry,
+    pub focused: bool,
+}
+
+/// What the menu manager needs from the compositor: finding, placing and
+/// closing menu windows, and knowing where the cursor and focus are
+#[async_trait]
+pub trait Compositor: Send + Sync {
+    /// All toplevel windows
+    async fn windows(&self) -> Result<Vec<Window>>;
+
+    /// Whether the focused window is fullscreen
+    async fn fullscreen_focused(&self) -> Result<bool>;
+
+    /// Cursor position in layout coordinates
+    async fn cursor_position(&self) -> Result<(i32, i32)>;
+
+    /// Whether `cursor_position` is the real pointer position rather than a guess
+    fn reports_cursor(&self) -> bool {
+        true
+    }
+
+    /// All active monitors
+    async fn monitors(&self) -> Result<Vec<Monitor>>;
+
+    /// Float a window at this position and size
+    async fn place_window(&self, id: &str, geometry: Geometry) -> Result<()>;
+
+    /// Move windows by an offset in pixels
+    async fn move_windows(&self, ids: &[String], dx: i32, dy: i32) -> Result<()>;
+
+    /// Set window props as (prop, value) pairs in Hyprland's naming, e.g. ("alpha", "0.90").
+    /// Props the compositor has no equivalent for are skipped.
+    async fn set_props(&self, id: &str, props: &[(&str, String)]) -> Result<()>;
+
+    /// Run a module's `window_props` entries for its menu window
+    async fn run_window_commands(&self, id: &str, commands: &[String]) -> Result<()>;
+
+    /// Ask a window to close
+    async fn close_window(&self, id: &str) -> Result<()>;
+
+    /// Move the pointer a pixel and back, so the bar notices it again
+    async fn jiggle_pointer(&self) -> Result<()>;
+
+    /// Ids of windows as they gain focus (empty when nothing has focus).
+    /// The channel closes when the connection to the compositor drops.
+    async fn focus_changes(&self) -> Result<mpsc::Receiver<String>>;
+}
+
+/// The backend named by `daemon.compositor`
+pub fn connect(config: &Config) -> Box<dyn Compositor> {
+    match config.daemon.compositor.as_str() {
+        "sway" | "i3" => Box::new(Sway),
+        "hyprland" => Box::new(Hyprland),
+        "wlroots" => Box::new(Wlroots::new()),
+        other => {
+            tracing::warn!("Unknown compositor {:?}, using Hyprland", other);
+            Box::new(Hyprland)
+        }
+    }
+}
@@ -1,14 +1,266 @@
 use anyhow::{Context, Result};
+use regex::Regex;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::geometry::Geometry;
+use crate::updates;
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub daemon: DaemonConfig,
     #[serde(default)]
     pub modules: HashMap<String, ModuleConfig>,
+    #[serde(default)]
+    pub osd: OsdConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub i3bar: I3barConfig,
+    /// Named HTTP requests, used by `webhook:<name>` actions
+    #[serde(default)]
+    pub webhooks: HashMap<String, WebhookConfig>,
+    /// Widgets that show one of several modules at a time
+    #[serde(default)]
+    pub groups: HashMap<String, GroupConfig>,
+    /// Bars the widgets live on, when there is more than one
+    #[serde(default)]
+    pub bars: HashMap<String, BarConfig>,
+    /// Overrides that apply while the machine is in a given state (docked, lid closed, ...)
+    #[serde(default)]
+    pub profiles: HashMap<String, ProfileConfig>,
+    /// Glyphs the built-in modules show
+    #[serde(default)]
+    pub icons: IconsConfig,
+}
+
+/// Icon set: a preset, with single roles replaced
+#[derive(Debug, Clone, Deserialize)]
+pub struct IconsConfig {
+    /// "fontawesome", "nerdfont", "emoji" or "ascii"
+    #[serde(default = "default_icon_preset")]
+    pub preset: String,
+    /// Glyph per role, e.g. `volume-high = "🔊"`
+    #[serde(flatten)]
+    pub overrides: HashMap<String, String>,
+}
+
+impl Default for IconsConfig {
+    fn default() -> Self {
+        Self {
+            preset: default_icon_preset(),
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+fn default_icon_preset() -> String {
+    "fontawesome".to_string()
+}
+
+/// Settings that change while the machine matches some conditions
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileConfig {
+    /// Conditions that must all hold: "lid-closed", "ac", "external-monitor", "docked"
+    /// (prefix "!" to negate)
+    #[serde(default)]
+    pub when: Vec<String>,
+    /// Modules hidden while the profile is active
+    #[serde(default)]
+    pub disable: Vec<String>,
+    /// Overrides `daemon.waybar_height`
+    pub waybar_height: Option<u32>,
+}
+
+/// A bar along one screen edge
+#[derive(Debug, Clone, Deserialize)]
+pub struct BarConfig {
+    /// Screen edge: "top", "bottom", "left" or "right"
+    #[serde(default = "default_bar_position")]
+    pub position: String,
+    /// Thickness in pixels: height for top and bottom bars, width for side bars
+    #[serde(default = "default_waybar_height")]
+    pub height: u32,
+    /// Modules on this bar, for events that don't name one
+    #[serde(default)]
+    pub modules: Vec<String>,
+    /// Outputs the bar is shown on, e.g. ["DP-1"]; empty for every output
+    #[serde(default)]
+    pub outputs: Vec<String>,
+}
+
+fn default_bar_position() -> String {
+    "top".to_string()
+}
+
+impl BarConfig {
+    /// Whether the bar is shown on this output
+    pub fn is_on(&self, output: &str) -> bool {
+        self.outputs.is_empty() || self.outputs.iter().any(|o| o == output)
+    }
+
+    pub fn is_bottom(&self) -> bool {
+        self.position == "bottom"
+    }
+
+    /// Whether the bar runs down the left or right edge
+    pub fn is_vertical(&self) -> bool {
+        matches!(self.position.as_str(), "left" | "right")
+    }
+
+    /// Unit step from the middle of the screen toward the bar's edge
+    pub fn edge_direction(&self) -> (i32, i32) {
+        match self.position.as_str() {
+            "bottom" => (0, 1),
+            "left" => (-1, 0),
+            "right" => (1, 0),
+            _ => (0, -1),
+        }
+    }
+
+    /// Whether a point is over this bar on the given monitor
+    pub fn contains(&self, monitor: Geometry, x: i32, y: i32) -> bool {
+        let (left, right) = (monitor.x, monitor.x + monitor.width as i32);
+        let (top, bottom) = (monitor.y, monitor.y + monitor.height as i32);
+        let thickness = self.height as i32;
+        let across = x >= left && x < right;
+        let down = y >= top && y < bottom;
+        match self.position.as_str() {
+            "bottom" => y >= bottom - thickness && y <= bottom && across,
+            "left" => x >= left && x <= left + thickness && down,
+            "right" => x >= right - thickness && x <= right && down,
+            _ => y >= top && y <= top + thickness && across,
+        }
+    }
+
+    /// Top edge of a window `height` tall placed against this (top or bottom) bar, `margin` away from it
+    pub fn window_y(&self, monitor: Geometry, height: u32, margin: i32) -> i32 {
+        if self.is_bottom() {
+            monitor.y + monitor.height as i32 - self.height as i32 - margin - height as i32
+        } else {
+            monitor.y + self.height as i32 + margin
+        }
+    }
+
+    /// Left edge of a window `width` wide placed against this (side) bar, `margin` away from it
+    pub fn window_x(&self, monitor: Geometry, width: u32, margin: i32) -> i32 {
+        if self.position == "right" {
+            monitor.x + monitor.width as i32 - self.height as i32 - margin - width as i32
+        } else {
+            monitor.x + self.height as i32 + margin
+        }
+    }
+}
+
+/// Several modules sharing one widget
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupConfig {
+    /// Members, in cycling order
+    pub modules: Vec<String>,
+    /// What cycles to the next member: "scroll" or "click"
+    #[serde(default = "default_cycle_on")]
+    pub cycle_on: String,
+}
+
+fn default_cycle_on() -> String {
+    "scroll".to_string()
+}
+
+/// Settings for `--i3bar` mode (swaybar/i3bar status_command)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct I3barConfig {
+    /// Modules to show, left to right. Defaults to all enabled modules, alphabetically.
+    #[serde(default)]
+    pub modules: Vec<String>,
+}
+
+/// Read-only HTTP status endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_http_listen")]
+    pub listen: String,
+    /// Name of the secret holding the bearer token. Unauthenticated if unset.
+    pub token_secret: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_http_listen(),
+            token_secret: None,
+        }
+    }
+}
+
+fn default_http_listen() -> String {
+    "127.0.0.1:8731".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default = "default_webhook_method")]
+    pub method: String,
+    pub url: String,
+    /// Header values may reference `{secret:name}`
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// JSON body template; module fields are substituted (JSON-escaped)
+    pub body: Option<String>,
+    #[serde(default = "default_webhook_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_webhook_method() -> String {
+    "POST".to_string()
+}
+
+fn default_webhook_timeout_secs() -> u64 {
+    10
+}
+
+/// On-screen display shown when volume/brightness change
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsdConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// How long the OSD stays visible
+    #[serde(default = "default_osd_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Where the overlay appears ("bottom-center", "top-right", ...), and `{position}` for a command
+    #[serde(default = "default_osd_position")]
+    pub position: String,
+    /// Command to run instead of drawing the overlay. `{kind}`, `{value}`, `{icon}`,
+    /// `{timeout}` and `{position}` are substituted.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Run the command under `sh -c` instead of splitting it into arguments
+    #[serde(default)]
+    pub shell: bool,
+}
+
+impl Default for OsdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: default_osd_timeout_ms(),
+            position: default_osd_position(),
+            command: None,
+            shell: false,
+        }
+    }
+}
+
+fn default_osd_timeout_ms() -> u64 {
+    1500
+}
+
+fn default_osd_position() -> String {
+    "bottom-center".to_string()
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,11 +269,68 @@ pub struct DaemonConfig {
     pub terminal_cmd: String,
     #[serde(default = "default_waybar_height")]
     pub waybar_height: u32,
+    /// Screen edge waybar is on: "top", "bottom", "left" or "right". Used when no `[bars]` are configured.
+    #[serde(default = "default_bar_position")]
+    pub bar_position: String,
     #[serde(default = "default_socket_path")]
     pub socket_path: String,
     /// Global toggle for hover-to-open behavior. When false, menus only open/close via click.
     #[serde(default)]
     pub hover: bool,
+    /// Launcher template for daemon-generated menus (power, confirm prompts).
+    /// `{prompt}` is substituted; entries are written to stdin, the choice is read from stdout.
+    #[serde(default = "default_launcher_cmd")]
+    pub launcher_cmd: String,
+    /// Ignore hover/click opens while the focused window is fullscreen
+    #[serde(default)]
+    pub suppress_fullscreen: bool,
+    /// Close the open, unpinned menu when another window gets focus
+    #[serde(default)]
+    pub close_on_focus_loss: bool,
+    /// Daily do-not-disturb window, e.g. "22:00-07:00"
+    pub dnd_schedule: Option<String>,
+    /// A press held at least this long before `release` counts as a long-press
+    #[serde(default = "default_long_press_ms")]
+    pub long_press_ms: u64,
+    /// App-mode browser template for `kind = "web"` menus.
+    /// `{url}`, `{class}` and `{profile}` (a per-module profile directory) are substituted.
+    #[serde(default = "default_browser_cmd")]
+    pub browser_cmd: String,
+    /// Run command templates under `sh -c` instead of splitting them into arguments
+    #[serde(default)]
+    pub shell: bool,
+    /// How long an external tool (wpctl, nmcli, ...) may run before it is killed
+    #[serde(default = "default_command_timeout_ms")]
+    pub command_timeout_ms: u64,
+    /// `status`/`follow` queries reuse a module's last status while it is younger than this (0 disables)
+    #[serde(default = "default_status_max_age_ms")]
+    pub status_max_age_ms: u64,
+    /// How many module statuses bulk queries (`status all`, startup, profile switches) compute at once
+    #[serde(default = "default_status_concurrency")]
+    pub status_concurrency: usize,
+    /// Write each module's latest status to `$XDG_RUNTIME_DIR/waybar-hovermenu/status/<module>.json`
+    #[serde(default)]
+    pub export_status: bool,
+    /// Pause polled modules while the session is idle or locked (logind `IdleHint`/`LockedHint`)
+    #[serde(default = "default_true")]
+    pub pause_when_idle: bool,
+    /// Slack around menu windows, in pixels, for the safe zone after touch input
+    #[serde(default = "default_touch_buffer")]
+    pub touch_buffer: i32,
+    /// Window manager the menus are placed with: "hyprland", "sway" or "wlroots"
+    #[serde(default = "default_compositor")]
+    pub compositor: String,
+    /// Speech command for accessibility announcements, e.g. "spd-say -- {message}"
+    pub announce_cmd: Option<String>,
+    /// After a leave, how long the cursor has to stay off the bar and menu before the menu closes
+    #[serde(default = "default_leave_grace_ms")]
+    pub leave_grace_ms: u64,
+    /// How often an open hover menu checks where the cursor is
+    #[serde(default = "default_watcher_poll_ms")]
+    pub watcher_poll_ms: u64,
+    /// How long the cursor may be away from the bar and an open hover menu before it closes
+    #[serde(default = "default_watcher_close_after_ms")]
+    pub watcher_close_after_ms: u64,
 }
 
 impl Default for DaemonConfig {
@@ -29,12 +338,63 @@ impl Default for DaemonConfig {
         Self {
             terminal_cmd: default_terminal_cmd(),
             waybar_height: default_waybar_height(),
+            bar_position: default_bar_position(),
             socket_path: default_socket_path(),
             hover: false,
+            launcher_cmd: default_launcher_cmd(),
+            suppress_fullscreen: false,
+            close_on_focus_loss: false,
+            dnd_schedule: None,
+            long_press_ms: default_long_press_ms(),
+            browser_cmd: default_browser_cmd(),
+            shell: false,
+            command_timeout_ms: default_command_timeout_ms(),
+            status_max_age_ms: default_status_max_age_ms(),
+            status_concurrency: default_status_concurrency(),
+            export_status: false,
+            pause_when_idle: true,
+            touch_buffer: default_touch_buffer(),
+            compositor: default_compositor(),
+            announce_cmd: None,
+            leave_grace_ms: default_leave_grace_ms(),
+            watcher_poll_ms: default_watcher_poll_ms(),
+            watcher_close_after_ms: default_watcher_close_after_ms(),
         }
     }
 }
 
+fn default_leave_grace_ms() -> u64 {
+    300
+}
+
+fn default_watcher_poll_ms() -> u64 {
+    100
+}
+
+fn default_watcher_close_after_ms() -> u64 {
+    500
+}
+
+fn default_compositor() -> String {
+    "hyprland".to_string()
+}
+
+fn default_touch_buffer() -> i32 {
+    40
+}
+
+fn default_command_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_status_max_age_ms() -> u64 {
+    5000
+}
+
+fn default_status_concurrency() -> usize {
+    4
+}
+
 fn default_terminal_cmd() -> String {
     "foot -T {title} {command}".to_string()
 }
@@ -43,6 +403,18 @@ fn default_waybar_height() -> u32 {
     32
 }
 
+fn default_launcher_cmd() -> String {
+    "fuzzel --dmenu --prompt '{prompt} '".to_string()
+}
+
+fn default_long_press_ms() -> u64 {
+    500
+}
+
+fn default_browser_cmd() -> String {
+    "chromium --user-data-dir={profile} --class={class} --app={url}".to_string()
+}
+
 fn default_socket_path() -> String {
     "/tmp/waybar-hovermenu.sock".to_string()
 }
@@ -52,7 +424,7 @@ pub struct ModuleConfig {
     #[serde(default = "default_true")]
     pub enabled: bool,
 
-    /// Menu type: "tui" or "gui"
+    /// Menu type: "tui", "gui", "web", "power" or "notification-center"
     #[serde(default = "default_kind")]
     pub kind: String,
 
@@ -62,22 +434,530 @@ pub struct ModuleConfig {
     /// Window class for GUI apps (e.g., "localsend")
     pub window_class: Option<String>,
 
-    /// Window size [width, height]
+    /// Per-module override of `daemon.shell` for this module's command and actions
+    pub shell: Option<bool>,
+
+    /// Per-module override of `daemon.command_timeout_ms` for status reads
+    pub timeout_ms: Option<u64>,
+
+    /// Page opened by a "web" menu
+    pub url: Option<String>,
+
+    /// Match the menu window by title instead of the `WAYBAR-MENU:` prefix or window class
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub match_title_regex: Option<Regex>,
+
+    /// Match the menu window by a class pattern instead of the exact `window_class`
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub match_class_regex: Option<Regex>,
+
+    /// Window size [width, height], in pixels or as a percentage of the monitor ("40%")
     #[serde(default = "default_size")]
-    pub size: [u32; 2],
+    pub size: [Dimension; 2],
 
-    /// Position: "top-right" or "top-left"
+    /// Position: "top-right", "top-left", "top-center" or "widget" (under the widget it opened from)
     #[serde(default = "default_position")]
     pub position: String,
 
+    /// Gap in pixels between the menu and the bar and screen edge
+    #[serde(default)]
+    pub margin: u32,
+
+    /// Extra [x, y] shift in pixels applied after positioning
+    #[serde(default)]
+    pub offset: [i32; 2],
+
     /// Right-click quick action command
     pub action: Option<String>,
 
+    /// Ask for confirmation before running `action`, `middle_action` or `long_press_action`
+    #[serde(default)]
+    pub confirm_actions: bool,
+
+    /// Question shown when confirming an action (default "Run action?")
+    pub confirm_message: Option<String>,
+
+    /// Ignore an action of this module while the same one is running or for this long after
+    /// it started; 0 turns the locking off
+    #[serde(default = "default_action_cooldown_ms")]
+    pub action_cooldown_ms: u64,
+
+    /// Percentage `charge-limit:` actions stop charging at, where the hardware takes a threshold
+    #[serde(default = "default_charge_limit")]
+    pub charge_limit: u32,
+
+    /// Widget text template, e.g. "{icon} {percent}%". Placeholders are the status's
+    /// fields (`{icon}`, `{percent}`, `{ssid}`, `{device}`, ...), `{text}` and `{tooltip}`.
+    pub format: Option<String>,
+
+    /// How the `network` module shows Wi-Fi signal quality: "bars", "percent" or "none"
+    #[serde(default = "default_signal_format")]
+    pub signal_format: String,
+
+    /// Command printing the `audio` volume, replacing the built-in pactl/wpctl reading
+    pub volume_command: Option<String>,
+
+    /// Name screen reader announcements use, e.g. "Audio mixer" (default: the module name)
+    pub label: Option<String>,
+
+    /// SSID for `hotspot:` actions (may use `{secret:name}`)
+    pub hotspot_ssid: Option<String>,
+    /// Password for `hotspot:` actions, usually `{secret:name}`
+    pub hotspot_password: Option<String>,
+
+    /// Send a desktop notification (with the command's stderr) when an action fails
+    #[serde(default)]
+    pub notify_on_failure: bool,
+
+    /// Middle-click action, same syntax as `action`
+    pub middle_action: Option<String>,
+
+    /// Long-press action, same syntax as `action`
+    pub long_press_action: Option<String>,
+
+    /// Named right-click actions. When set, right-click shows a context popup to pick one.
+    #[serde(default)]
+    pub actions: Vec<NamedAction>,
+
+    /// Value at which the `warning` class is added (CPU %, battery %, unread count, ...)
+    pub warning: Option<f64>,
+
+    /// Value at which the `critical` class is added
+    pub critical: Option<f64>,
+
+    /// Status field compared against the thresholds (defaults per module, e.g. `usage` for cpu)
+    pub threshold_field: Option<String>,
+
+    /// Whether lower values are worse. Inferred when unset: true if critical < warning, or for battery and network.
+    pub threshold_below: Option<bool>,
+
+    /// Add the `urgent` class while the module needs attention (new mail, failed units,
+    /// `critical` threshold), until the condition resolves or the menu is opened
+    #[serde(default)]
+    pub urgent: bool,
+
+    /// Send a desktop notification when the module becomes urgent
+    #[serde(default)]
+    pub urgent_notify: bool,
+
+    /// Hide the widget when the hardware it reports on (bluetooth adapter, backlight) is absent
+    #[serde(default = "default_true")]
+    pub hide_without_hardware: bool,
+
+    /// Hide the widget (emit empty text) when this holds: "empty", a comparison like
+    /// "unread == 0" / "volume < 5", or "cmd:<shell command>" (hidden when it exits 0)
+    pub hide_when: Option<String>,
+
+    /// Classes that make the widget blink while present, e.g. ["critical", "urgent"]
+    #[serde(default)]
+    pub blink_on: Vec<String>,
+
+    /// Blink period in milliseconds (at least 250)
+    #[serde(default = "default_blink_interval_ms")]
+    pub blink_interval_ms: u64,
+
+    /// Text shown during the "off" phase of a blink. If unset, only a `blink` class is toggled.
+    pub blink_text: Option<String>,
+
+    /// Percentage changed per scroll event (audio, brightness)
+    #[serde(default = "default_scroll_step")]
+    pub scroll_step: u32,
+
+    /// Lower bound for scroll adjustments, in percent
+    #[serde(default)]
+    pub scroll_min: u32,
+
+    /// Upper bound for scroll adjustments, in percent
+    #[serde(default = "default_scroll_max")]
+    pub scroll_max: u32,
+
     /// Poll interval in seconds (for modules that poll)
     pub poll_interval: Option<u64>,
 
+    /// Command whose output is the module's status: waybar JSON, or plain text lines
+    /// (text, tooltip, class). Makes any `[modules.<name>]` a custom module.
+    pub status_command: Option<String>,
+
+    /// Long-running command for a `status_command` module; each line it prints triggers a refresh
+    pub watch_command: Option<String>,
+
     /// Watch directory (for mail module)
     pub watch_dir: Option<String>,
+
+    /// hwmon sensor the `temperature` module reads: a chip name ("k10temp"), a chip and
+    /// input label ("coretemp:Package id 0"), or a path to a `temp*_input` file
+    pub sensor: Option<String>,
+
+    /// Where the `weather` module reports on: "lat,lon" or a place name
+    pub location: Option<String>,
+
+    /// Weather source: "open-meteo" or "wttr"
+    #[serde(default = "default_weather_provider")]
+    pub weather_provider: String,
+
+    /// "metric" (°C, km/h) or "imperial" (°F, mph), for the weather module
+    #[serde(default = "default_units")]
+    pub units: String,
+
+    /// Notification daemon the `notifications` module talks to: "mako", "dunst" or "swaync".
+    /// Unset means whichever is running.
+    pub notification_daemon: Option<String>,
+
+    /// What the `idle` module's inhibitor blocks, in logind's terms: "idle", or "idle:sleep"
+    /// to keep the machine from suspending too
+    #[serde(default = "default_inhibit")]
+    pub inhibit: String,
+
+    /// Gamma tool the `nightlight` module runs, e.g. "wlsunset -l 52.5 -L 13.4".
+    /// Unset means the first of hyprsunset, wlsunset and gammastep that is installed.
+    pub nightlight_command: Option<String>,
+
+    /// Have the daemon own the night light process (stopped with the daemon) rather
+    /// than look for one started elsewhere
+    #[serde(default)]
+    pub nightlight_managed: bool,
+
+    /// Tunnel interfaces the `vpn` module reports, e.g. ["wg0", "tun0"]
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+
+    /// VPN backend when no `interfaces` are given: "wireguard", "openvpn", "tailscale"
+    /// or "mullvad". Unset means every WireGuard and tun/tap interface.
+    pub vpn_provider: Option<String>,
+
+    /// Look up where VPN traffic exits (am.i.mullvad.net) for the tooltip
+    #[serde(default)]
+    pub exit_lookup: bool,
+
+    /// Exit node the `tailscale` module's `tailscale:exit-node` action switches to:
+    /// a peer's host name, MagicDNS name or IP
+    pub exit_node: Option<String>,
+
+    /// Package managers the `updates` module asks: "pacman", "apt", "dnf" and "flatpak".
+    /// Empty means every one that is installed.
+    #[serde(default)]
+    pub checkers: Vec<String>,
+
+    /// Filesystems the `disk` module reports, by mountpoint
+    #[serde(default = "default_mountpoints")]
+    pub mountpoints: Vec<String>,
+
+    /// Show the number of flagged INBOX messages next to the unread count (mail module)
+    #[serde(default)]
+    pub mail_flagged: bool,
+
+    /// Close a pinned menu after this many seconds (the widget gets a `pin-expiring` class shortly before)
+    pub pin_timeout: Option<u64>,
+
+    /// Dwell time before a hover opens the menu; a leave within this window, or the
+    /// cursor being off the bar once it ends, cancels it
+    #[serde(default)]
+    pub hover_delay_ms: u64,
+
+    /// Menu window opacity, 0.0-1.0
+    pub opacity: Option<f64>,
+
+    /// Blur behind the menu window
+    pub blur: Option<bool>,
+
+    /// Menu window corner rounding in pixels
+    pub rounding: Option<u32>,
+
+    /// Drop shadow on the menu window
+    pub shadow: Option<bool>,
+
+    /// How the menu leaves when it closes: "auto" (slide toward the bar), "up", "down",
+    /// "left", "right", "fade" (no movement) or "none" (close at once)
+    #[serde(default = "default_close_animation")]
+    pub close_animation: String,
+
+    /// How the menu comes in when it opens, with the same choices as `close_animation`
+    #[serde(default = "default_close_animation")]
+    pub open_animation: String,
+
+    /// Animation frames when closing
+    #[serde(default = "default_close_steps")]
+    pub close_steps: u32,
+
+    /// Length of the close animation
+    #[serde(default = "default_close_duration_ms")]
+    pub close_duration_ms: u64,
+
+    /// How far the menu slides while closing, in pixels
+    #[serde(default = "default_close_distance")]
+    pub close_distance: u32,
+
+    /// Animation frames when opening (default: `close_steps`)
+    pub open_steps: Option<u32>,
+
+    /// Length of the open animation (default: `close_duration_ms`)
+    pub open_duration_ms: Option<u64>,
+
+    /// How far the menu slides while opening, in pixels (default: `close_distance`)
+    pub open_distance: Option<u32>,
+
+    /// Extra window props applied verbatim once the menu appears, e.g. `"noanim 1"`,
+    /// or dispatches like `"dispatch pin address:{address}"`
+    #[serde(default)]
+    pub window_props: Vec<String>,
+
+    /// Reopen the menu window at the size and position it had when last closed
+    #[serde(default)]
+    pub remember_geometry: bool,
+
+    /// Child menus. When set, opening the module shows a chooser and launches the picked entry.
+    #[serde(default)]
+    pub entries: Vec<MenuEntry>,
+
+    /// Per-module override of `daemon.suppress_fullscreen`
+    pub suppress_fullscreen: Option<bool>,
+
+    /// Command run after the daemon performs this module's action, e.g.
+    /// `swayosd-client --custom-progress {fraction}`. Status fields are substituted.
+    pub osd_hook: Option<String>,
+
+    /// Lock command (for power module)
+    #[serde(default = "default_lock_cmd")]
+    pub lock_cmd: String,
+
+    /// Suspend command (for power module)
+    #[serde(default = "default_suspend_cmd")]
+    pub suspend_cmd: String,
+
+    /// Reboot command (for power module)
+    #[serde(default = "default_reboot_cmd")]
+    pub reboot_cmd: String,
+
+    /// Shutdown command (for power module)
+    #[serde(default = "default_shutdown_cmd")]
+    pub shutdown_cmd: String,
+
+    /// Ask for confirmation before running a power command
+    #[serde(default = "default_true")]
+    pub confirm: bool,
+}
+
+impl Default for ModuleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            kind: default_kind(),
+            command: None,
+            window_class: None,
+            shell: None,
+            timeout_ms: None,
+            url: None,
+            match_title_regex: None,
+            match_class_regex: None,
+            size: default_size(),
+            position: default_position(),
+            margin: 0,
+            offset: [0, 0],
+            action: None,
+            confirm_actions: false,
+            confirm_message: None,
+            action_cooldown_ms: default_action_cooldown_ms(),
+            charge_limit: default_charge_limit(),
+            format: None,
+            signal_format: default_signal_format(),
+            volume_command: None,
+            label: None,
+            hotspot_ssid: None,
+            hotspot_password: None,
+            notify_on_failure: false,
+            middle_action: None,
+            long_press_action: None,
+            actions: Vec::new(),
+            warning: None,
+            critical: None,
+            threshold_field: None,
+            threshold_below: None,
+            urgent: false,
+            urgent_notify: false,
+            hide_without_hardware: true,
+            hide_when: None,
+            blink_on: Vec::new(),
+            blink_interval_ms: default_blink_interval_ms(),
+            blink_text: None,
+            scroll_step: default_scroll_step(),
+            scroll_min: 0,
+            scroll_max: default_scroll_max(),
+            poll_interval: None,
+            status_command: None,
+            watch_command: None,
+            watch_dir: None,
+            sensor: None,
+            location: None,
+            weather_provider: default_weather_provider(),
+            units: default_units(),
+            inhibit: default_inhibit(),
+            nightlight_command: None,
+            nightlight_managed: false,
+            interfaces: Vec::new(),
+            vpn_provider: None,
+            exit_lookup: false,
+            exit_node: None,
+            checkers: Vec::new(),
+            notification_daemon: None,
+            mountpoints: default_mountpoints(),
+            mail_flagged: false,
+            pin_timeout: None,
+            hover_delay_ms: 0,
+            opacity: None,
+            blur: None,
+            rounding: None,
+            shadow: None,
+            close_animation: default_close_animation(),
+            open_animation: default_close_animation(),
+            close_steps: default_close_steps(),
+            close_duration_ms: default_close_duration_ms(),
+            close_distance: default_close_distance(),
+            open_steps: None,
+            open_duration_ms: None,
+            open_distance: None,
+            window_props: Vec::new(),
+            remember_geometry: false,
+            entries: Vec::new(),
+            suppress_fullscreen: None,
+            osd_hook: None,
+            lock_cmd: default_lock_cmd(),
+            suspend_cmd: default_suspend_cmd(),
+            reboot_cmd: default_reboot_cmd(),
+            shutdown_cmd: default_shutdown_cmd(),
+            confirm: true,
+        }
+    }
+}
+
+impl ModuleConfig {
+    /// Confirmation question for the module's quick actions, if they need one
+    pub fn confirm_prompt(&self) -> Option<String> {
+        self.confirm_actions
+            .then(|| self.confirm_message.clone().unwrap_or_else(|| "Run action?".to_string()))
+    }
+
+    /// Whether the menu is an app window matched by class ("gui" and "web" kinds)
+    pub fn is_gui(&self) -> bool {
+        self.kind == "gui" || self.kind == "web"
+    }
+
+    /// Whether a compositor window belongs to this module's menu. The `match_*_regex`
+    /// overrides replace the built-in title prefix / window class heuristics.
+    pub fn matches_window(&self, module: &str, title: &str, class: &str) -> bool {
+        if self.match_title_regex.is_some() || self.match_class_regex.is_some() {
+            self.match_title_regex.as_ref().is_none_or(|r| r.is_match(title))
+                && self.match_class_regex.as_ref().is_none_or(|r| r.is_match(class))
+        } else if self.is_gui() {
+            self.window_class.as_deref() == Some(class)
+        } else {
+            title.contains(&format!("WAYBAR-MENU: {}", module))
+        }
+    }
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> std::result::Result<Option<Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let pattern: Option<String> = Option::deserialize(deserializer)?;
+    pattern
+        .map(|p| Regex::new(&p).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// A window dimension: absolute pixels, or a percentage of the monitor it opens on
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "RawDimension")]
+pub enum Dimension {
+    Pixels(u32),
+    Percent(f64),
+}
+
+impl Dimension {
+    /// Resolve to pixels against the monitor's extent along the same axis
+    pub fn resolve(self, monitor: u32) -> u32 {
+        match self {
+            Dimension::Pixels(px) => px,
+            Dimension::Percent(pct) => (monitor as f64 * pct / 100.0).round() as u32,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDimension {
+    Pixels(u32),
+    Text(String),
+}
+
+impl TryFrom<RawDimension> for Dimension {
+    type Error = String;
+
+    fn try_from(raw: RawDimension) -> std::result::Result<Self, Self::Error> {
+        let text = match raw {
+            RawDimension::Pixels(px) => return Ok(Dimension::Pixels(px)),
+            RawDimension::Text(text) => text,
+        };
+        let text = text.trim();
+        if let Some(pct) = text.strip_suffix('%') {
+            match pct.trim().parse::<f64>() {
+                Ok(pct) if pct > 0.0 && pct <= 100.0 => Ok(Dimension::Percent(pct)),
+                _ => Err(format!("invalid percentage size: {:?}", text)),
+            }
+        } else {
+            text.trim_end_matches("px")
+                .parse()
+                .map(Dimension::Pixels)
+                .map_err(|_| format!("invalid size: {:?} (expected pixels or a percentage)", text))
+        }
+    }
+}
+
+/// An entry of a module's right-click context popup
+#[derive(Debug, Clone, Deserialize)]
+pub struct NamedAction {
+    pub name: String,
+    /// Glyph shown before the name
+    pub icon: Option<String>,
+    /// Action to run, same syntax as `action`
+    pub command: String,
+    /// Ask for confirmation before running it
+    #[serde(default)]
+    pub confirm: bool,
+    /// Question shown when confirming (default "<name>?")
+    pub confirm_message: Option<String>,
+}
+
+impl NamedAction {
+    /// Confirmation question, if this action needs one
+    pub fn confirm_prompt(&self) -> Option<String> {
+        self.confirm
+            .then(|| self.confirm_message.clone().unwrap_or_else(|| format!("{}?", self.name)))
+    }
+
+    /// Label shown in the context popup
+    pub fn label(&self) -> String {
+        match &self.icon {
+            Some(icon) => format!("{} {}", icon, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// A child entry of a submenu module
+#[derive(Debug, Clone, Deserialize)]
+pub struct MenuEntry {
+    /// Label shown in the chooser
+    pub name: String,
+
+    /// Menu type: "tui" or "gui"
+    #[serde(default = "default_kind")]
+    pub kind: String,
+
+    pub command: String,
+
+    /// Window class for GUI entries
+    pub window_class: Option<String>,
 }
 
 fn default_true() -> bool {
@@ -88,14 +968,90 @@ fn default_kind() -> String {
     "tui".to_string()
 }
 
-fn default_size() -> [u32; 2] {
-    [600, 400]
+fn default_size() -> [Dimension; 2] {
+    pixels(600, 400)
+}
+
+fn pixels(width: u32, height: u32) -> [Dimension; 2] {
+    [Dimension::Pixels(width), Dimension::Pixels(height)]
+}
+
+fn default_action_cooldown_ms() -> u64 {
+    500
+}
+
+fn default_close_animation() -> String {
+    "auto".to_string()
+}
+
+fn default_close_steps() -> u32 {
+    8
+}
+
+fn default_close_duration_ms() -> u64 {
+    240
+}
+
+fn default_close_distance() -> u32 {
+    480
 }
 
 fn default_position() -> String {
     "top-right".to_string()
 }
 
+fn default_mountpoints() -> Vec<String> {
+    vec!["/".to_string()]
+}
+
+fn default_weather_provider() -> String {
+    "open-meteo".to_string()
+}
+
+fn default_units() -> String {
+    "metric".to_string()
+}
+
+fn default_inhibit() -> String {
+    "idle".to_string()
+}
+
+fn default_signal_format() -> String {
+    "bars".to_string()
+}
+
+fn default_charge_limit() -> u32 {
+    80
+}
+
+fn default_blink_interval_ms() -> u64 {
+    500
+}
+
+fn default_scroll_step() -> u32 {
+    5
+}
+
+fn default_scroll_max() -> u32 {
+    100
+}
+
+fn default_lock_cmd() -> String {
+    "loginctl lock-session".to_string()
+}
+
+fn default_suspend_cmd() -> String {
+    "systemctl suspend".to_string()
+}
+
+fn default_reboot_cmd() -> String {
+    "systemctl reboot".to_string()
+}
+
+fn default_shutdown_cmd() -> String {
+    "systemctl poweroff".to_string()
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         let config_path = Self::config_path();
@@ -103,8 +1059,10 @@ impl Config {
         if config_path.exists() {
             let content = std::fs::read_to_string(&config_path)
                 .with_context(|| format!("Failed to read config from {:?}", config_path))?;
-            let config: Config =
+            let mut config: Config =
                 toml::from_str(&content).with_context(|| "Failed to parse config")?;
+            config.resolve_web_modules();
+            config.resolve_builtin_modules();
             Ok(config)
         } else {
             // Return default config
@@ -122,6 +1080,118 @@ impl Config {
     pub fn get_module(&self, name: &str) -> Option<&ModuleConfig> {
         self.modules.get(name)
     }
+
+    /// The bar an event came from: the one it names, else the one listing the module
+    /// (preferring bars on the event's `output`), else one `daemon.waybar_height` thick
+    /// along `daemon.bar_position`
+    pub fn bar(&self, id: Option<&str>, module: &str, output: Option<&str>) -> BarConfig {
+        if let Some(id) = id.filter(|id| !self.bars.contains_key(*id)) {
+            tracing::warn!("Unknown bar: {}", id);
+        }
+        let listing: Vec<&BarConfig> = self.bars
+            .values()
+            .filter(|b| b.modules.iter().any(|m| m == module))
+            .collect();
+        let on_output = listing.iter().find(|b| output.is_none_or(|o| b.is_on(o)));
+        id.and_then(|id| self.bars.get(id))
+            .or_else(|| on_output.or(listing.first()).copied())
+            .cloned()
+            .unwrap_or_else(|| BarConfig {
+                position: self.daemon.bar_position.clone(),
+                height: crate::profile::waybar_height(self),
+                modules: Vec::new(),
+                outputs: Vec::new(),
+            })
+    }
+
+    /// Whether a module's commands run under `sh -c`
+    pub fn use_shell(&self, module: &str) -> bool {
+        self.get_module(module)
+            .and_then(|m| m.shell)
+            .unwrap_or(self.daemon.shell)
+    }
+
+    /// Fill in what built-in modules do when their config leaves it out: the media
+    /// widget toggles play/pause, the temperature menu is a sensor TUI, the updates
+    /// widget rechecks and its menu installs the updates, the notifications widget
+    /// toggles do-not-disturb and its menu is the notification center, the idle widget
+    /// toggles the inhibitor, the nightlight widget toggles the gamma tool, the tailscale
+    /// widget connects and disconnects
+    fn resolve_builtin_modules(&mut self) {
+        if let Some(media) = self.modules.get_mut("media") {
+            if media.action.is_none() && media.actions.is_empty() {
+                media.action = Some("media:play-pause".to_string());
+            }
+        }
+        if let Some(temperature) = self.modules.get_mut("temperature") {
+            if temperature.command.is_none() && temperature.kind == "tui" {
+                temperature.command = Some("s-tui".to_string());
+            }
+        }
+        if let Some(notifications) = self.modules.get_mut("notifications") {
+            if notifications.action.is_none() && notifications.actions.is_empty() {
+                notifications.action = Some("notification-dnd:toggle".to_string());
+            }
+            if notifications.command.is_none() && notifications.kind == "tui" {
+                notifications.kind = "notification-center".to_string();
+            }
+        }
+        if let Some(idle) = self.modules.get_mut("idle") {
+            if idle.action.is_none() && idle.actions.is_empty() {
+                idle.action = Some("idle-inhibit:toggle".to_string());
+            }
+        }
+        if let Some(nightlight) = self.modules.get_mut("nightlight") {
+            if nightlight.action.is_none() && nightlight.actions.is_empty() {
+                nightlight.action = Some("nightlight:toggle".to_string());
+            }
+        }
+        if let Some(tailscale) = self.modules.get_mut("tailscale") {
+            if tailscale.action.is_none() && tailscale.actions.is_empty() {
+                tailscale.action = Some("tailscale:toggle".to_string());
+            }
+        }
+        if let Some(updates) = self.modules.get_mut("updates") {
+            if updates.action.is_none() && updates.actions.is_empty() {
+                updates.action = Some("updates:check".to_string());
+            }
+            if updates.command.is_none() && updates.kind == "tui" {
+                updates.command = updates::checkers(&updates.checkers)
+                    .first()
+                    .and_then(|checker| updates::upgrade_command(checker))
+                    .map(str::to_string);
+            }
+        }
+    }
+
+    /// Give "web" modules a generated window class and a browser command for their URL
+    fn resolve_web_modules(&mut self) {
+        for (name, module) in self.modules.iter_mut().filter(|(_, m)| m.kind == "web") {
+            let class = module
+                .window_class
+                .get_or_insert_with(|| format!("hovermenu-{}", name))
+                .clone();
+            if module.command.is_some() {
+                continue;
+            }
+            let Some(url) = &module.url else {
+                tracing::warn!("Web module {} has no url", name);
+                continue;
+            };
+            let profile = dirs::data_local_dir()
+                .unwrap_or_else(|| PathBuf::from("/tmp"))
+                .join("waybar-hovermenu")
+                .join("web")
+                .join(name);
+            module.command = Some(
+                self.daemon
+                    .browser_cmd
+                    .replace("{url}", &shell_words::quote(url))
+                    .replace("{class}", &shell_words::quote(&class))
+                    .replace("{profile}", &shell_words::quote(&profile.to_string_lossy())),
+            );
+        }
+    }
 }
 
 impl Default for Config {
@@ -136,11 +1206,12 @@ impl Default for Config {
                 kind: "gui".to_string(),
                 command: Some("pavucontrol".to_string()),
                 window_class: Some("org.pulseaudio.pavucontrol".to_string()),
-                size: [600, 400],
+                size: pixels(600, 400),
                 position: "top-right".to_string(),
                 action: Some("pactl set-sink-mute @DEFAULT_SINK@ toggle".to_string()),
                 poll_interval: None,
                 watch_dir: None,
+                ..ModuleConfig::default()
             },
         );
 
@@ -152,11 +1223,12 @@ impl Default for Config {
                 kind: "tui".to_string(),
                 command: Some("bluetui".to_string()),
                 window_class: None,
-                size: [600, 400],
+                size: pixels(600, 400),
                 position: "top-right".to_string(),
-                action: Some("bluetoothctl power off || bluetoothctl power on".to_string()),
+                action: Some("bluetooth:toggle".to_string()),
                 poll_interval: None,
                 watch_dir: None,
+                ..ModuleConfig::default()
             },
         );
 
@@ -168,11 +1240,13 @@ impl Default for Config {
                 kind: "tui".to_string(),
                 command: Some("impala".to_string()),
                 window_class: None,
-                size: [600, 400],
+                size: pixels(600, 400),
                 position: "top-right".to_string(),
                 action: Some("nmcli radio wifi off || nmcli radio wifi on".to_string()),
+                shell: Some(true),
                 poll_interval: None,
                 watch_dir: None,
+                ..ModuleConfig::default()
             },
         );
 
@@ -184,11 +1258,12 @@ impl Default for Config {
                 kind: "tui".to_string(),
                 command: Some("/usr/bin/btop".to_string()),
                 window_class: None,
-                size: [900, 600],
+                size: pixels(900, 600),
                 position: "top-right".to_string(),
                 action: None,
                 poll_interval: Some(3),
                 watch_dir: None,
+                ..ModuleConfig::default()
             },
         );
 
@@ -200,11 +1275,12 @@ impl Default for Config {
                 kind: "tui".to_string(),
                 command: Some("~/.local/bin/powertui".to_string()),
                 window_class: None,
-                size: [600, 400],
+                size: pixels(600, 400),
                 position: "top-right".to_string(),
                 action: None,
                 poll_interval: Some(30),
                 watch_dir: None,
+                ..ModuleConfig::default()
             },
         );
 
@@ -216,11 +1292,12 @@ impl Default for Config {
                 kind: "tui".to_string(),
                 command: Some("mailtui".to_string()),
                 window_class: None,
-                size: [600, 400],
+                size: pixels(600, 400),
                 position: "top-left".to_string(),
                 action: Some("mbsync -a".to_string()),
                 poll_interval: None,
                 watch_dir: Some("~/.local/share/mail".to_string()),
+                ..ModuleConfig::default()
             },
         );
 
@@ -232,11 +1309,12 @@ impl Default for Config {
                 kind: "tui".to_string(),
                 command: Some("~/.local/bin/calentui".to_string()),
                 window_class: None,
-                size: [600, 400],
+                size: pixels(600, 400),
                 position: "top-right".to_string(),
                 action: None,
                 poll_interval: None,
                 watch_dir: None,
+                ..ModuleConfig::default()
             },
         );
 
@@ -248,17 +1326,36 @@ impl Default for Config {
                 kind: "gui".to_string(),
                 command: Some("flatpak run org.localsend.localsend_app".to_string()),
                 window_class: Some("localsend".to_string()),
-                size: [400, 500],
+                size: pixels(400, 500),
                 position: "top-left".to_string(),
                 action: None,
                 poll_interval: None,
                 watch_dir: None,
+                ..ModuleConfig::default()
+            },
+        );
+
+        // Power
+        modules.insert(
+            "power".to_string(),
+            ModuleConfig {
+                kind: "power".to_string(),
+                position: "top-right".to_string(),
+                ..ModuleConfig::default()
             },
         );
 
         Self {
             daemon: DaemonConfig::default(),
             modules,
+            osd: OsdConfig::default(),
+            http: HttpConfig::default(),
+            i3bar: I3barConfig::default(),
+            webhooks: HashMap::new(),
+            groups: HashMap::new(),
+            bars: HashMap::new(),
+            profiles: HashMap::new(),
+            icons: IconsConfig::default(),
         }
     }
 }
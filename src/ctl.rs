@@ -1,30 +1,79 @@
 use std::env;
 use std::io::{BufRead, BufReader, Write};
 use std::os::unix::net::UnixStream;
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
 const SOCKET_PATH: &str = "/tmp/waybar-hovermenu.sock";
+const BUS_NAME: &str = "io.github.chbornman.WaybarHovermenu";
+
+/// Ask the session bus to start the daemon via its D-Bus service file.
+/// Blocks until the daemon has claimed its bus name (or activation failed).
+fn activate_daemon() -> bool {
+    Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus.StartServiceByName",
+            &format!("string:{}", BUS_NAME),
+            "uint32:0",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Connect to the daemon, activating it over D-Bus if it isn't running yet
+fn connect() -> std::io::Result<UnixStream> {
+    let err = match UnixStream::connect(SOCKET_PATH) {
+        Ok(s) => return Ok(s),
+        Err(e) => e,
+    };
+
+    if !activate_daemon() {
+        return Err(err);
+    }
+
+    // The bus name is claimed before the socket is bound, so give it a moment
+    for _ in 0..20 {
+        if let Ok(s) = UnixStream::connect(SOCKET_PATH) {
+            return Ok(s);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    UnixStream::connect(SOCKET_PATH)
+}
+
+/// Exit once the parent (the bar) dies, instead of lingering as an orphan
+/// that keeps a follow stream open
+fn exit_when_orphaned() {
+    let parent = std::os::unix::process::parent_id();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_secs(5));
+        if std::os::unix::process::parent_id() != parent {
+            std::process::exit(0);
+        }
+    });
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     if args.len() < 2 {
-        eprintln!("Usage: hovermenu-ctl <command> [module]");
-        eprintln!("Commands: follow, status, hover, leave, click, action");
+        eprintln!("Usage: hovermenu-ctl <command> [module] [bar]");
+        eprintln!("Commands: follow, status, tooltip, list, open, close, close-all, pin, unpin, hover, leave, click, tap, hold, action, middle-click, long-press, press, release, scroll-up, scroll-down, dnd, log, simulate, quit");
         std::process::exit(1);
     }
 
     let command = &args[1];
-    let module = args.get(2).map(|s| s.as_str()).unwrap_or("");
-
-    // Build the command string
-    let cmd = if module.is_empty() {
-        format!("{}\n", command)
-    } else {
-        format!("{} {}\n", command, module)
-    };
 
-    // Connect to the daemon
-    let mut stream = match UnixStream::connect(SOCKET_PATH) {
+    // Connect to the daemon (starting it via D-Bus activation if needed)
+    let mut stream = match connect() {
         Ok(s) => s,
         Err(e) => {
             eprintln!("Failed to connect to daemon: {}", e);
@@ -33,26 +82,61 @@ fn main() {
         }
     };
 
+    // follow streams the bar's JSON as plain-text lines; everything else is a JSON
+    // request, so the daemon can tell us when it fails
+    let request = if command == "follow" {
+        format!("{}\n", args[1..].join(" "))
+    } else {
+        let request = serde_json::json!({ "cmd": command, "args": &args[2..] });
+        format!("{}\n", request)
+    };
+
     // Send the command
-    if let Err(e) = stream.write_all(cmd.as_bytes()) {
+    if let Err(e) = stream.write_all(request.as_bytes()) {
         eprintln!("Failed to send command: {}", e);
         std::process::exit(1);
     }
 
-    // For follow command, keep reading and printing output
-    // For other commands, just read one line (if any)
-    if command == "follow" || command == "status" {
-        let reader = BufReader::new(stream);
-        for line in reader.lines() {
-            match line {
-                Ok(line) => println!("{}", line),
-                Err(_) => break,
-            }
-
-            // For status, just print one line
-            if command == "status" {
-                break;
-            }
+    let reader = BufReader::new(stream);
+    if command != "follow" {
+        std::process::exit(print_reply(reader));
+    }
+
+    exit_when_orphaned();
+    let mut stdout = std::io::stdout();
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        // The bar went away; exit so the daemon can drop this stream
+        if writeln!(stdout, "{}", line).and_then(|_| stdout.flush()).is_err() {
+            break;
         }
     }
 }
+
+/// Print a JSON reply's `result` (if any) or its error, returning the exit code
+fn print_reply(mut reader: impl BufRead) -> i32 {
+    let mut line = String::new();
+    if let Err(e) = reader.read_line(&mut line) {
+        eprintln!("Failed to read reply: {}", e);
+        return 1;
+    }
+    let reply: serde_json::Value = match serde_json::from_str(&line) {
+        Ok(reply) => reply,
+        Err(_) => {
+            eprintln!("The daemon closed the connection without replying");
+            return 1;
+        }
+    };
+
+    if reply.get("ok").and_then(|ok| ok.as_bool()) != Some(true) {
+        let error = reply.get("error").and_then(|e| e.as_str()).unwrap_or("unknown error");
+        eprintln!("{}", error);
+        return 1;
+    }
+    if let Some(result) = reply.get("result") {
+        println!("{}", result);
+    }
+    0
+}
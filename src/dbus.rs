@@ -0,0 +1,15 @@
+use anyhow::{Context, Result};
+use zbus::Connection;
+
+/// Well-known session bus name used for D-Bus activation
+pub const BUS_NAME: &str = "io.github.chbornman.WaybarHovermenu";
+
+/// Claim the daemon's bus name so D-Bus activation requests see it as started.
+/// The returned connection must be kept alive for the name to stay owned.
+pub async fn claim_name() -> Result<Connection> {
+    zbus::connection::Builder::session()?
+        .name(BUS_NAME)?
+        .build()
+        .await
+        .with_context(|| format!("Failed to claim bus name {}", BUS_NAME))
+}
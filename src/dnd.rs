@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+/// Manual override: follow the schedule, forced off, or forced on
+const FOLLOW_SCHEDULE: u8 = 0;
+const FORCED_OFF: u8 = 1;
+const FORCED_ON: u8 = 2;
+
+static OVERRIDE: AtomicU8 = AtomicU8::new(FOLLOW_SCHEDULE);
+/// Daily quiet window as (start, end) minutes since midnight
+static SCHEDULE: Mutex<Option<(u32, u32)>> = Mutex::new(None);
+
+/// Set the daily schedule from config, e.g. "22:00-07:00"
+pub fn init(schedule: Option<&str>) {
+    let parsed = schedule.and_then(|s| {
+        let parsed = parse_schedule(s);
+        if parsed.is_none() {
+            tracing::warn!("Invalid dnd_schedule {:?}, expected HH:MM-HH:MM", s);
+        }
+        parsed
+    });
+    *SCHEDULE.lock().unwrap() = parsed;
+}
+
+fn parse_schedule(s: &str) -> Option<(u32, u32)> {
+    let (start, end) = s.split_once('-')?;
+    Some((parse_time(start)?, parse_time(end)?))
+}
+
+fn parse_time(s: &str) -> Option<u32> {
+    let (h, m) = s.trim().split_once(':')?;
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+/// Minutes since local midnight
+fn local_minutes() -> u32 {
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        libc::localtime_r(&now, &mut tm);
+    }
+    (tm.tm_hour * 60 + tm.tm_min) as u32
+}
+
+fn in_schedule() -> bool {
+    let Some((start, end)) = *SCHEDULE.lock().unwrap() else {
+        return false;
+    };
+    let now = local_minutes();
+    if start <= end {
+        now >= start && now < end
+    } else {
+        // Window wraps past midnight
+        now >= start || now < end
+    }
+}
+
+/// Whether do-not-disturb is currently in effect
+pub fn is_active() -> bool {
+    match OVERRIDE.load(Ordering::SeqCst) {
+        FORCED_ON => true,
+        FORCED_OFF => false,
+        _ => in_schedule(),
+    }
+}
+
+/// Apply an IPC `dnd` argument: "on", "off", "toggle" or "auto" (follow the schedule)
+pub fn set(arg: &str) -> bool {
+    let value = match arg {
+        "on" => FORCED_ON,
+        "off" => FORCED_OFF,
+        "auto" => FOLLOW_SCHEDULE,
+        "toggle" | "" => {
+            if is_active() {
+                FORCED_OFF
+            } else {
+                FORCED_ON
+            }
+        }
+        _ => return false,
+    };
+    OVERRIDE.store(value, Ordering::SeqCst);
+    true
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::config::Config;
+use crate::modules::get_status;
+
+/// Where exported statuses go: `$XDG_RUNTIME_DIR/waybar-hovermenu/status`
+fn export_dir() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("waybar-hovermenu")
+        .join("status")
+}
+
+/// Replace `<module>.json` in one step, so readers never see a half-written file
+fn write_atomic(dir: &Path, module: &str, json: &str) -> std::io::Result<()> {
+    let path = dir.join(format!("{}.json", module));
+    let tmp = dir.join(format!(".{}.json.tmp", module));
+    std::fs::write(&tmp, format!("{}\n", json))?;
+    std::fs::rename(&tmp, &path)
+}
+
+/// Write a module's status unless the file already holds it
+fn export(dir: &Path, written: &mut HashMap<String, String>, module: String, json: String) {
+    // Module names end up in a path; refuse anything that could leave the directory
+    if module.is_empty() || module.contains(['/', '\0']) || module.starts_with('.') {
+        return;
+    }
+    if written.get(&module) == Some(&json) {
+        return;
+    }
+    match write_atomic(dir, &module, &json) {
+        Ok(()) => {
+            written.insert(module, json);
+        }
+        Err(e) => tracing::warn!("Failed to export {} status: {}", module, e),
+    }
+}
+
+/// Mirror every broadcast status into a file per module (`daemon.export_status`)
+pub async fn run(config: Arc<Config>, status_tx: broadcast::Sender<(String, String)>) {
+    let dir = export_dir();
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::warn!("Not exporting statuses, can't create {}: {}", dir.display(), e);
+        return;
+    }
+    tracing::info!("Exporting statuses to {}", dir.display());
+    
+    let mut rx = status_tx.subscribe();
+    let mut written: HashMap<String, String> = HashMap::new();
+    
+    // Start with every module's current status rather than waiting for its first change
+    let modules: Vec<String> = config.modules.iter()
+        .filter(|(_, m)| m.enabled)
+        .map(|(name, _)| name.clone())
+        .collect();
+    for module in modules {
+        let cfg = Arc::clone(&config);
+        let module_owned = module.clone();
+        let Ok(status) = tokio::task::spawn_blocking(move || get_status(&cfg, &module_owned, false)).await else {
+            continue;
+        };
+        export(&dir, &mut written, module, status.to_json());
+    }
+    
+    loop {
+        match rx.recv().await {
+            Ok((module, json)) => export(&dir, &mut written, module, json),
+            Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Follow streams connecting this close together count as one wave
+const WAVE_GAP: Duration = Duration::from_secs(2);
+/// A bar (re)starting opens at least this many follow streams at once
+const WAVE_MIN: usize = 3;
+/// How long a wave has to go quiet before it is considered complete
+pub const WAVE_SETTLE: Duration = Duration::from_secs(1);
+
+/// Open follow streams per widget name
+static FOLLOWING: OnceLock<Mutex<HashMap<String, usize>>> = OnceLock::new();
+/// The current wave of follow connections
+static WAVE: Mutex<Wave> = Mutex::new(Wave { last: None, size: 0, generation: 0 });
+
+struct Wave {
+    last: Option<Instant>,
+    size: usize,
+    generation: u64,
+}
+
+fn following() -> &'static Mutex<HashMap<String, usize>> {
+    FOLLOWING.get_or_init(Default::default)
+}
+
+/// Counts a follow stream as open until dropped
+pub struct FollowGuard(String);
+
+impl Drop for FollowGuard {
+    fn drop(&mut self) {
+        let mut following = following().lock().unwrap();
+        if let Some(count) = following.get_mut(&self.0) {
+            *count -= 1;
+            if *count == 0 {
+                following.remove(&self.0);
+            }
+        }
+    }
+}
+
+/// Record a new follow stream, returning its guard and the wave generation it joined
+pub fn connect(name: &str) -> (FollowGuard, u64) {
+    *following().lock().unwrap().entry(name.to_string()).or_insert(0) += 1;
+
+    let mut wave = WAVE.lock().unwrap();
+    if wave.last.is_none_or(|last| last.elapsed() > WAVE_GAP) {
+        wave.size = 0;
+    }
+    wave.size += 1;
+    wave.generation += 1;
+    wave.last = Some(Instant::now());
+    (FollowGuard(name.to_string()), wave.generation)
+}
+
+/// Once the wave `generation` was the last connection and it was big enough to be a bar
+/// (re)starting, every widget name being followed. Each wave is reported once.
+pub fn settled_wave(generation: u64) -> Option<Vec<String>> {
+    let mut wave = WAVE.lock().unwrap();
+    if wave.generation != generation || wave.size < WAVE_MIN {
+        return None;
+    }
+    wave.size = 0;
+    drop(wave);
+
+    Some(following().lock().unwrap().keys().cloned().collect())
+}
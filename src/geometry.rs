@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A menu window's position and size in layout pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Geometry {
+    /// Read the geometry of a `hyprctl clients -j` entry
+    pub fn from_client(client: &serde_json::Value) -> Option<Self> {
+        let pair = |key: &str| -> Option<(i64, i64)> {
+            let v = client.get(key)?.as_array()?;
+            Some((v.first()?.as_i64()?, v.get(1)?.as_i64()?))
+        };
+        let (x, y) = pair("at")?;
+        let (width, height) = pair("size")?;
+        Some(Self {
+            x: x as i32,
+            y: y as i32,
+            width: width as u32,
+            height: height as u32,
+        })
+    }
+
+    /// Whether a point lies inside
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width as i32 && y >= self.y && y < self.y + self.height as i32
+    }
+
+    /// Read the logical (scaled, rotated) area of a `hyprctl monitors -j` entry
+    pub fn from_monitor(monitor: &serde_json::Value) -> Option<Self> {
+        let int = |key: &str| monitor.get(key).and_then(|v| v.as_i64());
+        let scale = monitor.get("scale").and_then(|v| v.as_f64()).filter(|s| *s > 0.0).unwrap_or(1.0);
+        let mut width = (int("width")? as f64 / scale).round() as u32;
+        let mut height = (int("height")? as f64 / scale).round() as u32;
+        // Odd transforms are rotated by 90 or 270 degrees
+        if int("transform").unwrap_or(0) % 2 == 1 {
+            std::mem::swap(&mut width, &mut height);
+        }
+        Some(Self {
+            x: int("x")? as i32,
+            y: int("y")? as i32,
+            width,
+            height,
+        })
+    }
+}
+
+/// Where remembered menu geometry is stored
+fn state_path() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("waybar-hovermenu")
+        .join("geometry.json")
+}
+
+/// Load remembered geometry per module. A missing or unreadable file yields none.
+pub fn load() -> HashMap<String, Geometry> {
+    std::fs::read_to_string(state_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Save remembered geometry so it survives daemon restarts
+pub fn save(geometry: &HashMap<String, Geometry>) {
+    let path = state_path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match serde_json::to_string_pretty(geometry) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&path, json) {
+                tracing::warn!("Failed to save menu geometry to {}: {}", path.display(), e);
+            }
+        }
+        Err(e) => tracing::warn!("Failed to serialize menu geometry: {}", e),
+    }
+}
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::config::Config;
+
+/// Index of the member each group currently displays
+static CURRENT: OnceLock<Mutex<HashMap<String, usize>>> = OnceLock::new();
+
+fn current() -> &'static Mutex<HashMap<String, usize>> {
+    CURRENT.get_or_init(Default::default)
+}
+
+/// The module a widget name stands for: a group's displayed member, otherwise the name itself
+pub fn resolve(config: &Config, name: &str) -> String {
+    let Some(group) = config.groups.get(name) else {
+        return name.to_string();
+    };
+    let index = current().lock().unwrap().get(name).copied().unwrap_or(0);
+    group
+        .modules
+        .get(index)
+        .or_else(|| group.modules.first())
+        .cloned()
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Show the next (or previous) member of a group, returning it
+pub fn cycle(config: &Config, name: &str, forward: bool) -> Option<String> {
+    let group = config.groups.get(name)?;
+    let count = group.modules.len();
+    if count == 0 {
+        return None;
+    }
+
+    let mut current = current().lock().unwrap();
+    let index = current.entry(name.to_string()).or_insert(0);
+    *index = if forward {
+        (*index + 1) % count
+    } else {
+        (*index + count - 1) % count
+    };
+    group.modules.get(*index).cloned()
+}
@@ -0,0 +1,36 @@
+use std::path::Path;
+
+/// Sysfs class directory that must be non-empty for a module to have hardware behind it
+fn device_class(module: &str) -> Option<&'static str> {
+    match module {
+        "bluetooth" => Some("/sys/class/bluetooth"),
+        "brightness" => Some("/sys/class/backlight"),
+        "airplane" => Some("/sys/class/rfkill"),
+        _ => None,
+    }
+}
+
+/// Whether the hardware a module reports on exists. Modules without a known
+/// hardware dependency are always considered present.
+pub fn present(module: &str) -> bool {
+    let Some(class) = device_class(module) else {
+        return true;
+    };
+    std::fs::read_dir(Path::new(class))
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false)
+}
+
+/// Modules whose presence can change on hotplug
+pub fn hotplug_modules() -> [&'static str; 2] {
+    ["bluetooth", "brightness"]
+}
+
+/// Log which hardware-backed modules will be hidden
+pub fn log_probe() {
+    for module in hotplug_modules() {
+        if !present(module) {
+            tracing::info!("No {} hardware found, hiding module until it appears", module);
+        }
+    }
+}
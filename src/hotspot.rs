@@ -0,0 +1,232 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::process::Command;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+use crate::command;
+
+const NM: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const NM_DEVICE: &str = "org.freedesktop.NetworkManager.Device";
+/// `NM_DEVICE_TYPE_WIFI`
+const NM_WIFI: u32 = 2;
+
+/// A running access point
+pub struct Hotspot {
+    /// Connection (NetworkManager) or network (iwd) name
+    pub name: String,
+    /// Wireless interface it runs on
+    pub device: String,
+    backend: Backend,
+}
+
+enum Backend {
+    NetworkManager,
+    /// The iwd device's object path
+    Iwd(OwnedObjectPath),
+}
+
+/// The active hotspot, from NetworkManager or else iwd
+pub fn active() -> Option<Hotspot> {
+    nm_active().or_else(iwd_active)
+}
+
+/// Stations associated with the hotspot, via `iw`
+pub fn client_count(device: &str) -> Option<usize> {
+    let output = command::output(Command::new("iw").args(["dev", device, "station", "dump"])).ok()?;
+    output.status.success().then(|| {
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|l| l.starts_with("Station "))
+            .count()
+    })
+}
+
+/// Start a hotspot. NetworkManager makes up the SSID and password when not given;
+/// iwd needs both.
+pub fn start(ssid: Option<&str>, password: Option<&str>) -> Result<()> {
+    if nm_running() {
+        // A password is handed over D-Bus, where other users can't read it the way
+        // they could read nmcli's arguments
+        if let Some(password) = password {
+            return nm_start(ssid, password);
+        }
+        let mut cmd = Command::new("nmcli");
+        cmd.args(["device", "wifi", "hotspot"]);
+        if let Some(ssid) = ssid {
+            cmd.args(["ssid", ssid]);
+        }
+        return run(&mut cmd);
+    }
+
+    let (Some(ssid), Some(password)) = (ssid, password) else {
+        bail!("iwd hotspots need hotspot_ssid and hotspot_password");
+    };
+    let device = iwd_devices()?
+        .into_iter()
+        .next()
+        .context("No wireless device found (neither NetworkManager nor iwd)")?;
+    let connection = zbus::blocking::Connection::system()?;
+    let device_proxy = iwd_proxy(&connection, &device, "net.connman.iwd.Device")?;
+    device_proxy.set_property("Mode", "ap")?;
+    iwd_proxy(&connection, &device, "net.connman.iwd.AccessPoint")?
+        .call_method("Start", &(ssid, password))?;
+    Ok(())
+}
+
+/// Stop the active hotspot
+pub fn stop(hotspot: &Hotspot) -> Result<()> {
+    match &hotspot.backend {
+        Backend::NetworkManager => {
+            run(Command::new("nmcli").args(["connection", "down", &hotspot.name]))
+        }
+        Backend::Iwd(device) => {
+            let connection = zbus::blocking::Connection::system()?;
+            iwd_proxy(&connection, device, "net.connman.iwd.AccessPoint")?
+                .call_method("Stop", &())?;
+            iwd_proxy(&connection, device, "net.connman.iwd.Device")?
+                .set_property("Mode", "station")?;
+            Ok(())
+        }
+    }
+}
+
+fn run(cmd: &mut Command) -> Result<()> {
+    let output = command::output(cmd)?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn nm_running() -> bool {
+    command::status(Command::new("nmcli").args(["-t", "general", "status"]))
+        .is_ok_and(|s| s.success())
+}
+
+/// Bring up a WPA2 access point on the first Wi-Fi device, like `nmcli device wifi
+/// hotspot` does. The profile is volatile, so NetworkManager forgets it once it's down.
+fn nm_start(ssid: Option<&str>, password: &str) -> Result<()> {
+    let connection = zbus::blocking::Connection::system()?;
+    let manager = zbus::blocking::Proxy::new(&connection, NM, NM_PATH, NM)?;
+    let devices: Vec<OwnedObjectPath> = manager.call("GetDevices", &())?;
+    let device = devices
+        .into_iter()
+        .find(|path| {
+            zbus::blocking::Proxy::new(&connection, NM, path.as_str(), NM_DEVICE)
+                .and_then(|device| device.get_property::<u32>("DeviceType"))
+                .is_ok_and(|kind| kind == NM_WIFI)
+        })
+        .context("No Wi-Fi device")?;
+
+    // nmcli's default name
+    let ssid = ssid.map(str::to_string).unwrap_or_else(|| {
+        let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+        format!("Hotspot-{}", hostname.trim())
+    });
+    let settings: HashMap<&str, HashMap<&str, Value>> = HashMap::from([
+        ("connection", HashMap::from([
+            ("id", Value::from("Hotspot")),
+            ("type", Value::from("802-11-wireless")),
+            ("autoconnect", Value::from(false)),
+        ])),
+        ("802-11-wireless", HashMap::from([
+            ("ssid", Value::from(ssid.into_bytes())),
+            ("mode", Value::from("ap")),
+        ])),
+        ("802-11-wireless-security", HashMap::from([
+            ("key-mgmt", Value::from("wpa-psk")),
+            ("psk", Value::from(password)),
+            ("proto", Value::from(vec!["rsn"])),
+            ("pairwise", Value::from(vec!["ccmp"])),
+            ("group", Value::from(vec!["ccmp"])),
+        ])),
+        ("ipv4", HashMap::from([("method", Value::from("shared"))])),
+        ("ipv6", HashMap::from([("method", Value::from("ignore"))])),
+    ]);
+    let options: HashMap<&str, Value> = HashMap::from([("persist", Value::from("volatile"))]);
+    let _: (OwnedObjectPath, OwnedObjectPath, HashMap<String, OwnedValue>) = manager.call(
+        "AddAndActivateConnection2",
+        &(settings, device, ObjectPath::try_from("/")?, options),
+    )?;
+    Ok(())
+}
+
+/// An active NetworkManager Wi-Fi connection in AP mode
+fn nm_active() -> Option<Hotspot> {
+    let output = command::output(Command::new("nmcli")
+        .args(["-t", "-f", "NAME,TYPE,DEVICE", "connection", "show", "--active"]))
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().find_map(|line| {
+        // Names may contain escaped colons, the last two fields can't
+        let mut fields = line.rsplitn(3, ':');
+        let device = fields.next()?;
+        let kind = fields.next()?;
+        let name = fields.next()?.replace("\\:", ":");
+        if kind != "802-11-wireless" {
+            return None;
+        }
+        let mode = command::output(Command::new("nmcli")
+            .args(["-g", "802-11-wireless.mode", "connection", "show", &name]))
+            .ok()?;
+        (String::from_utf8_lossy(&mode.stdout).trim() == "ap").then(|| Hotspot {
+            name,
+            device: device.to_string(),
+            backend: Backend::NetworkManager,
+        })
+    })
+}
+
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+fn iwd_objects() -> Result<ManagedObjects> {
+    let connection = zbus::blocking::Connection::system()?;
+    let reply = connection.call_method(
+        Some("net.connman.iwd"),
+        "/",
+        Some("org.freedesktop.DBus.ObjectManager"),
+        "GetManagedObjects",
+        &(),
+    )?;
+    Ok(reply.body().deserialize()?)
+}
+
+fn iwd_devices() -> Result<Vec<OwnedObjectPath>> {
+    Ok(iwd_objects()?
+        .into_iter()
+        .filter(|(_, interfaces)| interfaces.contains_key("net.connman.iwd.Device"))
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// An iwd device with a started access point
+fn iwd_active() -> Option<Hotspot> {
+    let objects = iwd_objects().ok()?;
+    objects.into_iter().find_map(|(path, interfaces)| {
+        let ap = interfaces.get("net.connman.iwd.AccessPoint")?;
+        let started = ap.get("Started").and_then(|v| bool::try_from(v).ok())?;
+        if !started {
+            return None;
+        }
+        let string = |props: Option<&HashMap<String, OwnedValue>>, key: &str| {
+            props
+                .and_then(|p| p.get(key))
+                .and_then(|v| String::try_from(v.clone()).ok())
+                .unwrap_or_default()
+        };
+        Some(Hotspot {
+            name: string(Some(ap), "Name"),
+            device: string(interfaces.get("net.connman.iwd.Device"), "Name"),
+            backend: Backend::Iwd(path),
+        })
+    })
+}
+
+fn iwd_proxy<'a>(
+    connection: &zbus::blocking::Connection,
+    path: &'a OwnedObjectPath,
+    interface: &'a str,
+) -> Result<zbus::blocking::Proxy<'a>> {
+    Ok(zbus::blocking::Proxy::new(connection, "net.connman.iwd", path.as_ref(), interface)?)
+}
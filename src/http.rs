@@ -0,0 +1,141 @@
+use anyhow::Result;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::Config;
+use crate::menu::MenuManager;
+use crate::modules::{get_status, ModuleStatus};
+use crate::secrets;
+
+/// Read-only HTTP server exposing module statuses as JSON
+pub async fn run(config: Arc<Config>, menu_manager: Arc<MenuManager>) -> Result<()> {
+    let listener = TcpListener::bind(&config.http.listen).await?;
+    tracing::info!("HTTP status server listening on {}", config.http.listen);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let config = Arc::clone(&config);
+                let menu_manager = Arc::clone(&menu_manager);
+                tokio::spawn(async move {
+                    if let Err(e) = handle_request(stream, config, menu_manager).await {
+                        tracing::debug!("HTTP client error: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::error!("HTTP accept error: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_request(
+    stream: TcpStream,
+    config: Arc<Config>,
+    menu_manager: Arc<MenuManager>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    // Request line: "GET /status/audio HTTP/1.1"
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+
+    // Headers, up to the blank line
+    let mut authorization = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            break;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+
+    let (code, body) = if method != "GET" {
+        (405, r#"{"error":"method not allowed"}"#.to_string())
+    } else if !authorized(&config, authorization.as_deref()) {
+        (401, r#"{"error":"unauthorized"}"#.to_string())
+    } else if path == "/status" {
+        (200, all_statuses(&config, &menu_manager).await)
+    } else if let Some(module) = path.strip_prefix("/status/") {
+        if config.get_module(module).is_some_and(|m| m.enabled) {
+            (200, module_status(&config, module, &menu_manager).await.to_json())
+        } else {
+            (404, r#"{"error":"unknown module"}"#.to_string())
+        }
+    } else {
+        (404, r#"{"error":"not found"}"#.to_string())
+    };
+
+    let reason = match code {
+        200 => "OK",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    );
+    writer.write_all(response.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Check the `Authorization: Bearer` token against the configured secret. Only the
+/// header counts: a token in the URL would end up in logs and shell history.
+fn authorized(config: &Config, authorization: Option<&str>) -> bool {
+    let Some(secret_name) = &config.http.token_secret else {
+        return true;
+    };
+    let Some(expected) = secrets::load().remove(secret_name) else {
+        tracing::warn!("HTTP token secret {:?} not found, rejecting request", secret_name);
+        return false;
+    };
+
+    let Some(token) = authorization.and_then(|a| a.strip_prefix("Bearer ")).map(str::trim) else {
+        return false;
+    };
+    // Constant time, so response timing doesn't reveal how much of a guess was right
+    token.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+async fn module_status(config: &Arc<Config>, module: &str, menu_manager: &MenuManager) -> ModuleStatus {
+    let pinned = menu_manager.is_pinned(module).await;
+    let config = Arc::clone(config);
+    let module_owned = module.to_string();
+    tokio::task::spawn_blocking(move || get_status(&config, &module_owned, pinned))
+        .await
+        .unwrap_or_else(|_| ModuleStatus::new("error"))
+}
+
+async fn all_statuses(config: &Arc<Config>, menu_manager: &MenuManager) -> String {
+    let mut all = serde_json::Map::new();
+    for (name, module) in &config.modules {
+        if !module.enabled {
+            continue;
+        }
+        let status = module_status(config, name, menu_manager).await;
+        all.insert(name.clone(), serde_json::to_value(&status).unwrap_or_default());
+    }
+    serde_json::Value::Object(all).to_string()
+}
@@ -0,0 +1,200 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+
+use crate::compositor::{Compositor, Monitor, Window};
+use crate::geometry::Geometry;
+
+/// Requests that take longer than this are abandoned, like a hung `hyprctl` would be killed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Path of one of this Hyprland instance's sockets (`.socket.sock`, `.socket2.sock`)
+fn socket_path(name: &str) -> Result<PathBuf> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .context("HYPRLAND_INSTANCE_SIGNATURE not set")?;
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").context("XDG_RUNTIME_DIR not set")?;
+    let path = PathBuf::from(runtime_dir).join("hypr").join(&signature).join(name);
+    if path.exists() {
+        return Ok(path);
+    }
+    // Hyprland before 0.40 kept its sockets in /tmp
+    Ok(PathBuf::from("/tmp/hypr").join(signature).join(name))
+}
+
+/// Send one request over the command socket and return the reply,
+/// e.g. `request("j/clients")` for what `hyprctl clients -j` prints
+async fn request(command: &str) -> Result<Vec<u8>> {
+    let path = socket_path(".socket.sock")?;
+    let exchange = async {
+        let mut stream = UnixStream::connect(&path).await?;
+        stream.write_all(command.as_bytes()).await?;
+        // Hyprland closes the connection after replying
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        anyhow::Ok(reply)
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .with_context(|| format!("Hyprland didn't answer {:?}", command))?
+        .with_context(|| format!("Hyprland request {:?} failed", command))
+}
+
+/// A query's JSON reply, e.g. `json("clients")`
+async fn json(query: &str) -> Result<serde_json::Value> {
+    let reply = request(&format!("j/{}", query)).await?;
+    serde_json::from_slice(&reply).with_context(|| format!("Bad reply to {:?}", query))
+}
+
+/// Run several commands in one request, like `hyprctl --batch`.
+/// Each is a full command, e.g. "dispatch setfloating address:0x...".
+async fn batch(commands: &[String]) -> Result<()> {
+    if commands.is_empty() {
+        return Ok(());
+    }
+    let reply = request(&format!("[[BATCH]]{}", commands.join(" ; "))).await?;
+    check(&reply)
+}
+
+/// Run one dispatcher, e.g. `dispatch("setprop address:0x... alpha 0.9")`
+async fn dispatch(args: &str) -> Result<()> {
+    let reply = request(&format!("dispatch {}", args)).await?;
+    check(&reply)
+}
+
+/// Fail on anything but "ok" replies (batches get one per command)
+fn check(reply: &[u8]) -> Result<()> {
+    let reply = String::from_utf8_lossy(reply);
+    if let Some(error) = reply.split("\n\n").map(str::trim).find(|r| !r.is_empty() && *r != "ok") {
+        bail!("Hyprland: {}", error);
+    }
+    Ok(())
+}
+
+/// Event lines from `.socket2.sock`, e.g. "activewindowv2>>55d3a8e0e0f0"
+async fn events() -> Result<Lines<BufReader<UnixStream>>> {
+    let stream = UnixStream::connect(socket_path(".socket2.sock")?).await?;
+    Ok(BufReader::new(stream).lines())
+}
+
+/// Hyprland, over its command and event sockets
+pub struct Hyprland;
+
+#[async_trait]
+impl Compositor for Hyprland {
+    async fn windows(&self) -> Result<Vec<Window>> {
+        let clients = json("clients").await?;
+        let str_field = |client: &serde_json::Value, key: &str| {
+            client.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string()
+        };
+        Ok(clients.as_array()
+            .into_iter()
+            .flatten()
+            .map(|client| Window {
+                id: str_field(client, "address"),
+                pid: client.get("pid").and_then(|p| p.as_i64()).unwrap_or(0) as i32,
+                title: str_field(client, "title"),
+                class: str_field(client, "class"),
+                geometry: Geometry::from_client(client),
+                focused: client.get("focusHistoryID").and_then(|f| f.as_i64()) == Some(0),
+            })
+            .collect())
+    }
+
+    async fn fullscreen_focused(&self) -> Result<bool> {
+        let window = json("activewindow").await?;
+        // Older Hyprland reports a bool, newer a mode (0 = none)
+        Ok(match window.get("fullscreen") {
+            Some(serde_json::Value::Bool(b)) => *b,
+            Some(v) => v.as_i64().unwrap_or(0) != 0,
+            None => false,
+        })
+    }
+
+    async fn cursor_position(&self) -> Result<(i32, i32)> {
+        let pos = json("cursorpos").await?;
+        let x = pos.get("x").and_then(|v| v.as_i64()).context("cursorpos without x")?;
+        let y = pos.get("y").and_then(|v| v.as_i64()).context("cursorpos without y")?;
+        Ok((x as i32, y as i32))
+    }
+
+    async fn monitors(&self) -> Result<Vec<Monitor>> {
+        let monitors = json("monitors").await?;
+        Ok(monitors.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| Some(Monitor {
+                name: m.get("name")?.as_str()?.to_string(),
+                geometry: Geometry::from_monitor(m)?,
+                focused: m.get("focused").and_then(|f| f.as_bool()) == Some(true),
+            }))
+            .collect())
+    }
+
+    async fn place_window(&self, id: &str, g: Geometry) -> Result<()> {
+        batch(&[
+            format!("dispatch setfloating address:{}", id),
+            format!("dispatch resizewindowpixel exact {} {},address:{}", g.width, g.height, id),
+            format!("dispatch movewindowpixel exact {} {},address:{}", g.x, g.y, id),
+        ]).await
+    }
+
+    async fn move_windows(&self, ids: &[String], dx: i32, dy: i32) -> Result<()> {
+        let commands: Vec<String> = ids.iter()
+            .map(|id| format!("dispatch movewindowpixel {} {},address:{}", dx, dy, id))
+            .collect();
+        batch(&commands).await
+    }
+
+    async fn set_props(&self, id: &str, props: &[(&str, String)]) -> Result<()> {
+        let commands: Vec<String> = props.iter()
+            .map(|(prop, value)| format!("dispatch setprop address:{} {} {} lock", id, prop, value))
+            .collect();
+        batch(&commands).await
+    }
+
+    async fn run_window_commands(&self, id: &str, commands: &[String]) -> Result<()> {
+        // "dispatch ..." entries run as-is; anything else is a setprop
+        let commands: Vec<String> = commands.iter()
+            .map(|raw| if raw.starts_with("dispatch ") {
+                raw.replace("{address}", id)
+            } else {
+                format!("dispatch setprop address:{} {}", id, raw)
+            })
+            .collect();
+        batch(&commands).await
+    }
+
+    async fn close_window(&self, id: &str) -> Result<()> {
+        dispatch(&format!("closewindow address:{}", id)).await
+    }
+
+    async fn jiggle_pointer(&self) -> Result<()> {
+        let (x, y) = self.cursor_position().await?;
+        batch(&[
+            format!("dispatch movecursor {} {}", x + 1, y),
+            format!("dispatch movecursor {} {}", x, y),
+        ]).await
+    }
+
+    async fn focus_changes(&self) -> Result<mpsc::Receiver<String>> {
+        let mut lines = events().await?;
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            while let Ok(Some(line)) = lines.next_line().await {
+                // "activewindowv2>>55d3a8e0e0f0" (empty when nothing has focus)
+                let Some(addr) = line.strip_prefix("activewindowv2>>") else {
+                    continue;
+                };
+                let id = if addr.is_empty() { String::new() } else { format!("0x{}", addr) };
+                if tx.send(id).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(rx)
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast;
+
+use crate::config::Config;
+use crate::ipc::{handle_action, handle_click};
+use crate::menu::MenuManager;
+use crate::modules::{get_status, ModuleStatus};
+
+/// Modules shown on the bar, in configured order (or alphabetically if unset)
+fn bar_modules(config: &Config) -> Vec<String> {
+    if !config.i3bar.modules.is_empty() {
+        return config.i3bar.modules.clone();
+    }
+    let mut names: Vec<String> = config
+        .modules
+        .iter()
+        .filter(|(_, m)| m.enabled)
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Emit the i3bar JSON protocol on stdout and route click events from stdin.
+/// Used by swaybar/i3bar as `status_command waybar-hovermenu --i3bar`.
+pub async fn run(
+    config: Arc<Config>,
+    menu_manager: Arc<MenuManager>,
+    status_tx: broadcast::Sender<(String, String)>,
+) -> Result<()> {
+    let modules = bar_modules(&config);
+    let mut rx = status_tx.subscribe();
+
+    // Initial snapshot of every module
+    let mut statuses: HashMap<String, serde_json::Value> = HashMap::new();
+    for module in &modules {
+        let pinned = menu_manager.is_pinned(module).await;
+        let config = Arc::clone(&config);
+        let module_owned = module.clone();
+        let status = tokio::task::spawn_blocking(move || get_status(&config, &module_owned, pinned))
+            .await
+            .unwrap_or_else(|_| ModuleStatus::new("error"));
+        statuses.insert(module.clone(), serde_json::to_value(&status)?);
+    }
+
+    // Click events arrive on stdin
+    let click_config = Arc::clone(&config);
+    let click_manager = Arc::clone(&menu_manager);
+    let click_tx = status_tx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = read_clicks(click_config, click_manager, click_tx).await {
+            tracing::error!("i3bar click reader error: {}", e);
+        }
+    });
+
+    let mut stdout = tokio::io::stdout();
+    stdout
+        .write_all(b"{\"version\":1,\"click_events\":true}\n[\n")
+        .await?;
+    stdout.write_all(render_line(&modules, &statuses).as_bytes()).await?;
+    stdout.flush().await?;
+
+    loop {
+        match rx.recv().await {
+            Ok((module, json)) => {
+                if !modules.contains(&module) {
+                    continue;
+                }
+                if let Ok(value) = serde_json::from_str(&json) {
+                    statuses.insert(module, value);
+                }
+                stdout.write_all(b",").await?;
+                stdout.write_all(render_line(&modules, &statuses).as_bytes()).await?;
+                stdout.flush().await?;
+            }
+            Err(broadcast::error::RecvError::Lagged(_)) => {
+                // Missed some updates, continue
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Render one status line: a JSON array of blocks followed by a newline
+fn render_line(modules: &[String], statuses: &HashMap<String, serde_json::Value>) -> String {
+    let blocks: Vec<serde_json::Value> = modules
+        .iter()
+        .filter_map(|module| {
+            let status = statuses.get(module)?;
+            let text = status.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            // Empty text hides the module, same as waybar
+            if text.is_empty() {
+                return None;
+            }
+            let mut block = serde_json::json!({
+                "name": module,
+                "full_text": text,
+            });
+            let pinned = status
+                .get("class")
+                .and_then(|c| c.as_array())
+                .is_some_and(|classes| classes.iter().any(|c| c == "pinned"));
+            if pinned {
+                block["border"] = serde_json::json!("#d4a366");
+            }
+            Some(block)
+        })
+        .collect();
+    format!("{}\n", serde_json::Value::Array(blocks))
+}
+
+/// Read i3bar click events and dispatch them like `click`/`action` IPC commands
+async fn read_clicks(
+    config: Arc<Config>,
+    menu_manager: Arc<MenuManager>,
+    status_tx: broadcast::Sender<(String, String)>,
+) -> Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        // The stream is an endless JSON array: "[", then "{...}", then ",{...}"
+        let line = line.trim().trim_start_matches([',', '[']);
+        if line.is_empty() {
+            continue;
+        }
+        let event: serde_json::Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!("Invalid i3bar click event: {}", e);
+                continue;
+            }
+        };
+        let Some(module) = event.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        match event.get("button").and_then(|b| b.as_u64()) {
+            Some(1) => {
+                // Click events carry the absolute cursor position
+                let x = event.get("x").and_then(|x| x.as_i64()).map(|x| x as i32);
+                menu_manager.set_event_x(x).await;
+                if let Err(e) = handle_click(&config, &menu_manager, &status_tx, module, None).await {
+                    tracing::error!("Click error: {}", e);
+                }
+            }
+            Some(3) => {
+                // Actions run to completion; don't hold up later clicks meanwhile
+                let config = Arc::clone(&config);
+                let menu_manager = Arc::clone(&menu_manager);
+                let status_tx = status_tx.clone();
+                let module = module.to_string();
+                tokio::spawn(async move {
+                    handle_action(&config, &menu_manager, &status_tx, &module).await;
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::IconsConfig;
+
+/// Glyph for each icon role in every preset: (role, fontawesome, nerdfont, emoji, ascii)
+const PRESETS: &[(&str, &str, &str, &str, &str)] = &[
+    ("volume-muted", "\u{f6a9}", "\u{f075f}", "🔇", "MUTE"),
+    ("volume-off", "\u{f026}", "\u{f0581}", "🔈", "VOL"),
+    ("volume-low", "\u{f027}", "\u{f057f}", "🔉", "VOL"),
+    ("volume-high", "\u{f028}", "\u{f057e}", "🔊", "VOL"),
+    ("bluetooth", "\u{f293}", "\u{f00af}", "ᛒ", "BT"),
+    ("wifi", "\u{f1eb}", "\u{f05a9}", "📶", "WIFI"),
+    ("ethernet", "\u{f796}", "\u{f0200}", "🌐", "ETH"),
+    ("cpu", "\u{f2db}", "\u{f0ee0}", "💻", "CPU"),
+    ("memory", "\u{f538}", "\u{f035b}", "🧠", "MEM"),
+    ("disk", "\u{f0a0}", "\u{f02ca}", "💾", "DSK"),
+    ("temperature", "\u{f2c9}", "\u{f050f}", "🌡️", "TMP"),
+    ("battery-charging", "\u{f0e7}", "\u{f0084}", "⚡", "CHG"),
+    ("battery-plugged", "\u{f1e6}", "\u{f06a5}", "🔌", "AC"),
+    ("battery-full", "\u{f240}", "\u{f0079}", "🔋", "BAT"),
+    ("battery-three-quarters", "\u{f241}", "\u{f0081}", "🔋", "BAT"),
+    ("battery-half", "\u{f242}", "\u{f007e}", "🔋", "BAT"),
+    ("battery-quarter", "\u{f243}", "\u{f007b}", "🔋", "BAT"),
+    ("battery-empty", "\u{f244}", "\u{f008e}", "🪫", "BAT"),
+    ("mail", "\u{f0e0}", "\u{f01ee}", "✉️", "MAIL"),
+    ("mail-flagged", "\u{f024}", "\u{f023b}", "🚩", "*"),
+    ("calendar", "\u{f073}", "\u{f00ed}", "📅", "CAL"),
+    ("localsend", "\u{2191}\u{2193}", "\u{f04e1}", "⇅", "^v"),
+    ("vpn", "\u{f3ed}", "\u{f0582}", "🛡️", "VPN"),
+    ("tailscale", "\u{f0e8}", "\u{f0c8a}", "🔗", "TS"),
+    ("surfshark", "\u{f21b}", "\u{f05f9}", "🕵️", "SS"),
+    ("brightness", "\u{f185}", "\u{f00e0}", "☀️", "BRI"),
+    ("systemd", "\u{f071}", "\u{f0026}", "⚠️", "!"),
+    ("airplane", "\u{f072}", "\u{f001d}", "✈️", "AIR"),
+    ("hotspot", "\u{f519}", "\u{f0002}", "📡", "AP"),
+    ("dnd-on", "\u{f1f6}", "\u{f009b}", "🔕", "DND"),
+    ("dnd-off", "\u{f0f3}", "\u{f009a}", "🔔", "BELL"),
+    ("notifications", "\u{f0f3}", "\u{f116b}", "🔔", "NTF"),
+    ("idle-inhibited", "\u{f0f4}", "\u{f0176}", "☕", "AWAKE"),
+    ("idle-allowed", "\u{f186}", "\u{f0594}", "💤", "IDLE"),
+    ("nightlight-on", "\u{f186}", "\u{f0594}", "🌙", "NIGHT"),
+    ("nightlight-off", "\u{f185}", "\u{f05a8}", "🔆", "DAY"),
+    ("power", "\u{f011}", "\u{f0425}", "🛑", "PWR"),
+    ("media-playing", "\u{f04b}", "\u{f040a}", "▶️", ">"),
+    ("media-paused", "\u{f04c}", "\u{f03e4}", "⏸️", "||"),
+    ("media-stopped", "\u{f04d}", "\u{f04db}", "⏹️", "[]"),
+    ("updates", "\u{f021}", "\u{f06b0}", "📦", "UPD"),
+    ("weather-clear", "\u{f185}", "\u{f0599}", "☀️", "SUN"),
+    ("weather-cloudy", "\u{f0c2}", "\u{f0590}", "☁️", "CLD"),
+    ("weather-fog", "\u{f75f}", "\u{f0591}", "🌫️", "FOG"),
+    ("weather-rain", "\u{f73d}", "\u{f0597}", "🌧️", "RAIN"),
+    ("weather-snow", "\u{f2dc}", "\u{f0598}", "🌨️", "SNOW"),
+    ("weather-storm", "\u{f0e7}", "\u{f0593}", "⛈️", "STRM"),
+];
+
+const PRESET_NAMES: [&str; 4] = ["fontawesome", "nerdfont", "emoji", "ascii"];
+
+/// Every role's glyph under the `[icons]` config; empty until `init`
+static ICONS: Mutex<Option<HashMap<&'static str, String>>> = Mutex::new(None);
+
+/// Resolve the icon set: the preset's glyphs, then per-role overrides on top
+pub fn init(config: &IconsConfig) {
+    let preset = PRESET_NAMES.iter().position(|p| *p == config.preset).unwrap_or_else(|| {
+        tracing::warn!("Unknown icon preset {:?}, using fontawesome", config.preset);
+        0
+    });
+    let mut icons: HashMap<&'static str, String> = PRESETS
+        .iter()
+        .map(|&(role, fa, nf, emoji, ascii)| (role, [fa, nf, emoji, ascii][preset].to_string()))
+        .collect();
+    for (role, glyph) in &config.overrides {
+        match icons.get_mut(role.as_str()) {
+            Some(icon) => *icon = glyph.clone(),
+            None => tracing::warn!("Unknown icon role {:?} in [icons]", role),
+        }
+    }
+    *ICONS.lock().unwrap() = Some(icons);
+}
+
+/// The glyph for a role, e.g. `get("volume-high")`
+pub fn get(role: &str) -> String {
+    if let Some(icon) = ICONS.lock().unwrap().as_ref().and_then(|icons| icons.get(role)) {
+        return icon.clone();
+    }
+    // Before init (or a role typo), fall back to the built-in FontAwesome glyph
+    PRESETS
+        .iter()
+        .find(|(r, ..)| *r == role)
+        .map(|(_, fa, ..)| fa.to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use futures::StreamExt;
+use std::sync::OnceLock;
+use tokio::sync::watch;
+
+/// Whether the session is idle (or locked), as reported by logind
+static IDLE: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+fn sender() -> &'static watch::Sender<bool> {
+    IDLE.get_or_init(|| watch::channel(false).0)
+}
+
+/// Follow idle state changes. Without a running monitor the session never counts as idle.
+pub fn subscribe() -> watch::Receiver<bool> {
+    sender().subscribe()
+}
+
+/// Track logind's `IdleHint` and `LockedHint` for our session
+pub async fn run() -> Result<()> {
+    let connection = zbus::Connection::system().await?;
+    let session = zbus::Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        "org.freedesktop.login1.Session",
+    )
+    .await?;
+    let mut idle_changes = session.receive_property_changed::<bool>("IdleHint").await;
+    let mut lock_changes = session.receive_property_changed::<bool>("LockedHint").await;
+
+    loop {
+        let idle = session.get_property::<bool>("IdleHint").await.unwrap_or(false);
+        let locked = session.get_property::<bool>("LockedHint").await.unwrap_or(false);
+        sender().send_if_modified(|state| {
+            let changed = *state != (idle || locked);
+            if changed {
+                tracing::debug!("Session {}", if idle || locked { "idle, pausing pollers" } else { "active again" });
+            }
+            *state = idle || locked;
+            changed
+        });
+
+        tokio::select! {
+            Some(_) = idle_changes.next() => {}
+            Some(_) = lock_changes.next() => {}
+            else => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::{bail, Result};
+use std::sync::Mutex;
+use zbus::zvariant::OwnedFd;
+
+/// The logind inhibitor lock the daemon holds; closing the fd releases it
+static LOCK: Mutex<Option<OwnedFd>> = Mutex::new(None);
+
+/// Whether the daemon is keeping the session awake
+pub fn is_active() -> bool {
+    LOCK.lock().unwrap().is_some()
+}
+
+/// Take or release the inhibitor: "on", "off" or "toggle". `what` is logind's
+/// colon-separated list of what to block, e.g. "idle" or "idle:sleep".
+pub fn set(mode: &str, what: &str) -> Result<()> {
+    let mut lock = LOCK.lock().unwrap();
+    let active = match mode {
+        "on" => true,
+        "off" => false,
+        "toggle" => lock.is_none(),
+        _ => bail!("Unknown idle-inhibit mode {:?}, expected on, off or toggle", mode),
+    };
+    if !active {
+        if lock.take().is_some() {
+            tracing::info!("Idle inhibitor released");
+        }
+        return Ok(());
+    }
+    if lock.is_none() {
+        *lock = Some(acquire(what)?);
+        tracing::info!("Idle inhibitor taken ({})", what);
+    }
+    Ok(())
+}
+
+/// Ask logind for a blocking inhibitor, like `systemd-inhibit --what=<what>`
+fn acquire(what: &str) -> Result<OwnedFd> {
+    let connection = zbus::blocking::Connection::system()?;
+    let manager = zbus::blocking::Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    let fd: OwnedFd = manager.call("Inhibit", &(what, "waybar-hovermenu", "Keeping the screen awake", "block"))?;
+    Ok(fd)
+}
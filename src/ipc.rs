@@ -1,12 +1,27 @@
-use anyhow::Result;
-use std::sync::Arc;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{UnixListener, UnixStream};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, Notify};
 
+use crate::cache;
 use crate::config::Config;
+use crate::dnd;
+use crate::followers;
+use crate::groups;
+use crate::icons;
+use crate::launcher;
+use crate::logging;
 use crate::menu::MenuManager;
-use crate::modules::{execute_action, get_status};
+use crate::modules::{execute_action, get_status, notify_failure, scroll};
+use crate::osd;
+use crate::profile;
+use crate::simulate;
+use crate::watchers;
 
 /// IPC server that listens on a Unix socket
 pub struct IpcServer {
@@ -17,8 +32,11 @@ pub struct IpcServer {
 }
 
 impl IpcServer {
-    pub fn new(config: Arc<Config>, menu_manager: Arc<MenuManager>) -> Self {
-        let (status_tx, _) = broadcast::channel(100);
+    pub fn new(
+        config: Arc<Config>,
+        menu_manager: Arc<MenuManager>,
+        status_tx: broadcast::Sender<(String, String)>,
+    ) -> Self {
         Self {
             config,
             menu_manager,
@@ -35,16 +53,14 @@ impl IpcServer {
     pub async fn run(&self) -> Result<()> {
         let socket_path = &self.config.daemon.socket_path;
         
-        // Remove existing socket if present
-        let _ = std::fs::remove_file(socket_path);
-        
         let listener = UnixListener::bind(socket_path)?;
         tracing::info!("IPC server listening on {}", socket_path);
         
         loop {
             match listener.accept().await {
                 Ok((stream, _)) => {
-                    let config = Arc::clone(&self.config);
+                    // The current config, which changes on reload
+                    let config = self.menu_manager.config();
                     let menu_manager = Arc::clone(&self.menu_manager);
                     let status_tx = self.status_tx.clone();
                     
@@ -60,13 +76,87 @@ impl IpcServer {
             }
         }
     }
+}
+
+/// Set off by the `quit` command
+static QUIT: Notify = Notify::const_new();
+
+/// Resolves once a client has asked the daemon to quit
+pub async fn quit_requested() {
+    QUIT.notified().await
+}
+
+/// Free the socket path for binding. A stale socket left by a crashed daemon is removed,
+/// but a live daemon is only displaced (asked to quit) when `replace` is set.
+pub async fn prepare_socket(socket_path: &str, replace: bool) -> Result<()> {
+    if let Ok(mut stream) = UnixStream::connect(socket_path).await {
+        if !replace {
+            bail!(
+                "waybar-hovermenu is already running on {} (use --replace to take over)",
+                socket_path
+            );
+        }
+        
+        tracing::info!("Replacing the daemon running on {}", socket_path);
+        stream.write_all(b"quit\n").await?;
+        
+        // Wait for it to let go of the socket
+        let mut gone = false;
+        for _ in 0..50 {
+            if UnixStream::connect(socket_path).await.is_err() {
+                gone = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        if !gone {
+            bail!("The daemon running on {} did not quit", socket_path);
+        }
+    }
     
-    /// Broadcast a status update for a module
-    pub fn broadcast_status(&self, module: &str) {
-        let pinned = futures::executor::block_on(self.menu_manager.is_pinned(module));
-        let status = get_status(module, pinned);
-        let json = status.to_json();
-        let _ = self.status_tx.send((module.to_string(), json));
+    // Nobody is listening - whatever is left at the path is stale
+    let _ = std::fs::remove_file(socket_path);
+    Ok(())
+}
+
+/// Version of the JSON protocol; a request asking for a newer one is refused
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Commands understood by both protocols
+const COMMANDS: &[&str] = &[
+    "follow", "status", "tooltip", "list", "open", "close", "close-all", "pin", "unpin",
+    "hover", "leave", "click", "tap", "hold", "action", "middle-click", "long-press",
+    "press", "release", "scroll-up", "scroll-down", "dnd", "log", "simulate", "quit",
+];
+
+/// A JSON request, e.g. `{"cmd":"click","module":"audio"}`.
+/// `args` carries what follows the module (and bar) in a plain-text command.
+#[derive(Deserialize)]
+struct Request {
+    /// Echoed back in the response, so clients can match them up
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    /// Protocol version the client expects (`PROTOCOL_VERSION` if missing)
+    #[serde(default)]
+    version: Option<u32>,
+    cmd: String,
+    #[serde(default)]
+    module: Option<String>,
+    #[serde(default)]
+    bar: Option<String>,
+    /// Cursor x at the event, for menus placed under their widget
+    #[serde(default)]
+    x: Option<i32>,
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+impl Request {
+    /// The same request as a plain-text command line
+    fn to_line(&self) -> String {
+        let x = self.x.map(|x| x.to_string());
+        let words = [Some(&self.cmd), self.module.as_ref(), self.bar.as_ref(), x.as_ref()];
+        words.into_iter().flatten().chain(&self.args).cloned().collect::<Vec<_>>().join(" ")
     }
 }
 
@@ -76,133 +166,768 @@ async fn handle_client(
     menu_manager: Arc<MenuManager>,
     status_tx: broadcast::Sender<(String, String)>,
 ) -> Result<()> {
-    let (reader, mut writer) = stream.into_split();
-    let mut reader = BufReader::new(reader);
+    let peer_pid = stream.peer_cred().ok().and_then(|c| c.pid());
+    let (reader, writer) = stream.into_split();
+    let mut client = Client { reader: BufReader::new(reader), writer, peer_pid };
     let mut line = String::new();
     
     // Read the first line to determine the command
-    reader.read_line(&mut line).await?;
+    client.reader.read_line(&mut line).await?;
     let line = line.trim();
     
+    // JSON clients send an object per line; anything else is a plain-text command
+    if line.starts_with('{') {
+        return client.serve_json(line, &config, &menu_manager, &status_tx).await;
+    }
+    
     let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.is_empty() {
-        return Ok(());
+    match parts.as_slice() {
+        [] => {}
+        ["follow"] => {}
+        ["follow", module, ..] => {
+            // Stream status updates for a module
+            follow(&config, &menu_manager, &status_tx, module, client, false).await;
+        }
+        _ => match dispatch(&config, &menu_manager, &status_tx, line).await {
+            Ok(Some(json)) => write_line(&mut client.writer, &json).await?,
+            Ok(None) => {}
+            Err(e) => tracing::warn!("{:?} failed: {:#}", line, e),
+        },
     }
     
-    let command = parts[0];
+    Ok(())
+}
+
+/// One connected client
+struct Client {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    /// Who connected, so follow streams can tell when the client has died
+    peer_pid: Option<i32>,
+}
+
+impl Client {
+    /// Speak the JSON protocol: one request per line, each answered with
+    /// `{"ok":true}` (plus a `result` for queries) or `{"ok":false,"error":"..."}`
+    async fn serve_json(
+        mut self,
+        first: &str,
+        config: &Arc<Config>,
+        menu_manager: &Arc<MenuManager>,
+        status_tx: &broadcast::Sender<(String, String)>,
+    ) -> Result<()> {
+        let mut line = first.to_string();
+        loop {
+            let request = match serde_json::from_str::<Request>(line.trim()) {
+                _ if line.trim().is_empty() => None,
+                Ok(request) => Some(request),
+                Err(e) => {
+                    self.reply(None, Err(anyhow::anyhow!("Invalid request: {}", e))).await?;
+                    None
+                }
+            };
+            if let Some(request) = request {
+                let id = request.id.clone();
+                let version = request.version.unwrap_or(PROTOCOL_VERSION);
+                if version > PROTOCOL_VERSION {
+                    let error = anyhow::anyhow!(
+                        "Protocol version {} not supported, the daemon speaks {}",
+                        version, PROTOCOL_VERSION
+                    );
+                    self.reply(id, Err(error)).await?;
+                    return Ok(());
+                }
+                
+                match request.cmd.as_str() {
+                    "hello" => {
+                        let hello = serde_json::json!({
+                            "version": PROTOCOL_VERSION,
+                            "commands": COMMANDS,
+                        });
+                        self.reply(id, Ok(Some(hello))).await?;
+                    }
+                    "follow" => {
+                        // The connection becomes a stream of {"module":...,"status":{...}} lines
+                        let Some(module) = request.module else {
+                            self.reply(id, Err(anyhow::anyhow!("follow needs a module"))).await?;
+                            return Ok(());
+                        };
+                        self.reply(id, Ok(None)).await?;
+                        follow(config, menu_manager, status_tx, &module, self, true).await;
+                        return Ok(());
+                    }
+                    _ => {
+                        let result = dispatch(config, menu_manager, status_tx, &request.to_line()).await;
+                        let result = result.map(|json| json.map(|j| serde_json::from_str(&j).unwrap_or_default()));
+                        self.reply(id, result).await?;
+                    }
+                }
+            }
+            
+            line.clear();
+            if self.reader.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+        }
+    }
+    
+    async fn reply(
+        &mut self,
+        id: Option<serde_json::Value>,
+        result: Result<Option<serde_json::Value>>,
+    ) -> std::io::Result<()> {
+        let mut response = match result {
+            Ok(None) => serde_json::json!({ "ok": true }),
+            Ok(Some(result)) => serde_json::json!({ "ok": true, "result": result }),
+            Err(e) => serde_json::json!({ "ok": false, "error": format!("{:#}", e) }),
+        };
+        if let Some(id) = id {
+            response["id"] = id;
+        }
+        write_line(&mut self.writer, &response.to_string()).await
+    }
+}
+
+/// Run one command other than `follow`, returning the JSON a query answers with
+async fn dispatch(
+    config: &Arc<Config>,
+    menu_manager: &Arc<MenuManager>,
+    status_tx: &broadcast::Sender<(String, String)>,
+    line: &str,
+) -> Result<Option<String>> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let Some(&command) = parts.first() else {
+        return Ok(None);
+    };
     let module = parts.get(1).copied();
+    // Which bar the event came from, for setups with several, then the cursor x
+    // (either may be left out: `hover audio top 1840`, `hover audio 1840`)
+    let (bar, x) = match parts.get(2).map(|p| (*p, p.parse::<i32>())) {
+        Some((_, Ok(x))) => (None, Some(x)),
+        Some((bar, Err(_))) => (Some(bar), parts.get(3).and_then(|p| p.parse().ok())),
+        None => (None, None),
+    };
+    if matches!(command, "hover" | "click" | "tap" | "hold" | "open" | "release") {
+        menu_manager.set_event_x(x).await;
+    }
+    
+    // A group cycles on its trigger; every other command goes to the member it shows
+    if let Some((name, group)) = module.and_then(|m| config.groups.get_key_value(m)) {
+        let trigger = match command {
+            "scroll-up" | "scroll-down" => "scroll",
+            "click" | "tap" => "click",
+            _ => "",
+        };
+        if trigger == group.cycle_on {
+            if let Some(member) = groups::cycle(config, name, command != "scroll-up") {
+                tracing::debug!("Group {} now shows {}", name, member);
+                let json = current_status(config, menu_manager, &member).await;
+                let _ = status_tx.send((name.clone(), json));
+            }
+            return Ok(None);
+        }
+    }
+    let member = module.map(|m| groups::resolve(config, m));
+    let module = member.as_deref();
+    let needs_module = || module.with_context(|| format!("{} needs a module", command));
     
     match command {
-        "follow" => {
-            // Stream status updates for a module
-            if let Some(module) = module {
-                let mut rx = status_tx.subscribe();
-                
-                // Send initial status (use spawn_blocking since get_status does blocking I/O)
-                let pinned = if config.daemon.hover {
-                    menu_manager.is_pinned(module).await
-                } else {
-                    menu_manager.is_menu_open(module).await
-                };
-                let module_owned = module.to_string();
-                let status = tokio::task::spawn_blocking(move || {
-                    get_status(&module_owned, pinned)
-                }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
-                writer.write_all(status.to_json().as_bytes()).await?;
-                writer.write_all(b"\n").await?;
-                writer.flush().await?;
-                
-                // Stream updates
-                loop {
-                    match rx.recv().await {
-                        Ok((update_module, json)) => {
-                            if update_module == module {
-                                writer.write_all(json.as_bytes()).await?;
-                                writer.write_all(b"\n").await?;
-                                writer.flush().await?;
-                            }
-                        }
-                        Err(broadcast::error::RecvError::Lagged(_)) => {
-                            // Missed some updates, continue
-                        }
-                        Err(broadcast::error::RecvError::Closed) => {
-                            break;
-                        }
+        "status" => {
+            // One-shot status query
+            if matches!(module, Some("all" | "--all")) {
+                // Every enabled module at once, as {"module": status, ...}
+                let mut tasks = watchers::gather(config, menu_manager);
+                let mut all = serde_json::Map::new();
+                while let Some(result) = tasks.join_next().await {
+                    if let Ok(Some((name, status))) = result {
+                        let value = serde_json::from_str(&status.to_json()).unwrap_or_default();
+                        all.insert(name, value);
                     }
                 }
+                return Ok(Some(serde_json::Value::Object(all).to_string()));
             }
+            let module = needs_module()?;
+            return Ok(Some(current_status(config, menu_manager, module).await));
         }
         
-        "status" => {
-            // One-shot status query (use spawn_blocking since get_status does blocking I/O)
-            if let Some(module) = module {
-                let pinned = if config.daemon.hover {
-                    menu_manager.is_pinned(module).await
-                } else {
-                    menu_manager.is_menu_open(module).await
-                };
-                let module_owned = module.to_string();
-                let status = tokio::task::spawn_blocking(move || {
-                    get_status(&module_owned, pinned)
-                }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
-                writer.write_all(status.to_json().as_bytes()).await?;
-                writer.write_all(b"\n").await?;
+        "tooltip" => {
+            // One-shot tooltip query, in markup and plain text
+            let cfg = Arc::clone(config);
+            let module_owned = needs_module()?.to_string();
+            let status = tokio::task::spawn_blocking(move || {
+                get_status(&cfg, &module_owned, false)
+            }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+            let json = serde_json::json!({
+                "markup": status.tooltip,
+                "plain": status.plain_tooltip(),
+            });
+            return Ok(Some(json.to_string()));
+        }
+        
+        "list" => {
+            // Every configured module, for clients discovering what they can control
+            let mut names: Vec<&String> = config.modules.keys().collect();
+            names.sort();
+            let mut modules = Vec::new();
+            for name in names {
+                let module_config = &config.modules[name];
+                modules.push(serde_json::json!({
+                    "name": name,
+                    "kind": module_config.kind,
+                    "enabled": module_config.enabled && !profile::is_disabled(config, name),
+                    "open": menu_manager.is_menu_open(name).await,
+                    "pinned": menu_manager.is_pinned(name).await,
+                }));
+            }
+            return Ok(Some(serde_json::Value::Array(modules).to_string()));
+        }
+        
+        "open" => {
+            let module = needs_module()?;
+            let result = MenuManager::open(menu_manager, module, bar).await;
+            broadcast_highlight(config, menu_manager, status_tx, module).await;
+            result?;
+        }
+        
+        "close" => {
+            let module = needs_module()?;
+            let result = menu_manager.close(module).await;
+            broadcast_highlight(config, menu_manager, status_tx, module).await;
+            result?;
+        }
+        
+        "close-all" => {
+            let open = menu_manager.open_module().await;
+            let result = menu_manager.close_all().await;
+            if let Some(module) = open {
+                broadcast_highlight(config, menu_manager, status_tx, &module).await;
+            }
+            result?;
+        }
+        
+        "pin" => {
+            // pin [module]: without one, whatever menu is open
+            let pinned = MenuManager::pin(menu_manager, module).await?;
+            broadcast_highlight(config, menu_manager, status_tx, &pinned).await;
+        }
+        
+        "unpin" => {
+            if let Some(unpinned) = menu_manager.unpin(module).await? {
+                broadcast_highlight(config, menu_manager, status_tx, &unpinned).await;
             }
         }
         
         "hover" => {
-            if let Some(module) = module {
-                if let Err(e) = MenuManager::hover(&menu_manager, module).await {
-                    tracing::error!("Hover error: {}", e);
+            menu_manager.set_touch(false);
+            MenuManager::hover(menu_manager, needs_module()?, bar).await?;
+        }
+        
+        "leave" => menu_manager.leave().await?,
+        
+        "click" | "tap" => {
+            // A tap is a click from the touchscreen
+            let module = needs_module()?;
+            menu_manager.set_touch(command == "tap");
+            handle_click(config, menu_manager, status_tx, module, bar).await?;
+        }
+        
+        "hold" => {
+            // Tap-and-hold pins, the touch stand-in for hover then click
+            let module = needs_module()?;
+            menu_manager.set_touch(true);
+            let result = MenuManager::hold(menu_manager, module, bar).await;
+            broadcast_highlight(config, menu_manager, status_tx, module).await;
+            result?;
+        }
+        
+        "action" => handle_action(config, menu_manager, status_tx, needs_module()?).await,
+        
+        "middle-click" => {
+            let module = needs_module()?;
+            let module_config = config.get_module(module).context("Module not found")?;
+            if let Some(action) = &module_config.middle_action {
+                let confirm = module_config.confirm_prompt();
+                run_action(config, menu_manager, status_tx, module, action, confirm).await;
+            }
+        }
+        
+        "long-press" => handle_long_press(config, menu_manager, status_tx, needs_module()?).await,
+        
+        "press" => menu_manager.press(needs_module()?).await,
+        
+        "release" => {
+            // Infer click vs long-press from how long the widget was held
+            let module = needs_module()?;
+            let long_press = Duration::from_millis(config.daemon.long_press_ms);
+            match menu_manager.release(module).await {
+                Some(held) if held >= long_press => {
+                    handle_long_press(config, menu_manager, status_tx, module).await;
                 }
+                Some(_) => handle_click(config, menu_manager, status_tx, module, bar).await?,
+                None => tracing::debug!("Release on {} without press", module),
+            }
+        }
+        
+        "scroll-up" | "scroll-down" => {
+            let module = needs_module()?;
+            handle_scroll(config, menu_manager, status_tx, module, command == "scroll-up").await;
+        }
+        
+        "dnd" => {
+            // dnd [on|off|toggle|auto]
+            if !dnd::set(module.unwrap_or("toggle")) {
+                bail!("Unknown dnd mode: {}", module.unwrap_or_default());
             }
+            tracing::info!("Do-not-disturb {}", if dnd::is_active() { "on" } else { "off" });
+            let cfg = Arc::clone(config);
+            let status = tokio::task::spawn_blocking(move || get_status(&cfg, "dnd", false))
+                .await
+                .unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+            watchers::publish(config, status_tx, "dnd", &status);
         }
         
-        "leave" => {
-            if let Err(e) = menu_manager.leave().await {
-                tracing::error!("Leave error: {}", e);
+        "simulate" => {
+            if !simulate::is_enabled() {
+                bail!("Ignoring simulate: start the daemon with --debug to allow it");
             }
+            simulate_event(config, menu_manager, status_tx, line, &parts).await;
+        }
+        
+        "log" => {
+            // log <module|target|reset> [level|reset]
+            let target = parts.get(1).copied().unwrap_or("reset");
+            let level = parts.get(2).copied().unwrap_or("reset");
+            logging::set_level(target, level).context("Log level change failed")?;
+            tracing::info!("Log level for {} set to {}", target, level);
         }
         
-        "click" => {
-            if let Some(module) = module {
-                if let Err(e) = MenuManager::click(&menu_manager, module).await {
-                    tracing::error!("Click error: {}", e);
+        "quit" => {
+            // Another instance is taking over; shut down as on SIGTERM
+            tracing::info!("Asked to quit");
+            QUIT.notify_one();
+        }
+        
+        _ => bail!("Unknown command: {}", command),
+    }
+    
+    Ok(None)
+}
+
+/// Inject a synthetic event (`--debug` only):
+/// `simulate status <module> <json>|clear`, `simulate cursor <x> <y>|clear`, `simulate clear`
+async fn simulate_event(
+    config: &Arc<Config>,
+    menu_manager: &MenuManager,
+    status_tx: &broadcast::Sender<(String, String)>,
+    line: &str,
+    parts: &[&str],
+) {
+    let mut changed = Vec::new();
+    match parts.get(1..).unwrap_or_default() {
+        ["status", module, "clear"] => {
+            simulate::set_status(module, None);
+            changed.push(module.to_string());
+        }
+        ["status", module, ..] => {
+            // The JSON may contain spaces, so take the rest of the line
+            let json = line.find('{').map_or("", |i| &line[i..]);
+            match serde_json::from_str(json) {
+                Ok(status) => {
+                    simulate::set_status(module, Some(status));
+                    changed.push(module.to_string());
                 }
-                // Broadcast status update to reflect active state
-                // When hover is disabled, highlight based on menu being open
-                // When hover is enabled, highlight based on pin state
-                let highlighted = if config.daemon.hover {
-                    menu_manager.is_pinned(module).await
-                } else {
-                    menu_manager.is_menu_open(module).await
-                };
-                let status = get_status(module, highlighted);
-                let _ = status_tx.send((module.to_string(), status.to_json()));
-            }
-        }
-        
-        "action" => {
-            if let Some(module) = module {
-                if let Some(module_config) = config.get_module(module) {
-                    if let Some(action) = &module_config.action {
-                        if let Err(e) = execute_action(action) {
-                            tracing::error!("Action error: {}", e);
-                        }
-                        // Give the action time to complete, then broadcast update
-                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                        let pinned = menu_manager.is_pinned(module).await;
-                        let status = get_status(module, pinned);
-                        let _ = status_tx.send((module.to_string(), status.to_json()));
+                Err(e) => tracing::warn!("Invalid simulated status for {}: {}", module, e),
+            }
+        }
+        ["cursor", "clear"] => simulate::set_cursor(None),
+        ["cursor", x, y] => match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => simulate::set_cursor(Some((x, y))),
+            _ => tracing::warn!("Invalid simulated cursor position: {} {}", x, y),
+        },
+        ["clear"] => changed = simulate::clear(),
+        _ => tracing::warn!("Unknown simulate command: {}", line),
+    }
+    
+    // Push the new (or restored) statuses to the bar
+    for module in changed {
+        let pinned = menu_manager.is_pinned(&module).await;
+        let cfg = Arc::clone(config);
+        let module_owned = module.clone();
+        let status = tokio::task::spawn_blocking(move || get_status(&cfg, &module_owned, pinned))
+            .await
+            .unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+        watchers::publish(config, status_tx, &module, &status);
+    }
+}
+
+/// Stream a module's status to a follow client until it goes away
+async fn follow(
+    config: &Arc<Config>,
+    menu_manager: &Arc<MenuManager>,
+    status_tx: &broadcast::Sender<(String, String)>,
+    module: &str,
+    mut client: Client,
+    json: bool,
+) {
+    let mut rx = status_tx.subscribe();
+    let mut liveness = tokio::time::interval(FOLLOW_LIVENESS_INTERVAL);
+    let mut rest = String::new();
+    
+    // A bar restarting reconnects all its widgets at once; re-prime them when it's done
+    let (_guard, wave) = followers::connect(module);
+    {
+        let config = Arc::clone(config);
+        let menu_manager = Arc::clone(menu_manager);
+        let status_tx = status_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(followers::WAVE_SETTLE).await;
+            if let Some(names) = followers::settled_wave(wave) {
+                tracing::info!("Bar reconnected {} widgets, re-priming them", names.len());
+                reprime(&config, &menu_manager, &status_tx, names).await;
+            }
+        });
+    }
+    
+    // Send initial status (for a group, that of the member it shows)
+    let member = groups::resolve(config, module);
+    let mut pending = Some(current_status(config, menu_manager, &member).await);
+    
+    loop {
+        if let Some(status) = pending.take() {
+            // JSON protocol clients get the module alongside each status
+            let line = if json {
+                let status: serde_json::Value = serde_json::from_str(&status).unwrap_or_default();
+                serde_json::json!({ "module": module, "status": status }).to_string()
+            } else {
+                status
+            };
+            if let Err(e) = write_line(&mut client.writer, &line).await {
+                tracing::info!("Dropping follow client for {}: {}", module, e);
+                return;
+            }
+        }
+        
+        tokio::select! {
+            update = rx.recv() => match update {
+                Ok((update_module, json)) => {
+                    if update_module == module || update_module == groups::resolve(config, module) {
+                        pending = Some(json);
                     }
                 }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    // Missed some updates - the last one may have been ours, so resync
+                    tracing::debug!("Follower of {} lagged by {}, resyncing", module, missed);
+                    let member = groups::resolve(config, module);
+                    pending = Some(current_status(config, menu_manager, &member).await);
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            // EOF or a read error means the client hung up; stray input after the
+            // command (an extra newline, say) is ignored
+            read = client.reader.read_line(&mut rest) => match read {
+                Ok(0) | Err(_) => {
+                    tracing::info!("Follow client for {} disconnected", module);
+                    return;
+                }
+                Ok(_) => rest.clear(),
+            },
+            _ = liveness.tick() => {
+                if !peer_alive(client.peer_pid) {
+                    tracing::info!("Follow client for {} (pid {:?}) died, dropping it", module, client.peer_pid);
+                    return;
+                }
             }
         }
-        
-        _ => {
-            tracing::warn!("Unknown command: {}", command);
+    }
+}
+
+/// Push a freshly computed status for every followed widget, bypassing the cache
+async fn reprime(
+    config: &Arc<Config>,
+    menu_manager: &Arc<MenuManager>,
+    status_tx: &broadcast::Sender<(String, String)>,
+    names: Vec<String>,
+) {
+    let tasks: Vec<_> = names
+        .into_iter()
+        .map(|name| {
+            let config = Arc::clone(config);
+            let menu_manager = Arc::clone(menu_manager);
+            let status_tx = status_tx.clone();
+            tokio::spawn(async move {
+                let member = groups::resolve(&config, &name);
+                let json = fresh_status(&config, &menu_manager, &member).await;
+                let _ = status_tx.send((name, json));
+            })
+        })
+        .collect();
+    for task in tasks {
+        let _ = task.await;
+    }
+}
+
+/// How often follow streams check that their client process still exists
+const FOLLOW_LIVENESS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether a client process still exists (unknown pids count as alive)
+fn peer_alive(pid: Option<i32>) -> bool {
+    let Some(pid) = pid else {
+        return true;
+    };
+    // Signal 0 only checks for existence; EPERM still means the process is there
+    let exists = unsafe { libc::kill(pid, 0) == 0 };
+    exists || std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+async fn write_line(writer: &mut OwnedWriteHalf, json: &str) -> std::io::Result<()> {
+    writer.write_all(json.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await
+}
+
+/// A module's status JSON, served from the cache while it is younger than
+/// `daemon.status_max_age_ms` and recomputed otherwise
+async fn current_status(config: &Arc<Config>, menu_manager: &MenuManager, module: &str) -> String {
+    // When hover is disabled, highlight based on menu being open
+    let highlighted = if menu_manager.hover_enabled() {
+        menu_manager.is_pinned(module).await
+    } else {
+        menu_manager.is_menu_open(module).await
+    };
+    
+    // Highlighted modules are always recomputed so the pinned class is current
+    let max_age = Duration::from_millis(config.daemon.status_max_age_ms);
+    if !highlighted {
+        if let Some(json) = cache::get(module, max_age) {
+            return json;
         }
     }
     
-    Ok(())
+    fresh_status(config, menu_manager, module).await
+}
+
+/// A module's status JSON, recomputed (and cached unless highlighted)
+async fn fresh_status(config: &Arc<Config>, menu_manager: &MenuManager, module: &str) -> String {
+    let highlighted = if menu_manager.hover_enabled() {
+        menu_manager.is_pinned(module).await
+    } else {
+        menu_manager.is_menu_open(module).await
+    };
+    
+    // Use spawn_blocking since get_status does blocking I/O
+    let cfg = Arc::clone(config);
+    let module_owned = module.to_string();
+    let json = tokio::task::spawn_blocking(move || {
+        get_status(&cfg, &module_owned, highlighted)
+    }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error")).to_json();
+    if !highlighted {
+        cache::store(module, &json);
+    }
+    json
+}
+
+/// Toggle a module's menu and broadcast its refreshed status
+pub async fn handle_click(
+    config: &Arc<Config>,
+    menu_manager: &Arc<MenuManager>,
+    status_tx: &broadcast::Sender<(String, String)>,
+    module: &str,
+    bar: Option<&str>,
+) -> Result<()> {
+    let result = MenuManager::click(menu_manager, module, bar).await;
+    broadcast_highlight(config, menu_manager, status_tx, module).await;
+    result
+}
+
+/// Broadcast a module's status after its menu opened, closed or got pinned
+async fn broadcast_highlight(
+    config: &Arc<Config>,
+    menu_manager: &MenuManager,
+    status_tx: &broadcast::Sender<(String, String)>,
+    module: &str,
+) {
+    // Broadcast status update to reflect active state
+    // When hover is disabled, highlight based on menu being open
+    // When hover is enabled, highlight based on pin state
+    let highlighted = if menu_manager.hover_enabled() {
+        menu_manager.is_pinned(module).await
+    } else {
+        menu_manager.is_menu_open(module).await
+    };
+    let cfg = Arc::clone(config);
+    let module_owned = module.to_string();
+    let status = tokio::task::spawn_blocking(move || get_status(&cfg, &module_owned, highlighted))
+        .await
+        .unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+    watchers::publish(config, status_tx, module, &status);
+}
+
+/// Run a module's quick action and broadcast its refreshed status.
+/// Modules with named `actions` show a context popup to pick one first.
+pub async fn handle_action(
+    config: &Arc<Config>,
+    menu_manager: &MenuManager,
+    status_tx: &broadcast::Sender<(String, String)>,
+    module: &str,
+) {
+    let Some(module_config) = config.get_module(module) else {
+        return;
+    };
+
+    let action = if module_config.actions.is_empty() {
+        module_config.action.clone().map(|a| (a, module_config.confirm_prompt()))
+    } else {
+        let labels: Vec<String> = module_config.actions.iter().map(|a| a.label()).collect();
+        match launcher::pick(&config.daemon, module, &labels).await {
+            Ok(choice) => choice.and_then(|c| {
+                module_config
+                    .actions
+                    .iter()
+                    .find(|a| a.label() == c)
+                    .map(|a| (a.command.clone(), a.confirm_prompt()))
+            }),
+            Err(e) => {
+                tracing::error!("Context popup error: {}", e);
+                None
+            }
+        }
+    };
+
+    if let Some((action, confirm)) = action {
+        run_action(config, menu_manager, status_tx, module, &action, confirm).await;
+    }
+}
+
+/// Run a module's long-press action, if it has one
+async fn handle_long_press(
+    config: &Arc<Config>,
+    menu_manager: &MenuManager,
+    status_tx: &broadcast::Sender<(String, String)>,
+    module: &str,
+) {
+    let Some(module_config) = config.get_module(module) else {
+        return;
+    };
+    if let Some(action) = &module_config.long_press_action {
+        let confirm = module_config.confirm_prompt();
+        run_action(config, menu_manager, status_tx, module, action, confirm).await;
+    }
+}
+
+/// Execute an action for a module, then broadcast its refreshed status.
+/// With a `confirm` prompt, the user has to confirm via the launcher first.
+async fn run_action(
+    config: &Arc<Config>,
+    menu_manager: &MenuManager,
+    status_tx: &broadcast::Sender<(String, String)>,
+    module: &str,
+    action: &str,
+    confirm: Option<String>,
+) {
+    if let Some(prompt) = confirm {
+        match launcher::confirm(&config.daemon, &prompt).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => {
+                tracing::error!("Confirm popup error: {}", e);
+                return;
+            }
+        }
+    }
+    
+    // Locked from when the action starts, so an open confirm prompt doesn't count
+    let cooldown = config.get_module(module).map_or(0, |m| m.action_cooldown_ms);
+    let Some(_guard) = ActionGuard::claim(module, action, Duration::from_millis(cooldown)) else {
+        tracing::info!("Ignoring {} action {:?}: still running or cooling down", module, action);
+        return;
+    };
+    
+    // Wait for the action to finish so the broadcast reflects its effect
+    if let Err(e) = execute_action(config, module, action).await {
+        tracing::error!("Action error: {:#}", e);
+        if config.get_module(module).is_some_and(|m| m.notify_on_failure) {
+            notify_failure(module, &e);
+        }
+    }
+    let pinned = menu_manager.is_pinned(module).await;
+    let cfg = Arc::clone(config);
+    let module_owned = module.to_string();
+    let status = tokio::task::spawn_blocking(move || get_status(&cfg, &module_owned, pinned))
+        .await
+        .unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+    if let Some(hook) = config.get_module(module).and_then(|m| m.osd_hook.as_ref()) {
+        osd::run_hook(hook, &status, config.use_shell(module));
+    }
+    watchers::publish(config, status_tx, module, &status);
+}
+
+/// A module and one of its action commands
+type ActionKey = (String, String);
+
+/// State for `action_cooldown_ms`, per action: when it last started, and whether
+/// it is still running
+static ACTIONS: OnceLock<Mutex<HashMap<ActionKey, (Instant, bool)>>> = OnceLock::new();
+
+/// Marks a module's action as running until dropped
+struct ActionGuard(ActionKey);
+
+impl ActionGuard {
+    /// Start an action unless the same action of the module is still running or
+    /// started less than `cooldown` ago. A zero cooldown never blocks.
+    fn claim(module: &str, action: &str, cooldown: Duration) -> Option<Self> {
+        let key = (module.to_string(), action.to_string());
+        let mut actions = ACTIONS.get_or_init(Default::default).lock().unwrap();
+        if !cooldown.is_zero() {
+            if let Some((started, running)) = actions.get(&key) {
+                if *running || started.elapsed() < cooldown {
+                    return None;
+                }
+            }
+        }
+        actions.insert(key.clone(), (Instant::now(), true));
+        Some(Self(key))
+    }
+}
+
+impl Drop for ActionGuard {
+    fn drop(&mut self) {
+        let mut actions = ACTIONS.get_or_init(Default::default).lock().unwrap();
+        if let Some((_, running)) = actions.get_mut(&self.0) {
+            *running = false;
+        }
+    }
+}
+
+/// Adjust a module's level (volume, brightness) and broadcast the fresh status right away
+async fn handle_scroll(
+    config: &Arc<Config>,
+    menu_manager: &MenuManager,
+    status_tx: &broadcast::Sender<(String, String)>,
+    module: &str,
+    up: bool,
+) {
+    let Some(module_config) = config.get_module(module).cloned() else {
+        return;
+    };
+
+    let config_owned = Arc::clone(config);
+    let module_owned = module.to_string();
+    let pinned = menu_manager.is_pinned(module).await;
+    let result = tokio::task::spawn_blocking(move || {
+        scroll(&module_owned, &module_config, up)
+            .map(|level| (level, get_status(&config_owned, &module_owned, pinned)))
+    })
+    .await;
+
+    match result {
+        Ok(Ok((level, status))) => {
+            // The audio watcher already flashes the OSD for sink changes
+            if module == "brightness" {
+                osd::show(&config.osd, "brightness", level, &icons::get("brightness"));
+            }
+            watchers::publish(config, status_tx, module, &status);
+        }
+        Ok(Err(e)) => tracing::error!("Scroll error: {}", e),
+        Err(e) => tracing::error!("Scroll task failed: {}", e),
+    }
 }
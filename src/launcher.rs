@@ -0,0 +1,40 @@
+use anyhow::{Context, Result};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::command;
+use crate::config::DaemonConfig;
+
+/// Show a list of entries in the configured launcher and return the chosen one.
+/// Returns `None` if the launcher was dismissed without a selection.
+pub async fn pick(daemon: &DaemonConfig, prompt: &str, entries: &[String]) -> Result<Option<String>> {
+    let cmd = command::build(&daemon.launcher_cmd, &[("prompt", prompt)], daemon.shell)?;
+
+    let mut child = Command::from(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to spawn launcher")?;
+
+    let mut stdin = child.stdin.take().context("Launcher has no stdin")?;
+    stdin.write_all(entries.join("\n").as_bytes()).await?;
+    drop(stdin);
+
+    let output = child.wait_with_output().await?;
+    let choice = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    if !output.status.success() || choice.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(choice))
+}
+
+/// Ask a yes/no question via the launcher
+pub async fn confirm(daemon: &DaemonConfig, prompt: &str) -> Result<bool> {
+    let entries = ["No".to_string(), "Yes".to_string()];
+    let choice = pick(daemon, prompt, &entries).await?;
+    Ok(choice.as_deref() == Some("Yes"))
+}
@@ -0,0 +1,69 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::filter::Directive;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+/// Level the daemon logs at unless RUST_LOG or an override says otherwise
+const BASE_DIRECTIVE: &str = "waybar_hovermenu=info";
+
+static HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+/// Levels set at runtime, keyed by target
+static OVERRIDES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Log to stderr (stdout stays free for --i3bar), with a filter `set_level` can change
+pub fn init() -> Result<()> {
+    let (filter, handle) = reload::Layer::new(filter(&BTreeMap::new())?);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .init();
+    let _ = HANDLE.set(handle);
+    Ok(())
+}
+
+/// RUST_LOG and the base level, then the runtime overrides on top
+fn filter(overrides: &BTreeMap<String, String>) -> Result<EnvFilter> {
+    let mut filter = EnvFilter::from_default_env().add_directive(BASE_DIRECTIVE.parse()?);
+    for (target, level) in overrides {
+        filter = filter.add_directive(directive(target, level)?);
+    }
+    Ok(filter)
+}
+
+/// A directive for a target: a daemon module name ("menu") or a full target path
+fn directive(target: &str, level: &str) -> Result<Directive> {
+    let target = if target.contains("::") || target == "waybar_hovermenu" {
+        target.to_string()
+    } else {
+        format!("waybar_hovermenu::{}", target)
+    };
+    format!("{}={}", target, level)
+        .parse()
+        .with_context(|| format!("Invalid level {:?} for {}", level, target))
+}
+
+/// Change the level of one target at runtime. `reset` as the target drops every override,
+/// `reset` as the level drops that target's.
+pub fn set_level(target: &str, level: &str) -> Result<()> {
+    let handle = HANDLE.get().context("Logging not initialized")?;
+    let mut overrides = OVERRIDES.lock().unwrap();
+    let mut updated = overrides.clone();
+    match (target, level) {
+        ("reset", _) => updated.clear(),
+        (_, "reset") => {
+            updated.remove(target);
+        }
+        _ => {
+            if level.parse::<tracing_subscriber::filter::LevelFilter>().is_err() {
+                bail!("Invalid level {:?}, expected off, error, warn, info, debug or trace", level);
+            }
+            updated.insert(target.to_string(), level.to_string());
+        }
+    }
+    handle.reload(filter(&updated)?)?;
+    *overrides = updated;
+    Ok(())
+}
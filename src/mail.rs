@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use walkdir::WalkDir;
+
+/// Unread count last sent to the bar, and when the menu was last opened
+struct Seen {
+    latest: usize,
+    seen: usize,
+}
+
+static SEEN: Mutex<Seen> = Mutex::new(Seen { latest: 0, seen: 0 });
+
+/// Unread and flagged INBOX messages under a maildir root
+#[derive(Default)]
+pub struct Mailbox {
+    /// Unread count per account (the directory holding the account's INBOX)
+    pub accounts: BTreeMap<String, usize>,
+    pub flagged: usize,
+}
+
+impl Mailbox {
+    pub fn unread(&self) -> usize {
+        self.accounts.values().sum()
+    }
+}
+
+/// Count unread (`INBOX/new`) and flagged (`F` in the maildir info) messages per account
+pub fn scan(root: &Path) -> Mailbox {
+    let mut mailbox = Mailbox::default();
+    for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(folder) = path.parent() else {
+            continue;
+        };
+        let Some(inbox) = folder.parent().filter(|p| p.ends_with("INBOX")) else {
+            continue;
+        };
+        // "<root>/<account>/INBOX/...", or a single account's INBOX right under the root
+        let account = inbox
+            .parent()
+            .and_then(|p| p.strip_prefix(root).ok())
+            .and_then(|p| p.components().next())
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .unwrap_or_else(|| "INBOX".to_string());
+
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        if folder.ends_with("new") {
+            *mailbox.accounts.entry(account).or_default() += 1;
+        } else if folder.ends_with("cur") {
+            mailbox.accounts.entry(account).or_default();
+        }
+        // "1700000000.123_1.host:2,FS" - flags come after ":2,"
+        let flagged = name.rsplit_once(":2,").is_some_and(|(_, flags)| flags.contains('F'));
+        if flagged {
+            mailbox.flagged += 1;
+        }
+    }
+    mailbox
+}
+
+/// Whether more mail is unread than when the menu was last opened
+pub fn has_new(unread: usize) -> bool {
+    unread > SEEN.lock().unwrap().seen
+}
+
+/// Follow the unread count as the mail status goes out to the bar
+pub fn observe(unread: usize) {
+    let mut seen = SEEN.lock().unwrap();
+    seen.latest = unread;
+    // Reading mail elsewhere lowers the baseline, so the next arrival counts as new
+    seen.seen = seen.seen.min(unread);
+}
+
+/// The menu was opened: everything unread so far has been seen
+pub fn mark_seen() {
+    let mut seen = SEEN.lock().unwrap();
+    seen.seen = seen.latest;
+}
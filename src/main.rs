@@ -1,64 +1,213 @@
+mod announce;
+mod backlight;
+mod battery;
+mod bluez;
+mod cache;
+mod charge;
+mod command;
+mod compositor;
 mod config;
+mod dbus;
+mod dnd;
+mod export;
+mod followers;
+mod geometry;
+mod groups;
+mod hardware;
+mod hotspot;
+mod http;
+mod hypr;
+mod i3bar;
+mod icons;
+mod idle;
+mod inhibit;
 mod ipc;
+mod launcher;
+mod logging;
+mod mail;
+mod media;
 mod menu;
 mod modules;
+mod network;
+mod nightlight;
+mod notifications;
+mod osd;
+mod overlay;
+mod profile;
+mod pulse;
+mod reload;
+mod rfkill;
+mod secrets;
+mod simulate;
+mod sway;
+mod tailscale;
+mod thermal;
+mod updates;
+mod urgent;
+mod vpn;
 mod watchers;
+mod weather;
+mod webhook;
+mod wlr;
 
 use std::sync::Arc;
 use anyhow::Result;
-use tracing_subscriber::EnvFilter;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::broadcast;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::from_default_env()
-                .add_directive("waybar_hovermenu=info".parse()?)
-        )
-        .init();
+    // The menu mako and dunst get in place of a notification center
+    if let Some(daemon) = std::env::args().skip_while(|a| a != "--notification-history").nth(1) {
+        return notifications::show_history(&daemon);
+    }
+
+    // In i3bar mode stdout carries the status protocol
+    let i3bar_mode = std::env::args().any(|a| a == "--i3bar");
+    let replace = std::env::args().any(|a| a == "--replace");
+    let debug = std::env::args().any(|a| a == "--debug");
+    
+    // Initialize logging (stderr, so stdout stays free for --i3bar)
+    logging::init()?;
     
     tracing::info!("Starting waybar-hovermenu");
     
     // Load configuration
     let config = Arc::new(config::Config::load()?);
     tracing::info!("Loaded config with {} modules", config.modules.len());
+    ipc::prepare_socket(&config.daemon.socket_path, replace).await?;
+    command::init(config.daemon.command_timeout_ms);
+    dnd::init(config.daemon.dnd_schedule.as_deref());
+    hardware::log_probe();
+    simulate::init(debug);
+    announce::init(config.daemon.announce_cmd.as_deref());
+    icons::init(&config.icons);
+    
+    // Broadcast channel for status updates: (module, json)
+    let (status_tx, _) = broadcast::channel(100);
+    tokio::spawn(cache::run(status_tx.clone()));
+    if config.daemon.export_status {
+        tokio::spawn(export::run(Arc::clone(&config), status_tx.clone()));
+    }
     
     // Create menu manager
-    let menu_manager = Arc::new(menu::MenuManager::new(Arc::clone(&config)));
+    let menu_manager = Arc::new(menu::MenuManager::new(Arc::clone(&config), status_tx.clone()));
     
     // Create IPC server
     let ipc_server = Arc::new(ipc::IpcServer::new(
         Arc::clone(&config),
         Arc::clone(&menu_manager),
+        status_tx,
     ));
     
-    // Start watchers for real-time updates
-    watchers::start_watchers(
+    // Close menus when focus moves elsewhere
+    if config.daemon.close_on_focus_loss {
+        tokio::spawn(Arc::clone(&menu_manager).watch_focus());
+    }
+    
+    // Claim the bus name so D-Bus activation (from hovermenu-ctl) knows we're up.
+    // Not fatal: the daemon works fine without a session bus.
+    let _bus = match dbus::claim_name().await {
+        Ok(conn) => Some(conn),
+        Err(e) => {
+            tracing::warn!("D-Bus activation unavailable: {:#}", e);
+            None
+        }
+    };
+    
+    // Start watchers for real-time updates, restarted whenever the config reloads
+    let watchers = watchers::start_watchers(
         Arc::clone(&config),
         Arc::clone(&menu_manager),
         ipc_server.status_sender(),
-    ).await;
+    );
+    tokio::spawn(reload::run(Arc::clone(&menu_manager), ipc_server.status_sender(), watchers));
+    
+    // Prime every widget now rather than waiting for each module's first event
+    {
+        let config = Arc::clone(&config);
+        let menu_manager = Arc::clone(&menu_manager);
+        let status_tx = ipc_server.status_sender();
+        tokio::spawn(async move {
+            watchers::refresh_all(&config, &menu_manager, &status_tx).await;
+            tracing::debug!("Primed all module statuses");
+        });
+    }
+    
+    // Act as a swaybar/i3bar status_command
+    if i3bar_mode {
+        let config = Arc::clone(&config);
+        let menu_manager = Arc::clone(&menu_manager);
+        let status_tx = ipc_server.status_sender();
+        tokio::spawn(async move {
+            if let Err(e) = i3bar::run(config, menu_manager, status_tx).await {
+                tracing::error!("i3bar output error: {}", e);
+            }
+        });
+    }
     
-    // Handle shutdown signals
+    // Optional read-only HTTP status endpoint
+    if config.http.enabled {
+        let config = Arc::clone(&config);
+        let menu_manager = Arc::clone(&menu_manager);
+        tokio::spawn(async move {
+            if let Err(e) = http::run(config, menu_manager).await {
+                tracing::error!("HTTP server error: {}", e);
+            }
+        });
+    }
+    
+    // SIGUSR1 toggles hover
+    tokio::spawn(handle_signals(Arc::clone(&menu_manager)));
+    
+    // Handle shutdown signals, and the `quit` command of an instance taking over
+    let mut terminate = signal(SignalKind::terminate())?;
     let shutdown = async {
-        tokio::signal::ctrl_c().await.ok();
-        tracing::info!("Received shutdown signal");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => false,
+            _ = terminate.recv() => false,
+            _ = ipc::quit_requested() => true,
+        }
     };
     
     // Run IPC server until shutdown
+    let mut replaced = false;
     tokio::select! {
         result = ipc_server.run() => {
             if let Err(e) = result {
                 tracing::error!("IPC server error: {}", e);
             }
         }
-        _ = shutdown => {}
+        quit = shutdown => {
+            replaced = quit;
+            if !replaced {
+                tracing::info!("Received shutdown signal");
+            }
+        }
     }
     
-    // Cleanup
-    let _ = std::fs::remove_file(&config.daemon.socket_path);
+    // Cleanup. The instance taking over binds as soon as we stop listening, so the
+    // socket path may already be its.
+    if !replaced {
+        let _ = std::fs::remove_file(&config.daemon.socket_path);
+    }
     tracing::info!("Shutdown complete");
     
     Ok(())
 }
+
+/// React to SIGUSR1 (toggle hover) for the life of the daemon
+async fn handle_signals(menu_manager: Arc<menu::MenuManager>) {
+    let mut usr1 = match signal(SignalKind::user_defined1()) {
+        Ok(usr1) => usr1,
+        Err(e) => {
+            tracing::warn!("Can't listen for SIGUSR1: {}", e);
+            return;
+        }
+    };
+    
+    while usr1.recv().await.is_some() {
+        let enabled = menu_manager.toggle_hover();
+        tracing::info!("Hover {}", if enabled { "enabled" } else { "disabled" });
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use zbus::zvariant::OwnedValue;
+
+/// Bus names of MPRIS players start with this, e.g. "org.mpris.MediaPlayer2.spotify"
+const PLAYER_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const PLAYER_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// A media player and what it is playing
+pub struct Player {
+    /// Player name without the MPRIS prefix, e.g. "spotify" or "firefox.instance_1_42"
+    pub name: String,
+    /// "Playing", "Paused" or "Stopped"
+    pub status: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+}
+
+/// Bus names of every running MPRIS player
+fn player_names(connection: &zbus::blocking::Connection) -> Result<Vec<String>> {
+    let names = zbus::blocking::fdo::DBusProxy::new(connection)?.list_names()?;
+    Ok(names
+        .into_iter()
+        .map(|n| n.to_string())
+        .filter(|n| n.starts_with(PLAYER_PREFIX))
+        .collect())
+}
+
+fn player_proxy<'a>(
+    connection: &zbus::blocking::Connection,
+    bus_name: &'a str,
+) -> Result<zbus::blocking::Proxy<'a>> {
+    Ok(zbus::blocking::Proxy::new(connection, bus_name, PLAYER_PATH, PLAYER_INTERFACE)?)
+}
+
+fn read_player(connection: &zbus::blocking::Connection, bus_name: &str) -> Result<Player> {
+    let proxy = player_proxy(connection, bus_name)?;
+    let status: String = proxy.get_property("PlaybackStatus")?;
+    let metadata: HashMap<String, OwnedValue> = proxy.get_property("Metadata").unwrap_or_default();
+    let text = |key: &str| {
+        metadata
+            .get(key)
+            .and_then(|v| String::try_from(v.try_clone().ok()?).ok())
+            .unwrap_or_default()
+    };
+    // xesam:artist is a list of names
+    let artist = metadata
+        .get("xesam:artist")
+        .and_then(|v| Vec::<String>::try_from(v.try_clone().ok()?).ok())
+        .map(|artists| artists.join(", "))
+        .unwrap_or_default();
+    Ok(Player {
+        name: bus_name.trim_start_matches(PLAYER_PREFIX).to_string(),
+        status,
+        title: text("xesam:title"),
+        artist,
+        album: text("xesam:album"),
+    })
+}
+
+/// The player to show: the first one playing, else the first paused one, else any
+pub fn current() -> Option<Player> {
+    let connection = zbus::blocking::Connection::session().ok()?;
+    let mut players: Vec<Player> = player_names(&connection)
+        .ok()?
+        .iter()
+        .filter_map(|name| read_player(&connection, name).ok())
+        .collect();
+    let rank = |p: &Player| match p.status.as_str() {
+        "Playing" => 0,
+        "Paused" => 1,
+        _ => 2,
+    };
+    players.sort_by_key(rank);
+    players.into_iter().next()
+}
+
+/// Send a transport command to the current player: "play-pause", "play", "pause",
+/// "stop", "next" or "previous"
+pub fn control(command: &str) -> Result<()> {
+    let method = match command {
+        "play-pause" | "toggle" => "PlayPause",
+        "play" => "Play",
+        "pause" => "Pause",
+        "stop" => "Stop",
+        "next" => "Next",
+        "previous" | "prev" => "Previous",
+        _ => bail!("Unknown media command {:?}, expected play-pause, play, pause, stop, next or previous", command),
+    };
+    let player = current().context("No media player running")?;
+    let connection = zbus::blocking::Connection::session()?;
+    let bus_name = format!("{}{}", PLAYER_PREFIX, player.name);
+    player_proxy(&connection, &bus_name)?.call_method(method, &())?;
+    Ok(())
+}
+
+/// A message whenever a player changes state or track, or appears or goes away.
+/// The channel closes when the session bus connection drops.
+pub async fn changes() -> Result<mpsc::Receiver<()>> {
+    let connection = zbus::Connection::session().await?;
+    let properties = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path(PLAYER_PATH)?
+        .build();
+    let owners = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg0ns("org.mpris.MediaPlayer2")?
+        .build();
+    let properties = zbus::MessageStream::for_match_rule(properties, &connection, Some(16)).await?;
+    let owners = zbus::MessageStream::for_match_rule(owners, &connection, Some(16)).await?;
+    let mut messages = futures::stream::select(properties, owners);
+
+    let (tx, rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        while messages.next().await.is_some() {
+            // A full channel already has a refresh queued
+            if let Err(mpsc::error::TrySendError::Closed(())) = tx.try_send(()) {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
@@ -1,39 +1,240 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
 use std::process::Command;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
-use tokio::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
 use tracing::debug;
 
-use crate::config::{Config, ModuleConfig};
+use crate::announce;
+use crate::command;
+use crate::compositor::{self, Compositor, Monitor, Window};
+use crate::config::{BarConfig, Config, ModuleConfig};
+use crate::dnd;
+use crate::geometry::{self, Geometry};
+use crate::launcher;
+use crate::mail;
+use crate::modules::{execute_action, get_status, notify_failure, ModuleStatus};
+use crate::notifications;
+use crate::profile;
+use crate::simulate;
+use crate::urgent;
+use crate::watchers;
+
+/// How a menu slides and fades in or out
+#[derive(Clone, Copy)]
+struct Animation {
+    /// Unit direction the menu leaves in (and comes in from); (0, 0) only fades
+    direction: (i32, i32),
+    /// Frames, 0 for no animation
+    steps: u32,
+    /// Total slide in pixels
+    distance: i32,
+    frame: Duration,
+}
 
 /// Manages the state of open menus
 pub struct MenuManager {
-    config: Arc<Config>,
+    /// The live config, replaced on reload
+    config: std::sync::RwLock<Arc<Config>>,
+    /// Broadcast channel for status updates
+    status_tx: broadcast::Sender<(String, String)>,
     /// Currently pinned module (if any)
     pinned: Mutex<Option<String>>,
     /// Currently open module (if any) - tracks which module's menu is open
     open_module: Mutex<Option<String>>,
     /// Generation counter to cancel old cursor watchers
     watcher_generation: AtomicU64,
+    /// Generation counter to cancel pending (dwelling) hovers
+    hover_generation: AtomicU64,
+    /// When each module was last pressed, for long-press detection
+    presses: Mutex<HashMap<String, Instant>>,
+    /// Generation counter to cancel pin timeouts when the pin changes
+    pin_generation: AtomicU64,
+    /// Last geometry of each module's menu window (for `remember_geometry`)
+    geometry: Mutex<HashMap<String, Geometry>>,
+    /// Bar the open menu was opened from, and the monitor it is on
+    bar: Mutex<Option<(BarConfig, Geometry)>>,
+    /// Cursor x sent along with the last widget event, if any
+    event_x: Mutex<Option<i32>>,
+    /// Where along the bar the open menu's widget is (x, or y on a side bar), for `position = "widget"`
+    anchor: Mutex<Option<i32>>,
+    /// Hover-to-open, starting from `daemon.hover` (toggled with SIGUSR1)
+    hover: AtomicBool,
+    /// Whether the last widget event came from a touchscreen (`tap`/`hold`)
+    touch: AtomicBool,
+    /// Window management backend (`daemon.compositor`)
+    compositor: Box<dyn Compositor>,
+    /// Process of the last launched menu, for closing windows without a known pid
+    menu_pid: Mutex<Option<u32>>,
 }
 
 impl MenuManager {
-    pub fn new(config: Arc<Config>) -> Self {
+    pub fn new(config: Arc<Config>, status_tx: broadcast::Sender<(String, String)>) -> Self {
+        let hover = AtomicBool::new(config.daemon.hover);
+        let compositor = compositor::connect(&config);
         Self {
-            config,
+            config: std::sync::RwLock::new(config),
+            status_tx,
             pinned: Mutex::new(None),
             open_module: Mutex::new(None),
             watcher_generation: AtomicU64::new(0),
+            hover_generation: AtomicU64::new(0),
+            presses: Mutex::new(HashMap::new()),
+            pin_generation: AtomicU64::new(0),
+            geometry: Mutex::new(geometry::load()),
+            bar: Mutex::new(None),
+            event_x: Mutex::new(None),
+            anchor: Mutex::new(None),
+            hover,
+            touch: AtomicBool::new(false),
+            compositor,
+            menu_pid: Mutex::new(None),
         }
     }
     
+    /// The current config
+    pub fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().unwrap())
+    }
+    
+    /// Switch to a reloaded config. A changed `daemon.hover` takes effect;
+    /// otherwise a SIGUSR1 toggle survives the reload.
+    pub fn set_config(&self, config: Arc<Config>) {
+        let mut current = self.config.write().unwrap();
+        if config.daemon.hover != current.daemon.hover {
+            self.hover.store(config.daemon.hover, Ordering::SeqCst);
+        }
+        *current = config;
+    }
+    
     /// Check if a module is currently pinned
     pub async fn is_pinned(&self, module: &str) -> bool {
         let pinned = self.pinned.lock().await;
         pinned.as_deref() == Some(module)
     }
     
+    /// Whether hovering a widget opens its menu
+    pub fn hover_enabled(&self) -> bool {
+        self.hover.load(Ordering::SeqCst)
+    }
+    
+    /// Switch hover-to-open on or off, returning the new state
+    pub fn toggle_hover(&self) -> bool {
+        !self.hover.fetch_xor(true, Ordering::SeqCst)
+    }
+    
+    /// Record whether the latest widget event came from touch or a pointer
+    pub fn set_touch(&self, touch: bool) {
+        self.touch.store(touch, Ordering::SeqCst);
+    }
+    
+    /// Record the cursor x a widget event was sent with (`None` if it came without one)
+    pub async fn set_event_x(&self, x: Option<i32>) {
+        *self.event_x.lock().await = x;
+    }
+    
+    /// Handle tap-and-hold: open the menu and, with hover enabled, pin it.
+    /// Unlike click, holding never closes a menu.
+    pub async fn hold(self: &Arc<Self>, module: &str, bar: Option<&str>) -> Result<()> {
+        let settled = if self.hover_enabled() {
+            self.is_pinned(module).await
+        } else {
+            self.is_menu_open(module).await
+        };
+        if settled {
+            return Ok(());
+        }
+        self.click(module, bar).await
+    }
+    
+    /// The module whose menu is open, if any
+    pub async fn open_module(&self) -> Option<String> {
+        self.open_module.lock().await.clone()
+    }
+    
+    /// Open a module's menu on request (keybindings, scripts), closing any other.
+    /// With hover enabled it's pinned, so it stays put wherever the pointer is.
+    /// Unlike hover and click, do-not-disturb and fullscreen windows don't stop it.
+    pub async fn open(self: &Arc<Self>, module: &str, bar: Option<&str>) -> Result<()> {
+        if self.is_menu_open(module).await {
+            return Ok(());
+        }
+        
+        let config = self.config();
+        let module_config = config.get_module(module)
+            .context("Module not found")?;
+        if !module_config.enabled || profile::is_disabled(&config, module) {
+            bail!("Module {} is disabled", module);
+        }
+        
+        self.close_all_menus().await?;
+        *self.pinned.lock().await = None;
+        self.enter_bar(module, bar).await;
+        self.open_menu(module, module_config).await?;
+        
+        if self.hover_enabled() {
+            *self.pinned.lock().await = Some(module.to_string());
+            self.set_menu_border_gold(module).await?;
+            self.start_pin_timer(module);
+        }
+        Ok(())
+    }
+    
+    /// Close a module's menu, pinned or not. Nothing happens if it isn't open.
+    pub async fn close(&self, module: &str) -> Result<()> {
+        if !self.is_menu_open(module).await {
+            return Ok(());
+        }
+        self.close_all().await
+    }
+    
+    /// Close whatever menu is open, pinned or not
+    pub async fn close_all(&self) -> Result<()> {
+        *self.pinned.lock().await = None;
+        self.close_all_menus().await
+    }
+    
+    /// Pin an open menu: the named module's (opening it first if needed), or
+    /// whichever is open. Returns the module that's now pinned.
+    pub async fn pin(self: &Arc<Self>, module: Option<&str>) -> Result<String> {
+        if let Some(module) = module {
+            self.open(module, None).await?;
+        }
+        let open = self.open_module().await.context("No menu is open")?;
+        if self.is_pinned(&open).await {
+            return Ok(open);
+        }
+        
+        *self.pinned.lock().await = Some(open.clone());
+        self.set_menu_border_gold(&open).await?;
+        self.start_pin_timer(&open);
+        self.announce(&open, "pinned");
+        Ok(open)
+    }
+    
+    /// Unpin the named module's menu, or whichever is pinned, leaving it open.
+    /// Returns the module that was unpinned, if any.
+    pub async fn unpin(&self, module: Option<&str>) -> Result<Option<String>> {
+        let unpinned = {
+            let mut pinned = self.pinned.lock().await;
+            if module.is_some() && pinned.as_deref() != module {
+                return Ok(None);
+            }
+            pinned.take()
+        };
+        let Some(unpinned) = unpinned else {
+            return Ok(None);
+        };
+        
+        // Cancel the pin timeout
+        self.pin_generation.fetch_add(1, Ordering::SeqCst);
+        self.clear_menu_border(&unpinned).await?;
+        self.announce(&unpinned, "unpinned");
+        Ok(Some(unpinned))
+    }
+    
     /// Check if any module is pinned
     pub async fn has_pinned(&self) -> bool {
         self.pinned.lock().await.is_some()
@@ -45,10 +246,25 @@ impl MenuManager {
         open.as_deref() == Some(module)
     }
     
+    /// Record a press on a module's widget
+    pub async fn press(&self, module: &str) {
+        self.presses.lock().await.insert(module.to_string(), Instant::now());
+    }
+    
+    /// End a press, returning how long it was held (None if no press was recorded)
+    pub async fn release(&self, module: &str) -> Option<Duration> {
+        self.presses.lock().await.remove(module).map(|t| t.elapsed())
+    }
+    
     /// Handle hover event - open menu for module (only if hover is enabled)
-    pub async fn hover(self: &Arc<Self>, module: &str) -> Result<()> {
+    pub async fn hover(self: &Arc<Self>, module: &str, bar: Option<&str>) -> Result<()> {
         // No-op if hover is disabled globally
-        if !self.config.daemon.hover {
+        if !self.hover_enabled() {
+            return Ok(());
+        }
+
+        // Do-not-disturb only lets explicit clicks through
+        if dnd::is_active() {
             return Ok(());
         }
 
@@ -58,10 +274,31 @@ impl MenuManager {
         }
         
         // Get module config
-        let module_config = self.config.get_module(module)
+        let config = self.config();
+        let module_config = config.get_module(module)
             .context("Module not found")?;
         
-        if !module_config.enabled {
+        if !module_config.enabled || profile::is_disabled(&config, module) {
+            return Ok(());
+        }
+        
+        // Dwell: wait before opening, and give up if a leave (or another hover) arrives meanwhile
+        let generation = self.hover_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        if module_config.hover_delay_ms > 0 {
+            tokio::time::sleep(tokio::time::Duration::from_millis(module_config.hover_delay_ms)).await;
+            if self.hover_generation.load(Ordering::SeqCst) != generation {
+                debug!("Hover on {} cancelled during dwell", module);
+                return Ok(());
+            }
+            // A quick flick off the bar doesn't always produce a leave event
+            if !self.is_cursor_on_bar(module, bar).await {
+                debug!("Hover on {} cancelled, cursor left the bar during dwell", module);
+                return Ok(());
+            }
+        }
+
+        if self.suppressed_by_fullscreen(module_config).await {
+            debug!("Hover on {} ignored (fullscreen window focused)", module);
             return Ok(());
         }
         
@@ -75,34 +312,39 @@ impl MenuManager {
         }
         
         // Open the new menu
+        self.enter_bar(module, bar).await;
         self.open_menu(module, module_config).await?;
         
         Ok(())
     }
     
     /// Handle leave event - close menu if not pinned and cursor not over menu
-    /// Uses debouncing: checks multiple times over 300ms before closing
+    /// Uses debouncing: checks every 50ms for `daemon.leave_grace_ms` before closing
     /// Only active when hover mode is enabled.
     pub async fn leave(&self) -> Result<()> {
         // No-op if hover is disabled — menus are managed by click only
-        if !self.config.daemon.hover {
+        if !self.hover_enabled() {
             return Ok(());
         }
 
+        // Cancel any hover still dwelling
+        self.hover_generation.fetch_add(1, Ordering::SeqCst);
+
         // Don't close if pinned
         if self.has_pinned().await {
             return Ok(());
         }
         
-        // Check cursor position multiple times over 300ms
+        // Check cursor position multiple times over the grace period
         // Only close if cursor stays outside the safe zone
-        for _ in 0..6 {
+        let checks = self.config().daemon.leave_grace_ms.div_ceil(50);
+        for _ in 0..checks {
             tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
             
             let (cursor_x, cursor_y) = self.get_cursor_pos().await;
             
             // If cursor is in waybar, don't close
-            if cursor_y <= self.config.daemon.waybar_height as i32 {
+            if self.is_cursor_in_bar(cursor_x, cursor_y).await {
                 return Ok(());
             }
             
@@ -112,7 +354,7 @@ impl MenuManager {
             }
         }
         
-        // Cursor stayed outside safe zone for 300ms - close
+        // Cursor stayed outside safe zone for the whole grace period - close
         self.close_all_menus().await?;
         
         Ok(())
@@ -121,18 +363,24 @@ impl MenuManager {
     /// Handle click event.
     /// When hover is disabled: simple toggle — click opens, click again closes.
     /// When hover is enabled: original pin-based behavior.
-    pub async fn click(self: &Arc<Self>, module: &str) -> Result<()> {
+    pub async fn click(self: &Arc<Self>, module: &str, bar: Option<&str>) -> Result<()> {
         let is_open = self.is_menu_open(module).await;
 
-        if !self.config.daemon.hover {
+        if !self.hover_enabled() {
             // Hover disabled — click is a simple open/close toggle
             if is_open {
                 self.close_all_menus().await?;
             } else {
-                let module_config = self.config.get_module(module)
+                let config = self.config();
+                let module_config = config.get_module(module)
                     .context("Module not found")?;
 
-                if !module_config.enabled {
+                if !module_config.enabled || profile::is_disabled(&config, module) {
+                    return Ok(());
+                }
+
+                if self.suppressed_by_fullscreen(module_config).await {
+                    debug!("Click on {} ignored (fullscreen window focused)", module);
                     return Ok(());
                 }
 
@@ -140,6 +388,7 @@ impl MenuManager {
                 self.close_all_menus().await?;
 
                 // Open the menu (no pin, no cursor watcher)
+                self.enter_bar(module, bar).await;
                 self.open_menu(module, module_config).await?;
             }
         } else {
@@ -160,12 +409,20 @@ impl MenuManager {
                     *pinned = Some(module.to_string());
                 }
                 self.set_menu_border_gold(module).await?;
+                self.start_pin_timer(module);
+                self.announce(module, "pinned");
             } else {
                 // Menu not open - open it and pin it
-                let module_config = self.config.get_module(module)
+                let config = self.config();
+                let module_config = config.get_module(module)
                     .context("Module not found")?;
 
-                if !module_config.enabled {
+                if !module_config.enabled || profile::is_disabled(&config, module) {
+                    return Ok(());
+                }
+
+                if self.suppressed_by_fullscreen(module_config).await {
+                    debug!("Click on {} ignored (fullscreen window focused)", module);
                     return Ok(());
                 }
 
@@ -173,70 +430,295 @@ impl MenuManager {
                 self.close_all_menus().await?;
 
                 // Open and pin
+                self.enter_bar(module, bar).await;
                 self.open_menu(module, module_config).await?;
                 {
                     let mut pinned = self.pinned.lock().await;
                     *pinned = Some(module.to_string());
                 }
                 self.set_menu_border_gold(module).await?;
+                self.start_pin_timer(module);
+                self.announce(module, "pinned");
             }
         }
 
         // Jiggle the mouse slightly to reset waybar's click target state,
         // allowing the same widget to be clicked again without moving the mouse.
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-        let _ = Command::new("ydotool")
-            .args(["mousemove", "-x", "1", "-y", "0"])
-            .output();
-        let _ = Command::new("ydotool")
-            .args(["mousemove", "-x", "-1", "-y", "0"])
-            .output();
+        self.jiggle_pointer().await;
 
         Ok(())
     }
     
+    /// How a module's menu comes in (`opening`) or leaves
+    async fn animation(&self, module: &ModuleConfig, opening: bool) -> Animation {
+        let (name, kind) = if opening {
+            ("open_animation", &module.open_animation)
+        } else {
+            ("close_animation", &module.close_animation)
+        };
+        let direction = match kind.as_str() {
+            "up" => (0, -1),
+            "down" => (0, 1),
+            "left" => (-1, 0),
+            "right" => (1, 0),
+            "fade" => (0, 0),
+            "none" => (0, 0),
+            other => {
+                if other != "auto" {
+                    tracing::warn!("Unknown {} {:?}, using auto", name, other);
+                }
+                // Slide back toward the bar the menu came from
+                match &*self.bar.lock().await {
+                    Some((bar, _)) => bar.edge_direction(),
+                    None => self.config().bar(None, "", None).edge_direction(),
+                }
+            }
+        };
+        // Opening falls back to the close settings, so one set of numbers drives both
+        let (steps, duration_ms, distance) = if opening {
+            (
+                module.open_steps.unwrap_or(module.close_steps),
+                module.open_duration_ms.unwrap_or(module.close_duration_ms),
+                module.open_distance.unwrap_or(module.close_distance),
+            )
+        } else {
+            (module.close_steps, module.close_duration_ms, module.close_distance)
+        };
+        let steps = if kind == "none" { 0 } else { steps };
+        Animation {
+            direction,
+            steps,
+            distance: distance as i32,
+            frame: Duration::from_millis(duration_ms / u64::from(steps.max(1))),
+        }
+    }
+    
+    /// Slide windows away while fading them out, or (`opening`) run it backwards,
+    /// bringing windows placed `distance` out back in up to `alpha`
+    async fn animate(&self, ids: &[String], animation: Animation, opening: bool, alpha: f32) {
+        let Animation { direction, steps, distance, frame } = animation;
+        let steps = steps as i32;
+        // Ease: the closing step k covers k parts of the distance, so it accelerates
+        // away and, run backwards, decelerates into place
+        let parts = (steps * (steps + 1) / 2).max(1);
+        let travelled = |k: i32| distance * (k * (k + 1) / 2) / parts;
+        for step in 1..=steps {
+            let (offset, faded) = if opening {
+                let k = steps - step;
+                (travelled(k) - travelled(k + 1), k)
+            } else {
+                (travelled(step) - travelled(step - 1), step)
+            };
+            let alpha = alpha * (1.0 - faded as f32 * 0.96 / steps as f32);
+            
+            let (dx, dy) = (direction.0 * offset, direction.1 * offset);
+            if (dx, dy) != (0, 0) {
+                let _ = self.compositor.move_windows(ids, dx, dy).await;
+            }
+            for id in ids {
+                let _ = self.compositor.set_props(id, &[("alpha", format!("{:.2}", alpha))]).await;
+            }
+            
+            tokio::time::sleep(frame).await;
+        }
+    }
+    
+    /// Close a pinned menu after its `pin_timeout`, flagging the widget with a
+    /// `pin-expiring` class shortly before. Any pin change cancels the timer.
+    fn start_pin_timer(self: &Arc<Self>, module: &str) {
+        let generation = self.pin_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let config = self.config();
+        let Some(timeout) = config.get_module(module).and_then(|m| m.pin_timeout) else {
+            return;
+        };
+
+        let manager = Arc::clone(self);
+        let module = module.to_string();
+        tokio::spawn(async move {
+            let timeout = Duration::from_secs(timeout);
+            let warning = (timeout / 2).min(Duration::from_secs(5));
+            let still_pinned = |manager: &Arc<MenuManager>| {
+                manager.pin_generation.load(Ordering::SeqCst) == generation
+            };
+
+            tokio::time::sleep(timeout - warning).await;
+            if !still_pinned(&manager) || !manager.is_pinned(&module).await {
+                return;
+            }
+            let status = manager.module_status(&module, true).await.with_class("pin-expiring");
+            watchers::publish(&manager.config(), &manager.status_tx, &module, &status);
+
+            tokio::time::sleep(warning).await;
+            if !still_pinned(&manager) || !manager.is_pinned(&module).await {
+                return;
+            }
+            debug!("Pin timeout for {}", module);
+            {
+                let mut pinned = manager.pinned.lock().await;
+                *pinned = None;
+            }
+            let _ = manager.close_all_menus().await;
+            let status = manager.module_status(&module, false).await;
+            watchers::publish(&manager.config(), &manager.status_tx, &module, &status);
+        });
+    }
+    
+    /// Compute a module's status off the async runtime
+    async fn module_status(&self, module: &str, pinned: bool) -> ModuleStatus {
+        let config = self.config();
+        let module = module.to_string();
+        tokio::task::spawn_blocking(move || get_status(&config, &module, pinned))
+            .await
+            .unwrap_or_else(|_| ModuleStatus::new("error"))
+    }
+    
     /// Open a menu for a module
     async fn open_menu(self: &Arc<Self>, module: &str, config: &ModuleConfig) -> Result<()> {
+        // Looking at the menu counts as seeing whatever made the module urgent
+        urgent::acknowledge(module);
+        if module == "mail" {
+            mail::mark_seen();
+        }
+        self.announce(module, "opened");
+
+        if config.kind == "power" {
+            // Daemon-generated menu shown via the launcher. The launcher manages its own
+            // window, so no cursor watcher is spawned for it.
+            {
+                let mut open_module = self.open_module.lock().await;
+                *open_module = Some(module.to_string());
+            }
+
+            let manager = Arc::clone(self);
+            let module = module.to_string();
+            let config = config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = manager.run_power_menu(&module, &config).await {
+                    tracing::error!("Power menu error: {}", e);
+                }
+                let mut open_module = manager.open_module.lock().await;
+                if open_module.as_deref() == Some(module.as_str()) {
+                    *open_module = None;
+                }
+            });
+            return Ok(());
+        }
+
+        let history;
+        let config = if config.kind == "notification-center" {
+            let setting = config.notification_daemon.clone();
+            let server = tokio::task::spawn_blocking(move || notifications::Server::find(setting.as_deref()))
+                .await?
+                .context("No notification daemon (mako, dunst or swaync) found")?;
+            if server.has_center() {
+                // The notification daemon's own panel: there is no window to place or track,
+                // and it closes with the other menus or on a click outside it
+                tokio::task::spawn_blocking(move || server.open_center()).await??;
+                *self.open_module.lock().await = Some(module.to_string());
+                return Ok(());
+            }
+            // Without one, the daemon's history in a terminal
+            let exe = std::env::current_exe()?;
+            history = ModuleConfig {
+                kind: "tui".to_string(),
+                command: Some(format!(
+                    "{} --notification-history {}",
+                    command::quote(&exe.to_string_lossy()),
+                    server.name()
+                )),
+                shell: Some(false),
+                ..config.clone()
+            };
+            &history
+        } else {
+            config
+        };
+
+        if !config.entries.is_empty() {
+            // Submenu: let the user pick an entry, then open it like a regular menu
+            {
+                let mut open_module = self.open_module.lock().await;
+                *open_module = Some(module.to_string());
+            }
+
+            let manager = Arc::clone(self);
+            let module = module.to_string();
+            let config = config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = manager.run_submenu(&module, &config).await {
+                    tracing::error!("Submenu error: {}", e);
+                }
+            });
+            return Ok(());
+        }
+
+        self.launch_menu(module, config).await
+    }
+    
+    /// Tell screen reader users what happened to a module's menu, e.g. "Audio opened"
+    fn announce(&self, module: &str, event: &str) {
+        let label = announce::label(module, self.config().get_module(module));
+        announce::say(&format!("{} {}", label, event));
+    }
+    
+    /// Build the terminal command for a TUI menu from `daemon.terminal_cmd`.
+    /// A bare `{command}` word is replaced by the command's own arguments.
+    fn terminal_command(&self, title: &str, command: &str, shell: bool) -> Result<Command> {
+        let template = &self.config().daemon.terminal_cmd;
+        if shell {
+            let script = template.replace("{command}", &shellexpand::tilde(command));
+            return command::build(&script, &[("title", title)], true);
+        }
+        
+        let mut argv = Vec::new();
+        for word in command::argv(template, &[("title", title)])? {
+            if word == "{command}" {
+                argv.extend(command::argv(command, &[])?);
+            } else {
+                argv.push(word.replace("{command}", &shellexpand::tilde(command)));
+            }
+        }
+        let (program, args) = argv.split_first().context("Empty terminal_cmd")?;
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        Ok(cmd)
+    }
+    
+    /// Launch a TUI/GUI menu window and start tracking it
+    async fn launch_menu(self: &Arc<Self>, module: &str, config: &ModuleConfig) -> Result<()> {
         let command = config.command.as_ref()
             .context("Module has no command configured")?;
         
-        let expanded_command = shellexpand::tilde(command);
+        let shell = config.shell.unwrap_or(self.config().daemon.shell);
         
-        if config.kind == "gui" {
+        if config.is_gui() {
             // GUI app - just launch it, with GTK dark theme forced
             // Use tokio::process so the child is auto-reaped (avoids zombies)
-            let gui_cmd = format!("GTK_THEME=Adwaita:dark {}", expanded_command);
-            tokio::process::Command::new("sh")
-                .args(["-c", &gui_cmd])
+            let child = tokio::process::Command::from(command::build(command, &[], shell)?)
+                .env("GTK_THEME", "Adwaita:dark")
                 .stdin(std::process::Stdio::null())
                 .stdout(std::process::Stdio::null())
                 .stderr(std::process::Stdio::null())
                 .spawn()?;
+            *self.menu_pid.lock().await = child.id();
             
             // Mouse jiggle to prevent hover-leave issues
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            let _ = Command::new("ydotool")
-                .args(["mousemove", "-x", "1", "-y", "0"])
-                .output();
-            let _ = Command::new("ydotool")
-                .args(["mousemove", "-x", "-1", "-y", "0"])
-                .output();
+            self.jiggle_pointer().await;
         } else {
             // TUI app - launch in terminal with special title
             let title = format!("WAYBAR-MENU: {}", module);
             
-            // Build command from template: replace {title} and {command}
-            let cmd = self.config.daemon.terminal_cmd
-                .replace("{title}", &title)
-                .replace("{command}", &expanded_command);
+            let cmd = self.terminal_command(&title, command, shell)?;
             
             // Use tokio::process so the child is auto-reaped (avoids zombies)
-            tokio::process::Command::new("sh")
-                .args(["-c", &cmd])
+            let child = tokio::process::Command::from(cmd)
                 .stdin(std::process::Stdio::null())
                 .stdout(std::process::Stdio::null())
                 .stderr(std::process::Stdio::null())
                 .spawn()?;
+            *self.menu_pid.lock().await = child.id();
         }
         
         // Track which module is open
@@ -245,21 +727,32 @@ impl MenuManager {
             *open_module = Some(module.to_string());
         }
         
+        // Float and size the window once it appears
+        {
+            let manager = Arc::clone(self);
+            let module = module.to_string();
+            let config = config.clone();
+            tokio::spawn(async move {
+                manager.place_menu_window(&module, &config).await;
+            });
+        }
+        
         // Only spawn cursor watcher when hover mode is enabled.
         // In click-only mode, menus stay open until explicitly closed by another click.
-        if self.config.daemon.hover {
+        if self.hover_enabled() {
             // Increment generation to cancel any previous cursor watcher
             let generation = self.watcher_generation.fetch_add(1, Ordering::SeqCst) + 1;
 
             // Spawn cursor watcher task
             let manager = Arc::clone(self);
-            let waybar_height = self.config.daemon.waybar_height;
             tokio::spawn(async move {
                 // Wait for window to appear
                 tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
 
-                let mut outside_count = 0;
-                const CHECKS_BEFORE_CLOSE: u32 = 5; // 500ms outside safe zone
+                let daemon = &manager.config().daemon;
+                let poll = Duration::from_millis(daemon.watcher_poll_ms.max(10));
+                let close_after = Duration::from_millis(daemon.watcher_close_after_ms);
+                let mut outside_since: Option<Instant> = None;
                 loop {
                     // Check if this watcher is still valid (not superseded by a new menu)
                     if manager.watcher_generation.load(Ordering::SeqCst) != generation {
@@ -282,25 +775,25 @@ impl MenuManager {
                     let (cursor_x, cursor_y) = manager.get_cursor_pos().await;
 
                     // Safe zone: waybar area OR over menu window
-                    let in_waybar = cursor_y <= waybar_height as i32;
+                    let in_waybar = manager.is_cursor_in_bar(cursor_x, cursor_y).await;
                     let over_menu = manager.is_cursor_over_menu(cursor_x, cursor_y).await;
 
                     tracing::debug!("Cursor at ({}, {}), in_waybar={}, over_menu={}", cursor_x, cursor_y, in_waybar, over_menu);
 
                     if in_waybar || over_menu {
-                        // Cursor is in safe zone - reset counter
-                        outside_count = 0;
+                        // Cursor is in safe zone - reset the clock
+                        outside_since = None;
                     } else {
                         // Cursor is outside safe zone
-                        outside_count += 1;
+                        let since = *outside_since.get_or_insert_with(Instant::now);
 
-                        if outside_count >= CHECKS_BEFORE_CLOSE {
+                        if since.elapsed() >= close_after {
                             let _ = manager.close_all_menus().await;
                             return;
                         }
                     }
 
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                    tokio::time::sleep(poll).await;
                 }
             });
         }
@@ -308,124 +801,432 @@ impl MenuManager {
         Ok(())
     }
     
+    /// Show the submenu chooser and open the picked entry
+    async fn run_submenu(self: &Arc<Self>, module: &str, config: &ModuleConfig) -> Result<()> {
+        let labels: Vec<String> = config.entries.iter().map(|e| e.name.clone()).collect();
+        let choice = launcher::pick(&self.config().daemon, module, &labels).await?;
+        let entry = choice.and_then(|c| config.entries.iter().find(|e| e.name == c));
+
+        let Some(entry) = entry else {
+            // Dismissed - forget the chooser unless something else opened meanwhile
+            let mut open_module = self.open_module.lock().await;
+            if open_module.as_deref() == Some(module) {
+                *open_module = None;
+            }
+            return Ok(());
+        };
+
+        let entry_config = ModuleConfig {
+            kind: entry.kind.clone(),
+            command: Some(entry.command.clone()),
+            window_class: entry.window_class.clone(),
+            entries: Vec::new(),
+            ..config.clone()
+        };
+
+        self.launch_menu(module, &entry_config).await
+    }
+
+    /// Show the power menu, confirm the choice if configured, then run its command
+    async fn run_power_menu(&self, module: &str, config: &ModuleConfig) -> Result<()> {
+        let daemon = &self.config().daemon;
+        let choices = [
+            ("Lock", &config.lock_cmd),
+            ("Suspend", &config.suspend_cmd),
+            ("Reboot", &config.reboot_cmd),
+            ("Shutdown", &config.shutdown_cmd),
+        ];
+        let labels: Vec<String> = choices.iter().map(|(label, _)| label.to_string()).collect();
+
+        let Some(choice) = launcher::pick(daemon, "Power", &labels).await? else {
+            return Ok(());
+        };
+        let Some((label, command)) = choices.iter().find(|(label, _)| *label == choice) else {
+            return Ok(());
+        };
+
+        // Locking is harmless, everything else asks first
+        if config.confirm && *label != "Lock" {
+            let prompt = format!("{}?", label);
+            if !launcher::confirm(daemon, &prompt).await? {
+                return Ok(());
+            }
+        }
+
+        debug!("Power menu: running {}", label);
+        // Don't hold the menu open while e.g. the locker runs
+        let config = self.config();
+        let module = module.to_string();
+        let command = command.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = execute_action(&config, &module, &command).await {
+                tracing::error!("Power action error: {:#}", e);
+                notify_failure(&module, &e);
+            }
+        });
+        Ok(())
+    }
+
     /// Close all waybar menus with slide-up animation, then kill
     async fn close_all_menus(&self) -> Result<()> {
-        // Collect all GUI window classes from config
-        let gui_classes: Vec<String> = self.config.modules.values()
-            .filter(|m| m.kind == "gui")
-            .filter_map(|m| m.window_class.clone())
-            .collect();
-
+        self.record_geometry().await;
+        
         // Find all menu windows
-        let output = Command::new("hyprctl")
-            .args(["clients", "-j"])
-            .output()?;
-        
-        let clients: serde_json::Value = serde_json::from_slice(&output.stdout)
-            .unwrap_or(serde_json::Value::Array(vec![]));
-        
-        // Collect windows to animate
-        let mut windows: Vec<(String, i32)> = Vec::new(); // (address, pid)
-        
-        if let Some(clients) = clients.as_array() {
-            for client in clients {
-                let title = client.get("title")
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("");
-                let class = client.get("class")
-                    .and_then(|c| c.as_str())
-                    .unwrap_or("");
-                let pid = client.get("pid")
-                    .and_then(|p| p.as_i64())
-                    .unwrap_or(0) as i32;
-                let addr = client.get("address")
-                    .and_then(|a| a.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                
-                let is_tui_menu = title.starts_with("WAYBAR-MENU:");
-                let is_gui_menu = gui_classes.iter().any(|c| c == class);
-                
-                if is_tui_menu || is_gui_menu {
-                    windows.push((addr, pid));
+        let is_menu = self.menu_clients();
+        let windows: Vec<Window> = self.compositor.windows().await?
+            .into_iter()
+            .filter(|w| is_menu(w))
+            .collect();
+        let ids: Vec<String> = windows.iter().map(|w| w.id.clone()).collect();
+        
+        // Animate: slide away and fade out
+        let config = self.config();
+        let open = self.open_module.lock().await.clone();
+        let defaults = ModuleConfig::default();
+        let module = open.as_deref().and_then(|m| config.get_module(m)).unwrap_or(&defaults);
+        let animation = self.animation(module, false).await;
+        self.animate(&ids, animation, false, 1.0).await;
+        
+        if module.kind == "notification-center" {
+            let setting = module.notification_daemon.clone();
+            let _ = tokio::task::spawn_blocking(move || {
+                notifications::Server::find(setting.as_deref()).map(|server| server.close_center())
+            })
+            .await;
+        }
+        
+        // Now kill the processes. Windows the compositor can't name a pid for are
+        // closed through the menu process we launched, or else by the compositor.
+        let launched = self.menu_pid.lock().await.take();
+        for window in &windows {
+            let pid = if window.pid > 0 { Some(window.pid) } else { launched.map(|p| p as i32) };
+            match pid {
+                Some(pid) => unsafe {
+                    libc::kill(pid, libc::SIGTERM);
+                },
+                None => {
+                    let _ = self.compositor.close_window(&window.id).await;
                 }
             }
         }
         
-        // Animate: slide up and fade out
-        for step in 1i32..=8 {
-            let move_y = step * -60; // Move up 60px per step
-            let alpha = 1.0 - (step as f32 * 0.12);
-            
-            for (addr, _) in &windows {
-                let _ = Command::new("hyprctl")
-                    .args(["--batch", &format!(
-                        "dispatch movewindowpixel 0 {},address:{} ; dispatch setprop address:{} alpha {:.2} lock",
-                        move_y, addr, addr, alpha
-                    )])
-                    .output();
-            }
-            
-            tokio::time::sleep(tokio::time::Duration::from_millis(30)).await;
+        // Clear open menu tracking
+        let closed = self.open_module.lock().await.take();
+        if let Some(module) = closed {
+            self.announce(&module, "closed");
         }
         
-        // Now kill the processes
-        for (_, pid) in &windows {
-            if *pid > 0 {
-                unsafe {
-                    libc::kill(*pid, libc::SIGTERM);
-                }
+        Ok(())
+    }
+    
+    /// Close the open, unpinned menu when focus moves to a window that isn't a menu.
+    /// Listens to the compositor's focus events and reconnects if they stop.
+    pub async fn watch_focus(self: Arc<Self>) {
+        loop {
+            if let Err(e) = self.follow_focus_events().await {
+                debug!("Focus watcher error: {}", e);
             }
+            tokio::time::sleep(Duration::from_secs(1)).await;
         }
+    }
+    
+    async fn follow_focus_events(&self) -> Result<()> {
+        let mut focus = self.compositor.focus_changes().await?;
         
-        // Clear open menu tracking
-        {
-            let mut open_module = self.open_module.lock().await;
-            *open_module = None;
+        while let Some(id) = focus.recv().await {
+            // Layer surfaces (waybar, launchers) don't report a window
+            if id.is_empty() || self.has_pinned().await || self.open_module.lock().await.is_none() {
+                continue;
+            }
+            if !self.is_menu_window(&id).await {
+                debug!("Focus moved outside the menu, closing");
+                let _ = self.close_all_menus().await;
+            }
         }
         
         Ok(())
     }
     
-    /// Find a menu window's address
-    async fn find_menu_window(&self, module: &str, config: &ModuleConfig) -> Option<String> {
-        let output = Command::new("hyprctl")
-            .args(["clients", "-j"])
-            .output()
-            .ok()?;
-        
-        let clients: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
-        
-        if let Some(clients) = clients.as_array() {
-            for client in clients {
-                if config.kind == "gui" {
-                    // Match by window class for GUI apps
-                    if let Some(window_class) = &config.window_class {
-                        let class = client.get("class")
-                            .and_then(|c| c.as_str())
-                            .unwrap_or("");
-                        if class == window_class {
-                            return client.get("address")
-                                .and_then(|a| a.as_str())
-                                .map(|s| s.to_string());
-                        }
-                    }
-                } else {
-                    // Match by title for TUI apps
-                    let title = client.get("title")
-                        .and_then(|t| t.as_str())
-                        .unwrap_or("");
-                    let expected_title = format!("WAYBAR-MENU: {}", module);
-                    if title.contains(&expected_title) || title == expected_title {
-                        return client.get("address")
-                            .and_then(|a| a.as_str())
-                            .map(|s| s.to_string());
-                    }
-                }
+    /// Check if a window belongs to a menu
+    async fn is_menu_window(&self, id: &str) -> bool {
+        let Ok(windows) = self.compositor.windows().await else {
+            // Can't tell - don't close on a guess
+            return true;
+        };
+        
+        windows.iter()
+            .filter(|w| w.id == id)
+            .any(self.menu_clients())
+    }
+    
+    /// Remember the open menu's window geometry, if its module asks for it
+    async fn record_geometry(&self) {
+        let Some(module) = self.open_module.lock().await.clone() else {
+            return;
+        };
+        let module_configs = self.config();
+        let Some(config) = module_configs.get_module(&module).filter(|c| c.remember_geometry) else {
+            return;
+        };
+        let Some(geometry) = self.find_menu_client(&module, config).await
+            .and_then(|w| w.geometry)
+        else {
+            return;
+        };
+        
+        debug!("Remembering {} menu geometry: {:?}", module, geometry);
+        let mut saved = self.geometry.lock().await;
+        saved.insert(module, geometry);
+        geometry::save(&saved);
+    }
+    
+    /// Wait for a menu window to appear, then float it at its remembered geometry
+    /// or resize it to the configured size
+    async fn place_menu_window(&self, module: &str, config: &ModuleConfig) {
+        let mut addr = None;
+        for _ in 0..40 {
+            addr = self.find_menu_window(module, config).await;
+            if addr.is_some() {
+                break;
             }
+            tokio::time::sleep(Duration::from_millis(50)).await;
         }
+        let Some(addr) = addr else {
+            debug!("Menu window for {} never appeared, not placing it", module);
+            return;
+        };
         
-        None
+        let remembered = if config.remember_geometry {
+            self.geometry.lock().await.get(module).copied()
+        } else {
+            None
+        };
+        
+        let placement = self.bar.lock().await.clone();
+        let anchor = *self.anchor.lock().await;
+        let geometry = remembered.or_else(|| {
+            placement.map(|(bar, monitor)| Self::menu_geometry(config, &bar, monitor, anchor))
+        });
+        
+        // Start out by the bar, faded, and slide into place
+        let animation = self.animation(config, true).await;
+        let animated = animation.steps > 0;
+        let (dx, dy) = animation.direction;
+        let placed = match geometry {
+            Some(g) if animated => {
+                let start = Geometry { x: g.x + dx * animation.distance, y: g.y + dy * animation.distance, ..g };
+                self.compositor.place_window(&addr, start).await
+            }
+            Some(g) => self.compositor.place_window(&addr, g).await,
+            None => {
+                debug!("No focused monitor, leaving {} menu unplaced", module);
+                Ok(())
+            }
+        };
+        let result = async {
+            placed?;
+            let mut props = Self::appearance_props(config);
+            if animated {
+                props.retain(|(prop, _)| *prop != "alpha");
+                props.push(("alpha", "0.04".to_string()));
+            }
+            self.compositor.set_props(&addr, &props).await?;
+            self.compositor.run_window_commands(&addr, &config.window_props).await
+        };
+        if let Err(e) = result.await {
+            debug!("Placing {} menu: {}", module, e);
+        }
+        
+        if animated {
+            let mut animation = animation;
+            if geometry.is_none() {
+                // It was never moved out, so only fade it in
+                animation.direction = (0, 0);
+            }
+            let alpha = config.opacity.map_or(1.0, |o| o.clamp(0.0, 1.0) as f32);
+            self.animate(std::slice::from_ref(&addr), animation, true, alpha).await;
+        }
+    }
+    
+    /// Window props for a module's configured appearance, as (prop, value) pairs
+    fn appearance_props(config: &ModuleConfig) -> Vec<(&'static str, String)> {
+        let mut props = Vec::new();
+        if let Some(opacity) = config.opacity {
+            props.push(("alpha", format!("{:.2}", opacity.clamp(0.0, 1.0))));
+        }
+        if let Some(rounding) = config.rounding {
+            props.push(("rounding", rounding.to_string()));
+        }
+        if let Some(blur) = config.blur {
+            props.push(("noblur", u8::from(!blur).to_string()));
+        }
+        if let Some(shadow) = config.shadow {
+            props.push(("noshadow", u8::from(!shadow).to_string()));
+        }
+        props
+    }
+    
+    /// Where a menu window goes on a monitor: its configured size, in the configured
+    /// corner next to the bar (or beside its widget at `anchor` along the bar), inset by
+    /// `margin` and shifted by `offset`. Next to a side bar the corner's left/right/center
+    /// picks top, bottom or middle.
+    fn menu_geometry(
+        config: &ModuleConfig,
+        bar: &BarConfig,
+        monitor: Geometry,
+        anchor: Option<i32>,
+    ) -> Geometry {
+        let width = config.size[0].resolve(monitor.width);
+        let height = config.size[1].resolve(monitor.height);
+        let margin = config.margin as i32;
+        let anchor = anchor.filter(|_| config.position == "widget");
+        
+        let (x, y) = if bar.is_vertical() {
+            let top = monitor.y + margin;
+            let bottom = monitor.y + monitor.height as i32 - height as i32 - margin;
+            let y = if let Some(anchor) = anchor {
+                (anchor - height as i32 / 2).min(bottom).max(top)
+            } else if config.position.ends_with("left") {
+                top
+            } else if config.position.ends_with("right") {
+                bottom
+            } else {
+                monitor.y + (monitor.height as i32 - height as i32) / 2
+            };
+            (bar.window_x(monitor, width, margin), y)
+        } else {
+            let left = monitor.x + margin;
+            let right = monitor.x + monitor.width as i32 - width as i32 - margin;
+            let x = if let Some(anchor) = anchor {
+                // Keep it on screen when the widget is near an edge
+                (anchor - width as i32 / 2).min(right).max(left)
+            } else if config.position.ends_with("left") {
+                left
+            } else if config.position.ends_with("right") {
+                right
+            } else {
+                monitor.x + (monitor.width as i32 - width as i32) / 2
+            };
+            (x, bar.window_y(monitor, height, margin))
+        };
+        
+        Geometry {
+            x: x + config.offset[0],
+            y: y + config.offset[1],
+            width,
+            height,
+        }
+    }
+    
+    /// Remember which bar a menu is opened from, the monitor it opens on and,
+    /// for menus placed under their widget, where along the bar the widget is
+    async fn enter_bar(&self, module: &str, bar: Option<&str>) {
+        let config = self.config();
+        let monitor = self.event_monitor().await;
+        let output = monitor.as_ref().map(|m| m.name.as_str());
+        let bar = config.bar(bar, module, output);
+        let vertical = bar.is_vertical();
+        *self.bar.lock().await = monitor.map(|monitor| (bar, monitor.geometry));
+        
+        let under_widget = config.get_module(module).is_some_and(|m| m.position == "widget");
+        // Events only carry an x, which says nothing about where on a side bar the widget is
+        let mut anchor = if vertical { None } else { *self.event_x.lock().await };
+        if under_widget && anchor.is_none() {
+            // The widget is where the cursor is now
+            anchor = self.known_cursor().await.map(|(x, y)| if vertical { y } else { x });
+        }
+        *self.anchor.lock().await = anchor;
+    }
+    
+    /// The cursor position, unless the backend can only guess it (Sway) or can't report it
+    async fn known_cursor(&self) -> Option<(i32, i32)> {
+        if let Some(position) = simulate::cursor() {
+            return Some(position);
+        }
+        if !self.compositor.reports_cursor() {
+            return None;
+        }
+        self.compositor.cursor_position().await.ok()
+    }
+    
+    /// The monitor a widget event came from: the one under the cursor, else the focused one
+    async fn event_monitor(&self) -> Option<Monitor> {
+        let monitors = self.compositor.monitors().await.ok()?;
+        let cursor = self.known_cursor().await;
+        let under_cursor = cursor.and_then(|(x, y)| monitors.iter().find(|m| m.geometry.contains(x, y)));
+        under_cursor.or_else(|| monitors.iter().find(|m| m.focused)).cloned()
+    }
+    
+    /// Whether the cursor is over the bar the open menu was opened from
+    async fn is_cursor_in_bar(&self, cursor_x: i32, cursor_y: i32) -> bool {
+        match &*self.bar.lock().await {
+            Some((bar, monitor)) => bar.contains(*monitor, cursor_x, cursor_y),
+            None => Self::in_bar_anywhere(&self.config(), cursor_x, cursor_y),
+        }
+    }
+    
+    /// Whether the cursor is over the bar a hover came from. Backends that only
+    /// guess the cursor position (Sway) or can't report it always pass.
+    async fn is_cursor_on_bar(&self, module: &str, bar: Option<&str>) -> bool {
+        let Some((cursor_x, cursor_y)) = self.known_cursor().await else {
+            return true;
+        };
+        let config = self.config();
+        match self.event_monitor().await {
+            Some(monitor) => config
+                .bar(bar, module, Some(&monitor.name))
+                .contains(monitor.geometry, cursor_x, cursor_y),
+            None => Self::in_bar_anywhere(&config, cursor_x, cursor_y),
+        }
+    }
+    
+    /// Without monitor geometry, whether the cursor is over a `daemon.bar_position` bar.
+    /// Only bars at the top or left edge can be told apart from the rest of the screen.
+    fn in_bar_anywhere(config: &Config, cursor_x: i32, cursor_y: i32) -> bool {
+        let thickness = profile::waybar_height(config) as i32;
+        match config.daemon.bar_position.as_str() {
+            "left" => cursor_x <= thickness,
+            "bottom" | "right" => false,
+            _ => cursor_y <= thickness,
+        }
+    }
+    
+    /// Find a menu window's id
+    async fn find_menu_window(&self, module: &str, config: &ModuleConfig) -> Option<String> {
+        self.find_menu_client(module, config).await.map(|w| w.id)
+    }
+    
+    /// Find a module's menu window
+    async fn find_menu_client(&self, module: &str, config: &ModuleConfig) -> Option<Window> {
+        self.compositor.windows().await.ok()?
+            .into_iter()
+            .find(|w| config.matches_window(module, &w.title, &w.class))
+    }
+    
+    /// Whether a window is a menu window, built once for a batch of windows: a module's
+    /// menu by its own matcher (`match_*_regex` replace the title and class heuristics),
+    /// or a GUI submenu entry by its window class
+    fn menu_clients(&self) -> impl Fn(&Window) -> bool {
+        let config = self.config();
+        let entry_classes: Vec<String> = config
+            .modules
+            .values()
+            .flat_map(|m| &m.entries)
+            .filter(|e| e.kind == "gui")
+            .filter_map(|e| e.window_class.clone())
+            .collect();
+        move |window| {
+            let (title, class) = (window.title.as_str(), window.class.as_str());
+            config.modules.iter().any(|(name, m)| m.matches_window(name, title, class))
+                || entry_classes.iter().any(|c| c == class)
+        }
+    }
+    
+    /// Nudge the pointer so waybar re-evaluates what's under it
+    async fn jiggle_pointer(&self) {
+        if let Err(e) = self.compositor.jiggle_pointer().await {
+            debug!("Pointer jiggle failed: {}", e);
+        }
     }
     
     /// Set gold border on menu window for a module
@@ -433,30 +1234,48 @@ impl MenuManager {
         // Give window time to appear
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         
-        let module_config = self.config.get_module(module);
+        let config = self.config();
+        let module_config = config.get_module(module);
         if let Some(config) = module_config {
             if let Some(addr) = self.find_menu_window(module, config).await {
-                let _ = Command::new("hyprctl")
-                    .args(["dispatch", "setprop", &format!("address:{}", addr), "activebordercolor", "0xffd4a366"])
-                    .output();
+                let gold = [("activebordercolor", "0xffd4a366".to_string())];
+                let _ = self.compositor.set_props(&addr, &gold).await;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Give an unpinned menu window its normal border back
+    async fn clear_menu_border(&self, module: &str) -> Result<()> {
+        let config = self.config();
+        if let Some(config) = config.get_module(module) {
+            if let Some(addr) = self.find_menu_window(module, config).await {
+                let unset = [("activebordercolor", "unset".to_string())];
+                let _ = self.compositor.set_props(&addr, &unset).await;
             }
         }
         Ok(())
     }
     
+    /// Whether opening this module's menu should be suppressed by a fullscreen window
+    async fn suppressed_by_fullscreen(&self, config: &ModuleConfig) -> bool {
+        let enabled = config.suppress_fullscreen.unwrap_or(self.config().daemon.suppress_fullscreen);
+        enabled && self.is_fullscreen_focused().await
+    }
+    
+    /// Check if the focused window is fullscreen
+    async fn is_fullscreen_focused(&self) -> bool {
+        self.compositor.fullscreen_focused().await.unwrap_or(false)
+    }
+    
     /// Get cursor position (x, y)
     async fn get_cursor_pos(&self) -> (i32, i32) {
-        let output = Command::new("hyprctl")
-            .args(["cursorpos", "-j"])
-            .output()
-            .ok();
+        if let Some(position) = simulate::cursor() {
+            return position;
+        }
         
-        if let Some(output) = output {
-            if let Ok(pos) = serde_json::from_slice::<serde_json::Value>(&output.stdout) {
-                let x = pos.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-                let y = pos.get("y").and_then(|v| v.as_i64()).unwrap_or(100) as i32;
-                return (x, y);
-            }
+        if let Ok(position) = self.compositor.cursor_position().await {
+            return position;
         }
         
         (0, 100) // Default to below waybar
@@ -464,53 +1283,37 @@ impl MenuManager {
     
     /// Check if cursor is inside any open menu window
     async fn is_cursor_over_menu(&self, cursor_x: i32, cursor_y: i32) -> bool {
-        let gui_classes: Vec<String> = self.config.modules.values()
-            .filter(|m| m.kind == "gui")
-            .filter_map(|m| m.window_class.clone())
-            .collect();
-
-        let output = Command::new("hyprctl")
-            .args(["clients", "-j"])
-            .output()
-            .ok();
-        
-        if let Some(output) = output {
-            if let Ok(clients) = serde_json::from_slice::<serde_json::Value>(&output.stdout) {
-                if let Some(clients) = clients.as_array() {
-                    for client in clients {
-                        let title = client.get("title")
-                            .and_then(|t| t.as_str())
-                            .unwrap_or("");
-                        let class = client.get("class")
-                            .and_then(|c| c.as_str())
-                            .unwrap_or("");
-                        
-                        // Check if this is a menu window
-                        let is_tui_menu = title.starts_with("WAYBAR-MENU:");
-                        let is_gui_menu = gui_classes.iter().any(|c| c == class);
-                        if !is_tui_menu && !is_gui_menu {
-                            continue;
-                        }
-                        
-                        // Get window position and size
-                        let at = client.get("at").and_then(|a| a.as_array());
-                        let size = client.get("size").and_then(|s| s.as_array());
-                        
-                        if let (Some(at), Some(size)) = (at, size) {
-                            let win_x = at.get(0).and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-                            let win_y = at.get(1).and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-                            let win_w = size.get(0).and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-                            let win_h = size.get(1).and_then(|v| v.as_i64()).unwrap_or(0) as i32;
-                            
-                            // Check if cursor is inside this window (with 10px buffer)
-                            let buffer = 10;
-                            if cursor_x >= win_x - buffer && cursor_x < win_x + win_w + buffer &&
-                               cursor_y >= win_y - buffer && cursor_y < win_y + win_h + buffer {
-                                return true;
-                            }
-                        }
-                    }
+        let Ok(windows) = self.compositor.windows().await else {
+            return false;
+        };
+        
+        let is_menu = self.menu_clients();
+        for window in windows {
+            // Check if this is a menu window
+            if !is_menu(&window) {
+                continue;
+            }
+            
+            // Without geometry (wlroots fallback), the focused menu counts as under the cursor
+            let Some(g) = window.geometry else {
+                if window.focused {
+                    return true;
                 }
+                continue;
+            };
+            let (win_x, win_y) = (g.x, g.y);
+            let (win_w, win_h) = (g.width as i32, g.height as i32);
+            
+            // Check if cursor is inside this window, with a buffer
+            // (a finger lands less precisely than a pointer)
+            let buffer = if self.touch.load(Ordering::SeqCst) {
+                self.config().daemon.touch_buffer
+            } else {
+                10
+            };
+            if cursor_x >= win_x - buffer && cursor_x < win_x + win_w + buffer &&
+               cursor_y >= win_y - buffer && cursor_y < win_y + win_h + buffer {
+                return true;
             }
         }
         
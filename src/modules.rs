@@ -1,30 +1,83 @@
-use anyhow::Result;
-use serde::Serialize;
-use std::path::Path;
-use std::process::Command;
-use walkdir::WalkDir;
-
-/// JSON output format for waybar
-#[derive(Debug, Clone, Serialize)]
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::backlight::Backlight;
+use crate::battery;
+use crate::bluez;
+use crate::cache;
+use crate::charge::ChargeLimit;
+use crate::command;
+use crate::config::{Config, ModuleConfig};
+use crate::dnd;
+use crate::hardware;
+use crate::hotspot;
+use crate::icons;
+use crate::inhibit;
+use crate::mail;
+use crate::media;
+use crate::network;
+use crate::nightlight;
+use crate::notifications;
+use crate::profile;
+use crate::pulse;
+use crate::rfkill;
+use crate::secrets;
+use crate::simulate;
+use crate::tailscale;
+use crate::thermal;
+use crate::updates;
+use crate::urgent;
+use crate::vpn;
+use crate::weather;
+use crate::webhook;
+
+/// JSON output format for waybar (also read back from the status cache)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModuleStatus {
     pub text: String,
-    #[serde(skip_serializing_if = "String::is_empty")]
-    pub class: String,
-    #[serde(skip_serializing_if = "String::is_empty")]
+    /// CSS classes (waybar accepts a list)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub class: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub tooltip: String,
+    /// State name for waybar's `format-icons` lookups (e.g. "muted", "charging")
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub alt: String,
+    /// Level for waybar's percentage-based `format-icons`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<u32>,
+    /// Raw values behind the text (e.g. `ssid`, `volume`), available to action templates
+    #[serde(default, skip_serializing)]
+    pub fields: BTreeMap<String, String>,
+    /// The module's condition needs attention (new mail, failed units, ...)
+    #[serde(skip)]
+    pub urgent: bool,
 }
 
 impl ModuleStatus {
     pub fn new(text: impl Into<String>) -> Self {
         Self {
             text: text.into(),
-            class: String::new(),
+            class: Vec::new(),
             tooltip: String::new(),
+            alt: String::new(),
+            percentage: None,
+            fields: BTreeMap::new(),
+            urgent: false,
         }
     }
 
     pub fn with_class(mut self, class: impl Into<String>) -> Self {
-        self.class = class.into();
+        let class = class.into();
+        if !self.class.contains(&class) {
+            self.class.push(class);
+        }
         self
     }
 
@@ -33,164 +86,600 @@ impl ModuleStatus {
         self
     }
 
+    pub fn with_alt(mut self, alt: impl Into<String>) -> Self {
+        self.alt = alt.into();
+        self
+    }
+
+    pub fn with_percentage(mut self, percentage: u32) -> Self {
+        self.percentage = Some(percentage);
+        self.fields.insert("percent".to_string(), percentage.to_string());
+        self
+    }
+
+    /// The glyph the status is shown with, as the `{icon}` field for `format` templates
+    pub fn with_icon(self, icon: &str) -> Self {
+        self.with_field("icon", icon)
+    }
+
+    pub fn with_urgent(mut self, urgent: bool) -> Self {
+        self.urgent = urgent;
+        self
+    }
+
+    pub fn with_field(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Substitute `{field}` placeholders in a template. `{text}` and `{tooltip}` are always available.
+    pub fn render(&self, template: &str) -> String {
+        self.render_with(template, |v| v.to_string())
+    }
+
+    /// Render a module's `format` template into its text. Fields this status
+    /// doesn't have (no `{ssid}` on ethernet, say) come out empty, and the template's
+    /// own whitespace is collapsed around them. Values are inserted as they are.
+    pub fn format(&self, template: &str) -> String {
+        let mut text = String::with_capacity(template.len());
+        // Template whitespace waiting to be written before the next non-blank piece
+        let mut space = false;
+        let mut last = 0;
+        for caps in command::placeholder().captures_iter(template) {
+            let placeholder = caps.get(0).unwrap();
+            push_literal(&mut text, &mut space, &template[last..placeholder.start()]);
+            if let Some(value) = self.field(&caps[1]).filter(|v| !v.is_empty()) {
+                push_spaced(&mut text, &mut space, value);
+            }
+            last = placeholder.end();
+        }
+        push_literal(&mut text, &mut space, &template[last..]);
+        text
+    }
+
+    /// Like `render`, but escapes values for use inside JSON string literals
+    pub fn render_json(&self, template: &str) -> String {
+        self.render_with(template, |v| {
+            let quoted = serde_json::to_string(v).unwrap_or_default();
+            quoted.trim_matches('"').to_string()
+        })
+    }
+
+    /// Like `render`, but percent-encodes values for use in a URL
+    pub fn render_url(&self, template: &str) -> String {
+        self.render_with(template, |v| {
+            percent_encoding::utf8_percent_encode(v, percent_encoding::NON_ALPHANUMERIC).to_string()
+        })
+    }
+
+    /// Placeholder values for command templates: `text`, `tooltip` and every field
+    pub fn vars(&self) -> Vec<(&str, &str)> {
+        let mut vars = vec![("text", self.text.as_str()), ("tooltip", self.tooltip.as_str())];
+        vars.extend(self.fields.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        vars
+    }
+
+    fn render_with(&self, template: &str, escape: impl Fn(&str) -> String) -> String {
+        command::substitute(template, |key| self.field(key).map(&escape))
+    }
+
+    fn field(&self, key: &str) -> Option<&str> {
+        match key {
+            "text" => Some(&self.text),
+            "tooltip" => Some(&self.tooltip),
+            _ => self.fields.get(key).map(String::as_str),
+        }
+    }
+
+    /// Tooltip with Pango markup tags stripped and entities unescaped
+    pub fn plain_tooltip(&self) -> String {
+        let mut plain = String::with_capacity(self.tooltip.len());
+        let mut in_tag = false;
+        for c in self.tooltip.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                _ if !in_tag => plain.push(c),
+                _ => {}
+            }
+        }
+        plain
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&")
+    }
+
     pub fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap_or_else(|_| r#"{"text":"error"}"#.to_string())
     }
 }
 
+/// Append a piece of a `format` template, turning each run of whitespace into a
+/// pending space that's only written once something follows it
+fn push_literal(text: &mut String, space: &mut bool, literal: &str) {
+    for c in literal.chars() {
+        if c.is_whitespace() {
+            *space = true;
+        } else {
+            push_spaced(text, space, c.encode_utf8(&mut [0; 4]));
+        }
+    }
+}
+
+fn push_spaced(text: &mut String, space: &mut bool, piece: &str) {
+    if *space && !text.is_empty() {
+        text.push(' ');
+    }
+    *space = false;
+    text.push_str(piece);
+}
+
 /// Get status for a specific module
-pub fn get_status(module: &str, pinned: bool) -> ModuleStatus {
-    let mut status = match module {
-        "audio" => get_audio_status(),
+pub fn get_status(config: &Config, module: &str, pinned: bool) -> ModuleStatus {
+    let module_config = config.get_module(module);
+
+    // The active profile (docked, lid closed, ...) can switch modules off
+    if profile::is_disabled(config, module) {
+        return ModuleStatus::new("");
+    }
+
+    // A simulated status (--debug) stands in for the tools, hardware or not
+    let mut status = match simulate::status(module) {
+        Some(status) => status,
+        None => {
+            // Don't show "off" for hardware this machine doesn't have
+            let hide_missing = module_config.is_none_or(|m| m.hide_without_hardware);
+            if hide_missing && !hardware::present(module) {
+                return ModuleStatus::new("");
+            }
+
+            let timeout = module_config.and_then(|m| m.timeout_ms).map(Duration::from_millis);
+            let (status, timed_out) = command::with_timeout(timeout, || read_status(config, module));
+            // Don't blank the widget over a hung tool: keep what the bar last got
+            if timed_out {
+                if let Some(last) = last_shown(module, pinned) {
+                    return last;
+                }
+            }
+            status
+        }
+    };
+
+    if let Some(module_config) = module_config {
+        status = apply_thresholds(status, module, module_config);
+        status = urgent::apply(status, module, module_config);
+
+        if let Some(condition) = &module_config.hide_when {
+            if should_hide(&status, condition) {
+                // Waybar collapses widgets with empty text; keep fields for action templates
+                let mut hidden = ModuleStatus::new("");
+                hidden.fields = status.fields;
+                return hidden;
+            }
+        }
+
+        // Empty text hides the widget; keep it that way
+        if let Some(format) = &module_config.format {
+            if !status.text.is_empty() {
+                status.text = status.format(format);
+            }
+        }
+    }
+
+    if pinned {
+        status = status.with_class("pinned");
+    }
+
+    status
+}
+
+/// The status last sent for a module, from the status cache
+fn last_shown(module: &str, pinned: bool) -> Option<ModuleStatus> {
+    let json = cache::get(module, Duration::MAX)?;
+    let mut status: ModuleStatus = serde_json::from_str(&json).ok()?;
+    status.class.retain(|class| class != "pinned");
+    Some(if pinned { status.with_class("pinned") } else { status })
+}
+
+/// Read a module's raw status from its backing tools
+fn read_status(config: &Config, module: &str) -> ModuleStatus {
+    if let Some(cmd) = config.get_module(module).and_then(|m| m.status_command.as_deref()) {
+        return get_custom_status(config, module, cmd);
+    }
+    match module {
+        "audio" => get_audio_status(config),
         "bluetooth" => get_bluetooth_status(),
-        "network" => get_network_status(),
+        "network" => get_network_status(config),
         "cpu" => get_cpu_status(),
+        "memory" => get_memory_status(),
+        "disk" => get_disk_status(config),
+        "temperature" => get_temperature_status(config),
+        "weather" => get_weather_status(config),
+        "updates" => get_updates_status(),
+        "notifications" => get_notifications_status(config),
+        "idle" => get_idle_status(config),
+        "nightlight" => get_nightlight_status(config),
         "battery" => get_battery_status(),
-        "mail" => get_mail_status(),
+        "mail" => get_mail_status(config),
         "calendar" => get_calendar_status(),
         "localsend" => get_localsend_status(),
-        "vpn" => get_vpn_status(),
-        "surfshark" => get_surfshark_status(),
+        "vpn" | "surfshark" => get_vpn_status(config, module),
+        "tailscale" => get_tailscale_status(),
+        "power" => get_power_status(),
+        "dnd" => get_dnd_status(),
+        "brightness" => get_brightness_status(),
+        "systemd" => get_systemd_status(),
+        "airplane" => get_airplane_status(),
+        "hotspot" => get_hotspot_status(),
+        "media" => get_media_status(),
         _ => ModuleStatus::new("?"),
-    };
+    }
+}
 
-    if pinned {
-        status.class = "pinned".to_string();
+/// Evaluate a `hide_when` condition against a status
+fn should_hide(status: &ModuleStatus, condition: &str) -> bool {
+    let condition = condition.trim();
+
+    if condition == "empty" {
+        return status.text.trim().is_empty();
     }
 
-    status
+    if let Some(cmd) = condition.strip_prefix("cmd:") {
+        // Always a shell snippet; substituted values are quoted
+        let Ok(mut cmd) = command::build(cmd, &status.vars(), true) else {
+            return false;
+        };
+        return command::status(&mut cmd)
+            .map(|s| s.success())
+            .unwrap_or(false);
+    }
+
+    // "<field> <op> <value>"; two-character operators first so "<=" isn't read as "<"
+    for op in ["==", "!=", "<=", ">=", "<", ">"] {
+        let Some((field, expected)) = condition.split_once(op) else {
+            continue;
+        };
+        let field = field.trim().trim_start_matches('{').trim_end_matches('}');
+        let expected = expected.trim().trim_matches('"');
+        let actual = match field {
+            "text" => status.text.as_str(),
+            _ => status.fields.get(field).map(String::as_str).unwrap_or(""),
+        };
+
+        let ordering = match (actual.parse::<f64>(), expected.parse::<f64>()) {
+            (Ok(a), Ok(e)) => a.partial_cmp(&e),
+            _ => Some(actual.cmp(expected)),
+        };
+        let Some(ordering) = ordering else {
+            return false;
+        };
+        return match op {
+            "==" => ordering.is_eq(),
+            "!=" => ordering.is_ne(),
+            "<=" => ordering.is_le(),
+            ">=" => ordering.is_ge(),
+            "<" => ordering.is_lt(),
+            _ => ordering.is_gt(),
+        };
+    }
+
+    tracing::warn!("Invalid hide_when condition: {:?}", condition);
+    false
 }
 
-fn get_audio_status() -> ModuleStatus {
-    // Get mute status
-    let muted = Command::new("pactl")
-        .args(["get-sink-mute", "@DEFAULT_SINK@"])
-        .output()
-        .map(|o| String::from_utf8_lossy(&o.stdout).contains("yes"))
-        .unwrap_or(false);
+/// Field compared against `warning`/`critical` when `threshold_field` isn't set
+fn default_threshold_field(module: &str) -> &'static str {
+    match module {
+        "audio" => "volume",
+        "cpu" => "usage",
+        "memory" => "usage",
+        "disk" => "usage",
+        "temperature" => "temperature",
+        "weather" => "temperature",
+        "updates" => "count",
+        "notifications" => "count",
+        "battery" => "capacity",
+        "mail" => "unread",
+        "brightness" => "brightness",
+        "network" => "signal",
+        _ => "value",
+    }
+}
+
+/// Add `warning`/`critical` classes when the module's value crosses its thresholds.
+/// A module that knows its own limits (a sensor's high and critical marks) suggests
+/// them in `warning_at`/`critical_at` fields, used when the config sets none.
+fn apply_thresholds(status: ModuleStatus, module: &str, config: &ModuleConfig) -> ModuleStatus {
+    let suggested = |key: &str| status.fields.get(key).and_then(|v| v.parse::<f64>().ok());
+    let warning = config.warning.or_else(|| suggested("warning_at"));
+    let critical = config.critical.or_else(|| suggested("critical_at"));
+    if warning.is_none() && critical.is_none() {
+        return status;
+    }
+
+    let field = config
+        .threshold_field
+        .as_deref()
+        .unwrap_or_else(|| default_threshold_field(module));
+    let Some(value) = status.fields.get(field).and_then(|v| v.parse::<f64>().ok()) else {
+        return status;
+    };
+
+    // Lower is worse when critical sits below warning (e.g. battery), unless set explicitly
+    let below = config.threshold_below.unwrap_or(match (warning, critical) {
+        (Some(w), Some(c)) => c < w,
+        _ => matches!(module, "battery" | "network"),
+    });
+    let crossed = |threshold: Option<f64>| {
+        threshold.is_some_and(|t| if below { value <= t } else { value >= t })
+    };
+
+    if crossed(critical) {
+        status.with_class("critical")
+    } else if crossed(warning) {
+        status.with_class("warning")
+    } else {
+        status
+    }
+}
+
+fn get_audio_status(config: &Config) -> ModuleStatus {
+    let model = pulse::state()
+        .map_err(|e| tracing::debug!("Audio server unavailable: {:#}", e))
+        .unwrap_or_default();
+    let (mut volume, muted) = match &model.sink {
+        Some(sink) => (sink.volume, sink.muted),
+        None => wpctl_volume().unwrap_or((0, false)),
+    };
+
+    let tooltip = audio_tooltip(&model);
 
     if muted {
-        return ModuleStatus::new("\u{f6a9}"); // volume-xmark
+        let icon = icons::get("volume-muted");
+        return ModuleStatus::new(icon.clone())
+            .with_icon(&icon)
+            .with_alt("muted")
+            .with_tooltip(tooltip)
+            .with_field("muted", "yes");
     }
 
-    // Get volume using the vol script (handles remapping)
-    let vol_path = shellexpand::tilde("~/.local/bin/vol").to_string();
-    let volume: u32 = Command::new(&vol_path)
-        .arg("get")
-        .output()
-        .map(|o| {
-            String::from_utf8_lossy(&o.stdout)
-                .trim()
-                .parse()
-                .unwrap_or(0)
-        })
-        .unwrap_or(0);
+    // A volume_command replaces the reading, e.g. a script that remaps the curve
+    if let Some(template) = config.get_module("audio").and_then(|m| m.volume_command.as_ref()) {
+        match command_volume(template, config.use_shell("audio")) {
+            Some(v) => volume = v,
+            None => tracing::debug!("volume_command {:?} gave no volume", template),
+        }
+    }
+
+    let icon = icons::get(if volume == 0 {
+        "volume-off"
+    } else if volume < 50 {
+        "volume-low"
+    } else {
+        "volume-high"
+    });
 
-    let icon = if volume == 0 {
-        "\u{f026}" // volume-off
+    let alt = if volume == 0 {
+        "off"
     } else if volume < 50 {
-        "\u{f027}" // volume-low
+        "low"
     } else {
-        "\u{f028}" // volume-high
+        "high"
     };
 
     ModuleStatus::new(format!("{} {}%", icon, volume))
+        .with_icon(&icon)
+        .with_alt(alt)
+        .with_percentage(volume)
+        .with_tooltip(tooltip)
+        .with_field("volume", volume.to_string())
+        .with_field("muted", "no")
 }
 
-fn get_bluetooth_status() -> ModuleStatus {
-    // Check if bluetooth is powered on
-    let powered = Command::new("bluetoothctl")
-        .arg("show")
-        .output()
-        .map(|o| String::from_utf8_lossy(&o.stdout).contains("Powered: yes"))
-        .unwrap_or(false);
+/// Default sink volume in percent and whether it is muted,
+/// from PulseAudio (or pipewire-pulse), else WirePlumber
+fn default_sink_volume() -> Option<(u32, bool)> {
+    pulse::state()
+        .ok()
+        .and_then(|model| model.sink)
+        .map(|sink| (sink.volume, sink.muted))
+        .or_else(wpctl_volume)
+}
 
-    let bt_icon = "\u{f293}"; // bluetooth-b
+fn wpctl_volume() -> Option<(u32, bool)> {
+    // "Volume: 0.40" or "Volume: 0.40 [MUTED]"
+    let output = command::output(Command::new("wpctl")
+        .args(["get-volume", "@DEFAULT_AUDIO_SINK@"]))
+        .ok()
+        .filter(|o| o.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let level: f64 = stdout.split_whitespace().nth(1)?.parse().ok()?;
+    Some(((level * 100.0).round() as u32, stdout.contains("[MUTED]")))
+}
 
-    if !powered {
-        return ModuleStatus::new(format!("{} off", bt_icon));
-    }
+/// Volume printed by a `volume_command` (a number, optionally with `%`)
+fn command_volume(template: &str, shell: bool) -> Option<u32> {
+    let output = command::output(&mut command::build(template, &[], shell).ok()?).ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().trim_end_matches('%').parse().ok()
+}
 
-    // Check for connected devices
-    let connected = Command::new("bluetoothctl")
-        .args(["devices", "Connected"])
-        .output()
-        .ok();
-
-    if let Some(output) = connected {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        if let Some(line) = stdout.lines().next() {
-            // Line format: "Device XX:XX:XX:XX:XX:XX DeviceName"
-            if let Some(name) = line
-                .split_whitespace()
-                .skip(2)
-                .collect::<Vec<_>>()
-                .join(" ")
-                .into()
-            {
-                let name: String = name;
-                if !name.is_empty() {
-                    return ModuleStatus::new(format!("{} {}", bt_icon, name));
-                }
-            }
+/// The output device and per-application stream volumes
+fn audio_tooltip(model: &pulse::Model) -> String {
+    let mut lines = Vec::new();
+    if let Some(sink) = &model.sink {
+        lines.push(format!("Output: {}", markup_escape(&sink.description)));
+    }
+    lines.push("<b>Streams</b>".to_string());
+    for stream in &model.streams {
+        if stream.muted {
+            lines.push(format!("{}: muted", markup_escape(&stream.application)));
+        } else {
+            lines.push(format!("{}: {}%", markup_escape(&stream.application), stream.volume));
         }
     }
 
-    ModuleStatus::new(format!("{} on", bt_icon))
+    if model.streams.is_empty() {
+        lines.push("Nothing playing".to_string());
+    }
+    lines.join("\n")
 }
 
-fn get_network_status() -> ModuleStatus {
-    let wifi_icon = "\u{f1eb}"; // wifi
-    let eth_icon = "\u{f796}"; // ethernet
+/// Escape text for inclusion in Pango markup
+fn markup_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
 
-    // Check for wifi connection via iwctl
-    let wifi_output = Command::new("iwctl")
-        .args(["station", "wlan0", "show"])
-        .output()
-        .ok();
-
-    if let Some(output) = wifi_output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut connected = false;
-        let mut ssid = String::new();
-        for line in stdout.lines() {
-            if line.contains("State") && line.contains("connected") {
-                connected = true;
-            }
-            if line.contains("Connected network") {
-                ssid = line.split_whitespace().last().unwrap_or("").to_string();
-            }
+fn get_bluetooth_status() -> ModuleStatus {
+    let bt_icon = icons::get("bluetooth");
+    let model = match bluez::state() {
+        Ok(model) if model.has_adapter() => model,
+        Ok(_) => {
+            return ModuleStatus::new(format!("{} off", bt_icon))
+                .with_icon(&bt_icon)
+                .with_alt("off")
+                .with_tooltip("<b>Bluetooth</b>\nNo adapter");
         }
-        if connected && !ssid.is_empty() {
-            return ModuleStatus::new(format!("{} {}", wifi_icon, ssid));
+        Err(e) => {
+            tracing::debug!("BlueZ unavailable: {:#}", e);
+            return ModuleStatus::new(format!("{} off", bt_icon)).with_icon(&bt_icon).with_alt("off");
         }
+    };
+
+    if !model.powered() {
+        return ModuleStatus::new(format!("{} off", bt_icon))
+            .with_icon(&bt_icon)
+            .with_alt("off");
     }
 
-    // Check for ethernet via ip — look for physical ethernet interfaces (en*) with state UP
-    let eth_output = Command::new("ip")
-        .args(["-o", "link", "show", "up"])
-        .output()
-        .ok();
-
-    if let Some(output) = eth_output {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for line in stdout.lines() {
-            // Extract interface name (first field after index)
-            let iface = line
-                .split_whitespace()
-                .nth(1)
-                .unwrap_or("")
-                .trim_end_matches(':');
-            if iface.starts_with("en") && line.contains("state UP") {
-                return ModuleStatus::new(eth_icon.to_string());
-            }
+    let devices = model.connected();
+    let Some(first) = devices.first() else {
+        return ModuleStatus::new(format!("{} on", bt_icon))
+            .with_icon(&bt_icon)
+            .with_alt("on")
+            .with_tooltip("<b>Bluetooth</b>\nNo devices connected");
+    };
+
+    let mut tooltip = vec!["<b>Connected</b>".to_string()];
+    for device in &devices {
+        match device.battery {
+            Some(pct) => tooltip.push(format!("{} ({}%)", markup_escape(&device.name), pct)),
+            None => tooltip.push(markup_escape(&device.name)),
         }
     }
 
-    ModuleStatus::new(format!("{} off", wifi_icon))
+    ModuleStatus::new(format!("{} {}", bt_icon, first.name))
+        .with_icon(&bt_icon)
+        .with_alt("connected")
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("device", first.name.clone())
+        .with_field("address", first.address.clone())
+}
+
+fn get_network_status(config: &Config) -> ModuleStatus {
+    let wifi_icon = icons::get("wifi");
+    let eth_icon = icons::get("ethernet");
+
+    let Some(link) = network::current() else {
+        return ModuleStatus::new(format!("{} off", wifi_icon))
+            .with_icon(&wifi_icon)
+            .with_alt("disconnected")
+            .with_tooltip("Disconnected");
+    };
+    // iwd and bare ethernet leave addressing to something else
+    let ip = if link.ip.is_empty() { interface_ip(&link.interface) } else { link.ip.clone() };
+    let gateway = if link.gateway.is_empty() { default_gateway(&link.interface) } else { link.gateway.clone() };
+
+    let Some(wifi) = link.wifi else {
+        let tooltip = format!("<b>Ethernet ({})</b>\nIP: {}\nGateway: {}", link.interface, ip, gateway);
+        return ModuleStatus::new(eth_icon.clone())
+            .with_icon(&eth_icon)
+            .with_alt("ethernet")
+            .with_tooltip(tooltip)
+            .with_field("ip", ip)
+            .with_field("interface", link.interface);
+    };
+
+    let mut tooltip = format!("<b>{}</b>\nIP: {}\nGateway: {}", markup_escape(&wifi.ssid), ip, gateway);
+    if let Some(signal) = wifi.signal {
+        match wifi.dbm {
+            Some(dbm) => tooltip.push_str(&format!("\nSignal: {}% ({} dBm)", signal, dbm)),
+            None => tooltip.push_str(&format!("\nSignal: {}%", signal)),
+        }
+    }
+    if let Some(freq) = wifi.frequency {
+        tooltip.push_str(&format!("\nFrequency: {} MHz ({})", freq, wifi_band(freq)));
+    }
+
+    let format = config.get_module("network").map_or("bars", |m| m.signal_format.as_str());
+    let text = match (format, wifi.signal) {
+        ("percent", Some(signal)) => format!("{} {} {}%", wifi_icon, wifi.ssid, signal),
+        ("bars", Some(signal)) => format!("{} {} {}", wifi_icon, wifi.ssid, signal_bars(signal)),
+        _ => format!("{} {}", wifi_icon, wifi.ssid),
+    };
+
+    let mut status = ModuleStatus::new(text)
+        .with_icon(&wifi_icon)
+        .with_alt("wifi")
+        .with_tooltip(tooltip)
+        .with_field("ip", ip)
+        .with_field("interface", link.interface)
+        .with_field("ssid", wifi.ssid);
+    if let Some(signal) = wifi.signal {
+        status = status.with_percentage(signal).with_field("signal", signal.to_string());
+    }
+    if let Some(freq) = wifi.frequency {
+        status = status.with_field("frequency", freq.to_string());
+    }
+    status
+}
+
+/// Signal quality as one to four bars
+fn signal_bars(signal: u32) -> String {
+    let bars = (signal.div_ceil(25)).clamp(1, 4) as usize;
+    "\u{2582}\u{2584}\u{2586}\u{2588}".chars().take(bars).collect()
+}
+
+fn wifi_band(freq: u32) -> &'static str {
+    match freq {
+        0..=2500 => "2.4 GHz",
+        2501..=5924 => "5 GHz",
+        _ => "6 GHz",
+    }
+}
+
+/// Default gateway reached through an interface, or empty if none
+fn default_gateway(iface: &str) -> String {
+    command::output(Command::new("ip")
+        .args(["route", "show", "default", "dev", iface]))
+        .ok()
+        .and_then(|o| {
+            // Line format: "default via 192.168.1.1 proto dhcp ..."
+            let stdout = String::from_utf8_lossy(&o.stdout);
+            let mut words = stdout.split_whitespace().skip_while(|w| *w != "via");
+            words.nth(1).map(str::to_string)
+        })
+        .unwrap_or_default()
+}
+
+/// First IPv4 address of an interface, or empty if it has none
+fn interface_ip(iface: &str) -> String {
+    command::output(Command::new("ip")
+        .args(["-4", "-o", "addr", "show", "dev", iface]))
+        .ok()
+        .and_then(|o| {
+            // Line format: "3: wlan0    inet 192.168.1.20/24 brd ..."
+            let stdout = String::from_utf8_lossy(&o.stdout);
+            let mut words = stdout.split_whitespace().skip_while(|w| *w != "inet");
+            words.nth(1).map(|addr| addr.split('/').next().unwrap_or("").to_string())
+        })
+        .unwrap_or_default()
 }
 
 fn get_cpu_status() -> ModuleStatus {
+    let icon = icons::get("cpu");
+
     // Read /proc/stat for CPU usage
     let stat = std::fs::read_to_string("/proc/stat").unwrap_or_default();
 
@@ -207,20 +696,266 @@ fn get_cpu_status() -> ModuleStatus {
             let idle = parts[3];
             let total = user + system + idle;
 
-            if total > 0 {
-                let usage = ((user + system) * 100) / total;
-                return ModuleStatus::new(format!("\u{f2db} {}%", usage)); // microchip
+            if let Some(usage) = ((user + system) * 100).checked_div(total) {
+                return ModuleStatus::new(format!("{} {}%", icon, usage))
+                    .with_icon(&icon)
+                    .with_percentage(usage as u32)
+                    .with_field("usage", usage.to_string());
             }
         }
     }
 
-    ModuleStatus::new("\u{f2db} ?%") // microchip
+    ModuleStatus::new(format!("{} ?%", icon))
+        .with_icon(&icon)
 }
 
-fn get_battery_status() -> ModuleStatus {
-    // Find the first battery in /sys/class/power_supply/
+/// Memory in use (total minus what is available without swapping), with swap in the tooltip
+fn get_memory_status() -> ModuleStatus {
+    let icon = icons::get("memory");
+    let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+    // Line format: "MemAvailable:    8123456 kB"
+    let kib = |key: &str| -> Option<u64> {
+        meminfo
+            .lines()
+            .find_map(|l| l.strip_prefix(key)?.strip_prefix(':'))?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    };
+
+    let (Some(total), Some(available)) = (kib("MemTotal"), kib("MemAvailable")) else {
+        return ModuleStatus::new(format!("{} ?", icon)).with_icon(&icon);
+    };
+    let used = total.saturating_sub(available);
+    let usage = (used * 100).checked_div(total).unwrap_or(0) as u32;
+    let swap_total = kib("SwapTotal").unwrap_or(0);
+    let swap_used = swap_total.saturating_sub(kib("SwapFree").unwrap_or(0));
+
+    let mut tooltip = vec![
+        format!("<b>Memory</b> {}%", usage),
+        format!("Used: {} of {}", gib(used), gib(total)),
+        format!("Available: {}", gib(available)),
+    ];
+    if swap_total > 0 {
+        tooltip.push(format!("Swap: {} of {}", gib(swap_used), gib(swap_total)));
+    }
+
+    ModuleStatus::new(format!("{} {}/{}", icon, gib(used), gib(total)))
+        .with_icon(&icon)
+        .with_percentage(usage)
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("usage", usage.to_string())
+        .with_field("used", gib(used))
+        .with_field("total", gib(total))
+        .with_field("swap_used", gib(swap_used))
+        .with_field("swap_total", gib(swap_total))
+}
+
+/// A size in KiB as GiB with one decimal, e.g. "7.8G"
+fn gib(kib: u64) -> String {
+    format!("{:.1}G", kib as f64 / (1024.0 * 1024.0))
+}
+
+/// A hwmon temperature. Its thresholds default to the sensor's own high and critical
+/// marks, else 80°C and 95°C, through the `warning_at` and `critical_at` fields.
+fn get_temperature_status(config: &Config) -> ModuleStatus {
+    let icon = icons::get("temperature");
+    let module_config = config.get_module("temperature");
+    let Some(sensor) = thermal::find(module_config.and_then(|m| m.sensor.as_deref())) else {
+        return ModuleStatus::new(""); // no sensor — hide module
+    };
+
+    let warning = module_config.and_then(|m| m.warning).or(sensor.max).unwrap_or(80.0);
+    let critical = module_config.and_then(|m| m.critical).or(sensor.crit).unwrap_or(95.0);
+
+    let mut tooltip = vec![format!("<b>{}</b>", markup_escape(&sensor.chip))];
+    for input in thermal::siblings(&sensor) {
+        tooltip.push(format!("{}: {:.0}°C", markup_escape(&input.label), input.celsius));
+    }
+    tooltip.push(format!("Warning at {:.0}°C, critical at {:.0}°C", warning, critical));
+
+    let celsius = sensor.celsius.round();
+    ModuleStatus::new(format!("{} {:.0}°C", icon, celsius))
+        .with_icon(&icon)
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("temperature", format!("{:.0}", celsius))
+        .with_field("sensor", sensor.label)
+        .with_field("chip", sensor.chip)
+        .with_field("warning_at", format!("{}", warning))
+        .with_field("critical_at", format!("{}", critical))
+}
+
+fn get_weather_status(config: &Config) -> ModuleStatus {
+    let Some(module_config) = config.get_module("weather") else {
+        return ModuleStatus::new("");
+    };
+    // Nothing fetched yet (offline at startup) — hide module until a fetch works
+    let Some((conditions, stale)) = weather::current(module_config) else {
+        return ModuleStatus::new("");
+    };
+
+    let (degrees, speed) = if module_config.units == "imperial" { ("°F", "mph") } else { ("°C", "km/h") };
+    let icon = icons::get(&conditions.sky);
+    let temperature = format!("{:.0}", conditions.temperature);
+
+    let mut tooltip = Vec::new();
+    if !conditions.place.is_empty() {
+        tooltip.push(format!("<b>{}</b>", markup_escape(&conditions.place)));
+    }
+    tooltip.push(format!("{}, {}{}", markup_escape(&conditions.description), temperature, degrees));
+    tooltip.push(format!("Feels like {:.0}{}", conditions.feels_like, degrees));
+    tooltip.push(format!("Humidity: {:.0}%", conditions.humidity));
+    tooltip.push(format!("Wind: {:.0} {}", conditions.wind, speed));
+    if let Some(age) = stale {
+        tooltip.push(format!("<i>Offline — last updated {} min ago</i>", age.as_secs() / 60));
+    }
+
+    let mut status = ModuleStatus::new(format!("{} {}{}", icon, temperature, degrees))
+        .with_icon(&icon)
+        .with_alt(conditions.sky.trim_start_matches("weather-"))
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("temperature", temperature)
+        .with_field("feels_like", format!("{:.0}", conditions.feels_like))
+        .with_field("humidity", format!("{:.0}", conditions.humidity))
+        .with_field("wind", format!("{:.0}", conditions.wind))
+        .with_field("description", conditions.description)
+        .with_field("place", conditions.place);
+    if stale.is_some() {
+        status = status.with_class("stale");
+    }
+    status
+}
+
+/// Packages listed in the updates tooltip before it says how many more there are
+const MAX_LISTED_UPDATES: usize = 25;
+
+fn get_updates_status() -> ModuleStatus {
+    // Checks are slow, so they run in the updates watcher; this only reads their result
+    let Some((age, pending)) = updates::pending() else {
+        return ModuleStatus::new(""); // not checked yet
+    };
+    if pending.is_empty() {
+        return ModuleStatus::new("");
+    }
+
+    let icon = icons::get("updates");
+    let count = pending.len();
+    let mut tooltip = vec![format!("<b>{} update{}</b>", count, if count == 1 { "" } else { "s" })];
+    for update in pending.iter().take(MAX_LISTED_UPDATES) {
+        let versions = if update.from.is_empty() {
+            update.to.clone()
+        } else {
+            format!("{} → {}", update.from, update.to)
+        };
+        tooltip.push(format!(
+            "{} <small>{}</small> ({})",
+            markup_escape(&update.name),
+            markup_escape(&versions),
+            update.source
+        ));
+    }
+    if count > MAX_LISTED_UPDATES {
+        tooltip.push(format!("… and {} more", count - MAX_LISTED_UPDATES));
+    }
+    tooltip.push(format!("Checked {} min ago", age.as_secs() / 60));
+
+    let mut status = ModuleStatus::new(format!("{} {}", icon, count))
+        .with_icon(&icon)
+        .with_class("pending")
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("count", count.to_string());
+    // Per-checker counts, e.g. {pacman} and {flatpak}
+    let mut sources: BTreeMap<&str, usize> = BTreeMap::new();
+    for update in &pending {
+        *sources.entry(update.source).or_default() += 1;
+    }
+    for (source, n) in sources {
+        status = status.with_field(source, n.to_string());
+    }
+    status
+}
+
+/// Space on one filesystem, in bytes
+struct DiskUsage {
+    mountpoint: String,
+    used: u64,
+    available: u64,
+}
+
+impl DiskUsage {
+    fn read(mountpoint: &str) -> Option<Self> {
+        let path = std::ffi::CString::new(mountpoint).ok()?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        let block = stat.f_frsize as u64;
+        let total = stat.f_blocks as u64 * block;
+        Some(Self {
+            mountpoint: mountpoint.to_string(),
+            used: total.saturating_sub(stat.f_bfree as u64 * block),
+            available: stat.f_bavail as u64 * block,
+        })
+    }
+
+    /// Percent in use, of the space usable without root, rounded up (like df)
+    fn usage(&self) -> u32 {
+        let usable = self.used + self.available;
+        if usable == 0 {
+            return 0;
+        }
+        (self.used * 100).div_ceil(usable) as u32
+    }
+}
+
+/// Usage of the fullest configured mountpoint, with every one in the tooltip
+fn get_disk_status(config: &Config) -> ModuleStatus {
+    let icon = icons::get("disk");
+    let mountpoints = config
+        .get_module("disk")
+        .map_or_else(|| vec!["/".to_string()], |m| m.mountpoints.clone());
+    let disks: Vec<DiskUsage> = mountpoints
+        .iter()
+        .filter_map(|m| {
+            let disk = DiskUsage::read(&shellexpand::tilde(m));
+            if disk.is_none() {
+                tracing::debug!("Can't stat mountpoint {}", m);
+            }
+            disk
+        })
+        .collect();
+
+    let Some(fullest) = disks.iter().max_by_key(|d| d.usage()) else {
+        return ModuleStatus::new(format!("{} ?", icon)).with_icon(&icon);
+    };
+
+    let mut tooltip = vec!["<b>Disks</b>".to_string()];
+    for disk in &disks {
+        tooltip.push(format!(
+            "{}: {}% ({} used, {} free)",
+            markup_escape(&disk.mountpoint),
+            disk.usage(),
+            gib(disk.used / 1024),
+            gib(disk.available / 1024)
+        ));
+    }
+
+    let usage = fullest.usage();
+    ModuleStatus::new(format!("{} {}%", icon, usage))
+        .with_icon(&icon)
+        .with_percentage(usage)
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("usage", usage.to_string())
+        .with_field("mount", fullest.mountpoint.clone())
+        .with_field("used", gib(fullest.used / 1024))
+        .with_field("free", gib(fullest.available / 1024))
+}
+
+/// The first battery in /sys/class/power_supply/
+fn find_battery() -> Option<PathBuf> {
     let ps_dir = Path::new("/sys/class/power_supply");
-    let battery_path = std::fs::read_dir(ps_dir)
+    std::fs::read_dir(ps_dir)
         .ok()
         .and_then(|entries| {
             entries.filter_map(|e| e.ok()).find(|e| {
@@ -230,9 +965,11 @@ fn get_battery_status() -> ModuleStatus {
                     .unwrap_or(false)
             })
         })
-        .map(|e| e.path());
+        .map(|e| e.path())
+}
 
-    let battery_path = match battery_path {
+fn get_battery_status() -> ModuleStatus {
+    let battery_path = match find_battery() {
         Some(p) => p,
         None => return ModuleStatus::new("".to_string()), // no battery — hide module
     };
@@ -246,95 +983,811 @@ fn get_battery_status() -> ModuleStatus {
         .unwrap_or_else(|_| "Unknown".to_string());
 
     let cap_num: u32 = capacity.parse().unwrap_or(0);
-    let bat_icon = match status.as_str() {
-        "Charging" => "\u{f0e7}",        // bolt
-        "Full" => "\u{f1e6}",            // plug
-        _ if cap_num > 75 => "\u{f240}", // battery-full
-        _ if cap_num > 50 => "\u{f241}", // battery-three-quarters
-        _ if cap_num > 25 => "\u{f242}", // battery-half
-        _ if cap_num > 10 => "\u{f243}", // battery-quarter
-        _ => "\u{f244}",                 // battery-empty
-    };
+    let bat_icon = icons::get(match status.as_str() {
+        "Charging" => "battery-charging",
+        "Full" => "battery-plugged",
+        _ if cap_num > 75 => "battery-full",
+        _ if cap_num > 50 => "battery-three-quarters",
+        _ if cap_num > 25 => "battery-half",
+        _ if cap_num > 10 => "battery-quarter",
+        _ => "battery-empty",
+    });
 
     let text = match status.as_str() {
-        "Full" => bat_icon.to_string(),
+        "Full" => bat_icon.clone(),
         "Charging" => format!("{} {}%", bat_icon, capacity),
         _ => format!("{} {}%", bat_icon, capacity),
     };
 
-    ModuleStatus::new(text)
+    let mut tooltip = vec![format!("<b>{}</b> {}%", status, capacity)];
+    if let Some(health) = battery_health(&battery_path) {
+        tooltip.push(format!("Health: {}%", health));
+    }
+    if let Some(watts) = battery::power_draw(&battery_path) {
+        tooltip.push(format!("Power draw: {:.1} W", watts));
+    }
+    let charging = status == "Charging";
+    let remaining = match status.as_str() {
+        "Charging" | "Discharging" => battery::time_remaining(&battery_path, charging),
+        _ => None,
+    };
+    if let Some(remaining) = remaining {
+        let label = if charging { "Time to full" } else { "Time to empty" };
+        tooltip.push(format!("{}: {}", label, battery::format_duration(remaining)));
+    }
+    let charge_limit = ChargeLimit::find(&battery_path);
+    if let Some(limit) = &charge_limit {
+        tooltip.push(limit.describe());
+    }
+    let limited = charge_limit.is_some_and(|l| l.is_active());
+
+    let status_alt = status.to_lowercase().replace(' ', "-");
+    let mut battery = ModuleStatus::new(text);
+    if limited {
+        battery = battery.with_class("charge-limited");
+    }
+    if matches!(status_alt.as_str(), "charging" | "discharging") {
+        battery = battery.with_class(status_alt.clone());
+    }
+    if let Some(remaining) = remaining {
+        battery = battery.with_field("minutes_remaining", (remaining.as_secs() / 60).to_string());
+    }
+    battery
+        .with_icon(&bat_icon)
+        .with_alt(status_alt)
+        .with_percentage(cap_num)
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("capacity", capacity)
+        .with_field("status", status)
+        .with_field("charge_limited", limited.to_string())
 }
 
-fn get_mail_status() -> ModuleStatus {
-    let mail_dir = shellexpand::tilde("~/.local/share/mail").to_string();
-    let mail_path = Path::new(&mail_dir);
-
-    let mut unread = 0;
-
-    if mail_path.exists() {
-        // Count files in */INBOX/new/
-        for entry in WalkDir::new(mail_path).into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(parent) = path.parent() {
-                    if parent.ends_with("new") {
-                        if let Some(grandparent) = parent.parent() {
-                            if grandparent.ends_with("INBOX") {
-                                unread += 1;
-                            }
-                        }
-                    }
-                }
-            }
+/// Full-charge capacity relative to design capacity, in percent
+fn battery_health(battery_path: &Path) -> Option<u64> {
+    // Batteries report either energy_* (µWh) or charge_* (µAh)
+    for prefix in ["energy", "charge"] {
+        let full = read_sysfs_number(&battery_path.join(format!("{}_full", prefix)));
+        let design = read_sysfs_number(&battery_path.join(format!("{}_full_design", prefix)));
+        if let (Some(full), Some(design)) = (full, design) {
+            return (full * 100).checked_div(design);
         }
     }
+    None
+}
 
-    // Unicode envelope
-    let envelope = "\u{f0e0}";
+fn read_sysfs_number(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
 
+fn get_mail_status(config: &Config) -> ModuleStatus {
+    let module_config = config.get_module("mail");
+    let mail_dir = module_config
+        .and_then(|m| m.watch_dir.as_deref())
+        .unwrap_or("~/.local/share/mail");
+    let mail_path = shellexpand::tilde(mail_dir).to_string();
+    let mailbox = mail::scan(Path::new(&mail_path));
+    let unread = mailbox.unread();
+    let show_flagged = module_config.is_some_and(|m| m.mail_flagged);
+
+    let envelope = icons::get("mail");
+
+    let mut text = envelope.clone();
     if unread > 0 {
-        ModuleStatus::new(format!("{} {}", envelope, unread))
-    } else {
-        ModuleStatus::new(envelope.to_string())
+        text.push_str(&format!(" {}", unread));
+    }
+    if show_flagged && mailbox.flagged > 0 {
+        text.push_str(&format!(" {}{}", icons::get("mail-flagged"), mailbox.flagged));
+    }
+
+    let mut tooltip = vec![format!("<b>{} unread</b>", unread)];
+    for (account, count) in &mailbox.accounts {
+        tooltip.push(format!("{}: {}", markup_escape(account), count));
     }
+    if show_flagged {
+        tooltip.push(format!("Flagged: {}", mailbox.flagged));
+    }
+
+    let mut status = ModuleStatus::new(text);
+    if mail::has_new(unread) {
+        status = status.with_class("new");
+    }
+    status
+        .with_icon(&envelope)
+        .with_urgent(unread > 0)
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("unread", unread.to_string())
+        .with_field("flagged", mailbox.flagged.to_string())
 }
 
 fn get_calendar_status() -> ModuleStatus {
     // Show current date and time
-    let output = Command::new("date")
-        .args(["+%a %d %b %H:%M"])
-        .output()
+    let output = command::output(Command::new("date")
+        .args(["+%a %d %b %H:%M"]))
         .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
         .unwrap_or_else(|_| "???".to_string());
 
-    ModuleStatus::new(format!("\u{f073} {}", output)) // calendar
+    let icon = icons::get("calendar");
+    ModuleStatus::new(format!("{} {}", icon, output))
+        .with_icon(&icon)
+        .with_field("datetime", output)
 }
 
 fn get_localsend_status() -> ModuleStatus {
-    ModuleStatus::new("\u{2191}\u{2193}") // ↑↓
+    let icon = icons::get("localsend");
+    ModuleStatus::new(icon.clone()).with_icon(&icon)
 }
 
-fn get_vpn_status() -> ModuleStatus {
-    let shield_icon = "\u{f3ed}"; // shield-halved
-    let up = std::process::Command::new("ip")
-        .args(["link", "show", "wg0"])
-        .output()
-        .map(|o| String::from_utf8_lossy(&o.stdout).contains("UP"))
-        .unwrap_or(false);
-    if up {
-        ModuleStatus::new(shield_icon.to_string())
+fn get_vpn_status(config: &Config, module: &str) -> ModuleStatus {
+    // "surfshark" is the same module with its own icon
+    let icon = icons::get(if module == "surfshark" { "surfshark" } else { "vpn" });
+    let module_config = config.get_module(module).cloned().unwrap_or_default();
+    let tunnels = match vpn::tunnels(module_config.vpn_provider.as_deref(), &module_config.interfaces) {
+        Ok(tunnels) => tunnels,
+        Err(e) => {
+            tracing::debug!("VPN status unavailable: {:#}", e);
+            Vec::new()
+        }
+    };
+
+    let Some(first) = tunnels.first() else {
+        return ModuleStatus::new(format!("{} off", icon))
+            .with_icon(&icon)
+            .with_alt("disconnected")
+            .with_class("disconnected")
+            .with_tooltip("VPN disconnected");
+    };
+
+    let mut tooltip = vec!["<b>VPN connected</b>".to_string()];
+    for tunnel in &tunnels {
+        let mut line = format!("{} ({})", markup_escape(&tunnel.name), tunnel.provider);
+        if let Some(ip) = tunnel.interface.as_deref().map(interface_ip).filter(|ip| !ip.is_empty()) {
+            line.push_str(&format!(" {}", ip));
+        }
+        tooltip.push(line);
+        if let Some(exit) = &tunnel.exit {
+            tooltip.push(format!("Exit: {}", markup_escape(exit)));
+        }
+    }
+    let mut exit = first.exit.clone();
+    if exit.is_none() && module_config.exit_lookup {
+        exit = vpn::public_exit(&tunnels);
+        if let Some(exit) = &exit {
+            tooltip.push(format!("Exit: {}", markup_escape(exit)));
+        }
+    }
+
+    ModuleStatus::new(icon.clone())
+        .with_icon(&icon)
+        .with_alt("connected")
+        .with_class("connected")
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("tunnel", first.name.clone())
+        .with_field("provider", first.provider)
+        .with_field("exit", exit.unwrap_or_default())
+}
+
+/// Online peers listed in the tailscale tooltip before it says how many more there are
+const MAX_LISTED_PEERS: usize = 15;
+
+fn get_tailscale_status() -> ModuleStatus {
+    let icon = icons::get("tailscale");
+    let state = match tailscale::status() {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::debug!("Tailscale status unavailable: {:#}", e);
+            return ModuleStatus::new(""); // tailscale not installed or not running — hide module
+        }
+    };
+    if !state.is_running() {
+        return ModuleStatus::new(format!("{} off", icon))
+            .with_icon(&icon)
+            .with_alt("disconnected")
+            .with_class("disconnected")
+            .with_tooltip(format!("Tailscale: {}", markup_escape(&state.backend_state)))
+            .with_field("state", state.backend_state);
+    }
+
+    let mut tooltip = vec![format!("<b>Tailscale</b> {}", markup_escape(&state.tailnet))];
+    if let Some(own) = &state.own {
+        tooltip.push(format!("{} {}", markup_escape(&own.host_name), own.ip));
+    }
+    let exit_node = state.exit_node();
+    if let Some(node) = exit_node {
+        let location = node.location.as_deref().map(|l| format!(" ({})", markup_escape(l))).unwrap_or_default();
+        tooltip.push(format!("Exit node: {}{}", markup_escape(&node.host_name), location));
+    }
+    let online: Vec<_> = state.peers.iter().filter(|p| p.online).collect();
+    tooltip.push(format!("<b>{} of {} peers online</b>", online.len(), state.peers.len()));
+    for peer in online.iter().take(MAX_LISTED_PEERS) {
+        let offers_exit = if peer.exit_node_option { " · exit node" } else { "" };
+        tooltip.push(format!(
+            "{} <small>{} {}{}</small>",
+            markup_escape(&peer.host_name),
+            peer.ip,
+            markup_escape(&peer.os),
+            offers_exit
+        ));
+    }
+    if online.len() > MAX_LISTED_PEERS {
+        tooltip.push(format!("… and {} more", online.len() - MAX_LISTED_PEERS));
+    }
+
+    let text = match exit_node {
+        Some(node) => format!("{} {}", icon, node.host_name),
+        None => icon.clone(),
+    };
+    let mut status = ModuleStatus::new(text)
+        .with_icon(&icon)
+        .with_alt(if exit_node.is_some() { "exit-node" } else { "connected" })
+        .with_class("connected")
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("state", state.backend_state.clone())
+        .with_field("tailnet", state.tailnet.clone())
+        .with_field("exit_node", exit_node.map(|n| n.host_name.clone()).unwrap_or_default())
+        .with_field("peers", online.len().to_string());
+    if exit_node.is_some() {
+        status = status.with_class("exit-node");
+    }
+    status
+}
+
+/// Current backlight level in percent
+fn brightness_percent() -> Option<u32> {
+    Backlight::find()?.percent()
+}
+
+fn get_brightness_status() -> ModuleStatus {
+    match brightness_percent() {
+        Some(percent) => {
+            let icon = icons::get("brightness");
+            ModuleStatus::new(format!("{} {}%", icon, percent))
+                .with_icon(&icon)
+                .with_percentage(percent)
+                .with_field("brightness", percent.to_string())
+        }
+        None => ModuleStatus::new(""), // no backlight — hide module
+    }
+}
+
+/// Failed systemd units, system and user
+fn get_systemd_status() -> ModuleStatus {
+    let mut failed = Vec::new();
+    for scope in [None, Some("--user")] {
+        let mut cmd = Command::new("systemctl");
+        cmd.args(scope);
+        cmd.args(["--failed", "--no-legend", "--plain"]);
+        if let Ok(output) = command::output(&mut cmd) {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            // Line format: "foo.service loaded failed failed Description"
+            failed.extend(stdout.lines().filter_map(|l| l.split_whitespace().next()).map(str::to_string));
+        }
+    }
+
+    let icon = icons::get("systemd");
+    if failed.is_empty() {
+        return ModuleStatus::new("").with_field("failed", "0"); // nothing failed — hide module
+    }
+
+    let tooltip = format!("<b>Failed units</b>\n{}", markup_escape(&failed.join("\n")));
+    ModuleStatus::new(format!("{} {}", icon, failed.len()))
+        .with_icon(&icon)
+        .with_tooltip(tooltip)
+        .with_urgent(true)
+        .with_field("failed", failed.len().to_string())
+}
+
+/// Status of a custom module from its `status_command`
+fn get_custom_status(config: &Config, module: &str, template: &str) -> ModuleStatus {
+    let output = command::build(template, &[], config.use_shell(module))
+        .map_err(|e| e.to_string())
+        .and_then(|mut cmd| command::output(&mut cmd).map_err(|e| e.to_string()));
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::warn!("{} status_command failed: {}", module, stderr.trim());
+            return ModuleStatus::new("?").with_class("error");
+        }
+        Err(e) => {
+            tracing::warn!("{} status_command: {}", module, e);
+            return ModuleStatus::new("?").with_class("error");
+        }
+    };
+    parse_custom_status(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Read a custom module's output the way waybar reads a `custom/` module's:
+/// a JSON object, or lines of text, tooltip and class
+fn parse_custom_status(stdout: &str) -> ModuleStatus {
+    let stdout = stdout.trim();
+    if stdout.starts_with('{') {
+        if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(stdout) {
+            // Waybar takes a single class as a plain string
+            if let Some(class) = json.get("class").and_then(|c| c.as_str()).map(str::to_string) {
+                json["class"] = serde_json::json!([class]);
+            }
+            if let Ok(status) = serde_json::from_value::<ModuleStatus>(json) {
+                // Let thresholds and hide_when compare the percentage as `value`
+                return match status.percentage {
+                    Some(percentage) => status
+                        .with_percentage(percentage)
+                        .with_field("value", percentage.to_string()),
+                    None => status,
+                };
+            }
+        }
+    }
+
+    let mut lines = stdout.lines();
+    let text = lines.next().unwrap_or("");
+    let mut status = ModuleStatus::new(text).with_field("value", text);
+    if let Some(tooltip) = lines.next() {
+        status = status.with_tooltip(tooltip);
+    }
+    if let Some(class) = lines.next().filter(|c| !c.is_empty()) {
+        status = status.with_class(class);
+    }
+    status
+}
+
+/// Whether radios are blocked, with a per-radio breakdown
+fn get_airplane_status() -> ModuleStatus {
+    let radios = rfkill::radios();
+    let blocked = radios.iter().filter(|r| r.is_blocked()).count();
+
+    let mut tooltip = vec!["<b>Radios</b>".to_string()];
+    for radio in &radios {
+        let state = match (radio.hard, radio.soft) {
+            (true, _) => "blocked (switch)",
+            (false, true) => "blocked",
+            (false, false) => "on",
+        };
+        tooltip.push(format!("{} ({}): {}", markup_escape(&radio.name), radio.kind, state));
+    }
+
+    let (icon, alt) = if blocked == 0 {
+        (icons::get("wifi"), "off")
+    } else if blocked == radios.len() {
+        (icons::get("airplane"), "on")
     } else {
-        ModuleStatus::new(format!("{} off", shield_icon))
+        (icons::get("airplane"), "partial")
+    };
+    let mut status = ModuleStatus::new(icon.clone())
+        .with_icon(&icon)
+        .with_alt(alt)
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("blocked", blocked.to_string())
+        .with_field("radios", radios.len().to_string());
+    if blocked > 0 {
+        status = status.with_class("blocked");
+    }
+    status
+}
+
+/// Whether a hotspot is running, and how many clients it has
+fn get_hotspot_status() -> ModuleStatus {
+    let icon = icons::get("hotspot");
+    let Some(hotspot) = hotspot::active() else {
+        return ModuleStatus::new(icon.clone())
+            .with_icon(&icon)
+            .with_alt("off")
+            .with_tooltip("Hotspot off")
+            .with_field("clients", "0");
+    };
+
+    let clients = hotspot::client_count(&hotspot.device);
+    let mut tooltip = format!("<b>{}</b> on {}", markup_escape(&hotspot.name), hotspot.device);
+    if let Some(clients) = clients {
+        tooltip.push_str(&format!("\n{} client{}", clients, if clients == 1 { "" } else { "s" }));
+    }
+    let clients = clients.unwrap_or(0);
+    let text = if clients > 0 { format!("{} {}", icon, clients) } else { icon.clone() };
+    ModuleStatus::new(text)
+        .with_icon(&icon)
+        .with_alt("on")
+        .with_class("active")
+        .with_tooltip(tooltip)
+        .with_field("ssid", hotspot.name)
+        .with_field("clients", clients.to_string())
+}
+
+/// Start, stop or toggle the hotspot with the module's SSID and password
+fn hotspot_action(mode: &str, config: &ModuleConfig) -> Result<()> {
+    let active = hotspot::active();
+    let start = match mode {
+        "start" => true,
+        "stop" => false,
+        "toggle" => active.is_none(),
+        _ => bail!("Unknown hotspot mode {:?}, expected start, stop or toggle", mode),
+    };
+
+    match (start, active) {
+        (true, None) => {
+            let secrets = secrets::load();
+            let ssid = config.hotspot_ssid.as_deref().map(|s| secrets::expand(s, &secrets));
+            let password = config.hotspot_password.as_deref().map(|s| secrets::expand(s, &secrets));
+            hotspot::start(ssid.as_deref(), password.as_deref())
+        }
+        (false, Some(hotspot)) => hotspot::stop(&hotspot),
+        // Already in the requested state
+        _ => Ok(()),
+    }
+}
+
+/// What the current MPRIS player is playing
+fn get_media_status() -> ModuleStatus {
+    let Some(player) = media::current() else {
+        return ModuleStatus::new(""); // no player — hide module
+    };
+
+    let state = player.status.to_lowercase();
+    let icon = icons::get(match state.as_str() {
+        "playing" => "media-playing",
+        "paused" => "media-paused",
+        _ => "media-stopped",
+    });
+    let track = match (player.artist.is_empty(), player.title.is_empty()) {
+        (false, false) => format!("{} - {}", player.artist, player.title),
+        (true, false) => player.title.clone(),
+        (false, true) => player.artist.clone(),
+        (true, true) => player.name.clone(),
+    };
+
+    let mut tooltip = vec![format!("<b>{}</b>", markup_escape(&player.title))];
+    if !player.artist.is_empty() {
+        tooltip.push(markup_escape(&player.artist));
+    }
+    if !player.album.is_empty() {
+        tooltip.push(format!("<i>{}</i>", markup_escape(&player.album)));
+    }
+    tooltip.push(format!("{} ({})", player.status, markup_escape(&player.name)));
+
+    ModuleStatus::new(format!("{} {}", icon, track))
+        .with_icon(&icon)
+        .with_alt(state.clone())
+        .with_class(state)
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("title", player.title)
+        .with_field("artist", player.artist)
+        .with_field("album", player.album)
+        .with_field("player", player.name)
+        .with_field("status", player.status)
+}
+
+fn get_dnd_status() -> ModuleStatus {
+    if dnd::is_active() {
+        let icon = icons::get("dnd-on");
+        ModuleStatus::new(icon.clone())
+            .with_icon(&icon)
+            .with_class("dnd")
+            .with_tooltip("Do not disturb")
+    } else {
+        let icon = icons::get("dnd-off");
+        ModuleStatus::new(icon.clone()).with_icon(&icon)
+    }
+}
+
+fn get_notifications_status(config: &Config) -> ModuleStatus {
+    let setting = config.get_module("notifications").and_then(|m| m.notification_daemon.as_deref());
+    let Some(server) = notifications::Server::find(setting) else {
+        return ModuleStatus::new(""); // no notification daemon — hide module
+    };
+    let state = match server.state() {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::debug!("Notification state unavailable: {:#}", e);
+            return ModuleStatus::new("");
+        }
+    };
+
+    let (icon, alt) = if state.dnd {
+        (icons::get("dnd-on"), "dnd")
+    } else if state.count > 0 {
+        (icons::get("notifications"), "unread")
+    } else {
+        (icons::get("dnd-off"), "none")
+    };
+    let text = if state.count > 0 { format!("{} {}", icon, state.count) } else { icon.clone() };
+    let mut tooltip = vec![match state.count {
+        0 => "No notifications".to_string(),
+        1 => "<b>1 notification</b>".to_string(),
+        n => format!("<b>{} notifications</b>", n),
+    }];
+    if state.dnd {
+        tooltip.push("Do not disturb".to_string());
+    }
+    tooltip.push(format!("via {}", server.name()));
+
+    let mut status = ModuleStatus::new(text)
+        .with_icon(&icon)
+        .with_alt(alt)
+        .with_tooltip(tooltip.join("\n"))
+        .with_field("count", state.count.to_string())
+        .with_field("daemon", server.name());
+    if state.dnd {
+        status = status.with_class("dnd");
+    }
+    if state.count > 0 {
+        status = status.with_class("unread");
     }
+    status
 }
 
-fn get_surfshark_status() -> ModuleStatus {
-    ModuleStatus::new("\u{f21b}") // user-secret (spy)
+fn get_idle_status(config: &Config) -> ModuleStatus {
+    if inhibit::is_active() {
+        let icon = icons::get("idle-inhibited");
+        let what = config.get_module("idle").map_or("idle", |m| m.inhibit.as_str());
+        ModuleStatus::new(icon.clone())
+            .with_icon(&icon)
+            .with_alt("on")
+            .with_class("active")
+            .with_tooltip(format!("Keeping the screen awake ({})", markup_escape(what)))
+    } else {
+        let icon = icons::get("idle-allowed");
+        ModuleStatus::new(icon.clone())
+            .with_icon(&icon)
+            .with_alt("off")
+            .with_tooltip("Screen may idle")
+    }
 }
 
-/// Execute a quick action for a module
-pub fn execute_action(action: &str) -> Result<()> {
-    let expanded = shellexpand::tilde(action);
-    Command::new("sh").args(["-c", &expanded]).spawn()?;
+fn get_nightlight_status(config: &Config) -> ModuleStatus {
+    let module_config = config.get_module("nightlight");
+    let Some(command) = nightlight::command(module_config.and_then(|m| m.nightlight_command.as_deref())) else {
+        return ModuleStatus::new(""); // no gamma tool installed — hide module
+    };
+    let program = nightlight::program(&command);
+    let managed = module_config.is_some_and(|m| m.nightlight_managed);
+
+    if nightlight::is_active(&program, managed) {
+        let icon = icons::get("nightlight-on");
+        ModuleStatus::new(icon.clone())
+            .with_icon(&icon)
+            .with_alt("on")
+            .with_class("active")
+            .with_tooltip(format!("Night light on\n{}", markup_escape(&command)))
+            .with_field("tool", program)
+    } else {
+        let icon = icons::get("nightlight-off");
+        ModuleStatus::new(icon.clone())
+            .with_icon(&icon)
+            .with_alt("off")
+            .with_tooltip(format!("Night light off ({})", markup_escape(&program)))
+            .with_field("tool", program)
+    }
+}
+
+fn get_power_status() -> ModuleStatus {
+    let icon = icons::get("power");
+    ModuleStatus::new(icon.clone())
+        .with_icon(&icon)
+        .with_tooltip("Lock / Suspend / Reboot / Shutdown")
+}
+
+/// Execute a quick action for a module.
+///
+/// Actions are commands (run under `sh -c` only if the module opts into `shell`), or one of the built-in verbs:
+/// - `clipboard:<template>` copies the rendered template (e.g. `{ip}`) via wl-copy
+/// - `webhook:<name>` performs the HTTP request configured in `[webhooks.<name>]`
+/// - `bluetooth:on`, `:off` or `:toggle` powers the Bluetooth adapter through BlueZ
+/// - `brightness:up`, `brightness:down` or `brightness:<percent>` steps (by `scroll_step`) or sets the backlight
+/// - `media:<command>` controls the current MPRIS player (`play-pause`, `next`, `previous`, ...)
+/// - `idle-inhibit:on`, `:off` or `:toggle` takes or releases a logind idle inhibitor held by the daemon
+/// - `nightlight:on`, `:off` or `:toggle` starts or stops the `nightlight` module's gamma tool
+/// - `tailscale:up`, `:down` or `:toggle` connects or disconnects, `tailscale:exit-node` toggles the module's `exit_node`
+/// - `notification-dnd:on`, `:off` or `:toggle` sets the notification daemon's do-not-disturb
+/// - `updates:check` reruns the `updates` module's package checks now
+///
+/// Commands are awaited; a non-zero exit is returned as an error carrying their stderr.
+pub async fn execute_action(config: &Arc<Config>, module: &str, action: &str) -> Result<()> {
+    if let Some(template) = action.strip_prefix("clipboard:") {
+        let (config, module, template) = (Arc::clone(config), module.to_string(), template.to_string());
+        return tokio::task::spawn_blocking(move || {
+            copy_to_clipboard(&get_status(&config, &module, false).render(&template))
+        })
+        .await?;
+    }
+
+    if let Some(mode) = action.strip_prefix("charge-limit:") {
+        let limit = find_battery()
+            .as_deref()
+            .and_then(ChargeLimit::find)
+            .context("No battery charge limit (conservation mode or charge threshold) found")?;
+        let active = match mode {
+            "on" => true,
+            "off" => false,
+            "toggle" => !limit.is_active(),
+            _ => bail!("Unknown charge-limit mode {:?}, expected on, off or toggle", mode),
+        };
+        let threshold = config.get_module(module).map_or(80, |m| m.charge_limit);
+        return limit.set(active, threshold);
+    }
+
+    if let Some(mode) = action.strip_prefix("hotspot:") {
+        let mode = mode.to_string();
+        let module_config = config.get_module(module).cloned().unwrap_or_default();
+        // Bringing up an access point takes a while, longer than the usual command timeout
+        return tokio::task::spawn_blocking(move || {
+            command::with_timeout(Some(Duration::from_secs(30)), || hotspot_action(&mode, &module_config)).0
+        })
+        .await?;
+    }
+
+    if let Some(mode) = action.strip_prefix("bluetooth:") {
+        let mode = mode.to_string();
+        return tokio::task::spawn_blocking(move || bluez::set_powered(&mode)).await?;
+    }
+
+    if let Some(mode) = action.strip_prefix("airplane:") {
+        let blocked = match mode {
+            "on" => true,
+            "off" => false,
+            // Anything still transmitting means airplane mode isn't on yet
+            "toggle" => rfkill::radios().iter().any(|r| !r.is_blocked()),
+            _ => bail!("Unknown airplane mode {:?}, expected on, off or toggle", mode),
+        };
+        return rfkill::block_all(blocked);
+    }
+
+    if let Some(level) = action.strip_prefix("brightness:") {
+        // Step with the brightness module's scroll settings, wherever the action is bound
+        let module_config = config
+            .get_module("brightness")
+            .or_else(|| config.get_module(module))
+            .cloned()
+            .unwrap_or_default();
+        return match level {
+            "up" | "down" => scroll("brightness", &module_config, level == "up").map(|_| ()),
+            _ => {
+                let percent: u32 = level.trim_end_matches('%').parse().with_context(|| {
+                    format!("Unknown brightness level {:?}, expected up, down or a percentage", level)
+                })?;
+                Backlight::find().context("No backlight")?.set_percent(percent)
+            }
+        };
+    }
+
+    if let Some(mode) = action.strip_prefix("idle-inhibit:") {
+        let what = config.get_module("idle").map_or_else(|| "idle".to_string(), |m| m.inhibit.clone());
+        let mode = mode.to_string();
+        return tokio::task::spawn_blocking(move || inhibit::set(&mode, &what)).await?;
+    }
+
+    if let Some(mode) = action.strip_prefix("nightlight:") {
+        let module_config = config.get_module("nightlight").cloned().unwrap_or_default();
+        let command = nightlight::command(module_config.nightlight_command.as_deref())
+            .context("No night light tool (hyprsunset, wlsunset or gammastep) found")?;
+        let shell = config.use_shell("nightlight");
+        let mode = mode.to_string();
+        return tokio::task::spawn_blocking(move || {
+            nightlight::set(&mode, &command, module_config.nightlight_managed, shell)
+        })
+        .await?;
+    }
+
+    if let Some(mode) = action.strip_prefix("tailscale:") {
+        let mode = mode.to_string();
+        let exit_node = config.get_module("tailscale").and_then(|m| m.exit_node.clone());
+        // Connecting can take a while, longer than the usual command timeout
+        return tokio::task::spawn_blocking(move || {
+            command::with_timeout(Some(Duration::from_secs(15)), || match mode.as_str() {
+                "exit-node" => tailscale::toggle_exit_node(exit_node.as_deref()),
+                _ => tailscale::set_connected(&mode),
+            })
+            .0
+        })
+        .await?;
+    }
+
+    if let Some(mode) = action.strip_prefix("notification-dnd:") {
+        let setting = config.get_module("notifications").and_then(|m| m.notification_daemon.as_deref());
+        let server = notifications::Server::find(setting)
+            .context("No notification daemon (mako, dunst or swaync) found")?;
+        let mode = mode.to_string();
+        return tokio::task::spawn_blocking(move || server.set_dnd(&mode)).await?;
+    }
+
+    if action == "updates:check" {
+        let configured = config.get_module("updates").map(|m| m.checkers.clone()).unwrap_or_default();
+        let checkers = updates::checkers(&configured);
+        return tokio::task::spawn_blocking(move || {
+            command::with_timeout(Some(updates::CHECK_TIMEOUT), || updates::check(&checkers)).0
+        })
+        .await?;
+    }
+
+    if let Some(command) = action.strip_prefix("media:") {
+        let command = command.to_string();
+        return tokio::task::spawn_blocking(move || media::control(&command)).await?;
+    }
+
+    if let Some(name) = action.strip_prefix("webhook:") {
+        let hook = config
+            .webhooks
+            .get(name)
+            .with_context(|| format!("Unknown webhook: {}", name))?
+            .clone();
+        let (config, module) = (Arc::clone(config), module.to_string());
+        let status = tokio::task::spawn_blocking(move || get_status(&config, &module, false)).await?;
+        return webhook::send(&hook, &status).await;
+    }
+
+    let output = tokio::process::Command::from(command::build(action, &[], config.use_shell(module))?)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {:?}", action))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("{:?} failed ({}): {}", action, output.status, stderr.trim());
+    }
+    Ok(())
+}
+
+/// Tell the user an action failed, with the error (usually the command's stderr)
+pub fn notify_failure(module: &str, error: &anyhow::Error) {
+    let summary = format!("{} action failed", module);
+    let body = format!("{:#}", error);
+    let result = Command::new("notify-send")
+        .args(["-a", "waybar-hovermenu", "-u", "critical", &summary, &body])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match result {
+        // Reap in the background so the child doesn't linger as a zombie
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => tracing::warn!("Failed to send failure notification: {}", e),
+    }
+}
+
+/// Step a module's level up or down on scroll, clamped to the configured range.
+/// Returns the new level in percent.
+pub fn scroll(module: &str, config: &ModuleConfig, up: bool) -> Result<u32> {
+    let current = match module {
+        "audio" => default_sink_volume().map(|(volume, _)| volume),
+        "brightness" => brightness_percent(),
+        _ => bail!("Module {} does not support scrolling", module),
+    }
+    .context("Failed to read current level")?;
+
+    let target = if up {
+        current.saturating_add(config.scroll_step)
+    } else {
+        current.saturating_sub(config.scroll_step)
+    };
+    let target = target.clamp(config.scroll_min, config.scroll_max.max(config.scroll_min));
+
+    if module == "brightness" {
+        Backlight::find().context("No backlight")?.set_percent(target)?;
+        return Ok(target);
+    }
+
+    pulse::set_volume(target).with_context(|| format!("Failed to set {} to {}%", module, target))?;
+    Ok(target)
+}
+
+/// Copy text to the Wayland clipboard
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut child = Command::new("wl-copy")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to spawn wl-copy")?;
+    child
+        .stdin
+        .take()
+        .context("wl-copy has no stdin")?
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("wl-copy failed ({})", status);
+    }
     Ok(())
 }
@@ -0,0 +1,342 @@
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+
+const NM: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const NM_ACTIVE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const NM_DEVICE: &str = "org.freedesktop.NetworkManager.Device";
+const NM_WIRELESS: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const NM_ACCESS_POINT: &str = "org.freedesktop.NetworkManager.AccessPoint";
+const NM_IP4: &str = "org.freedesktop.NetworkManager.IP4Config";
+
+/// `NM_DEVICE_TYPE_ETHERNET`, `NM_DEVICE_TYPE_WIFI` and `NM_DEVICE_STATE_ACTIVATED`
+const NM_ETHERNET: u32 = 1;
+const NM_WIFI: u32 = 2;
+const NM_ACTIVATED: u32 = 100;
+
+const IWD: &str = "net.connman.iwd";
+const IWD_PATH: &str = "/net/connman/iwd";
+const IWD_DEVICE: &str = "net.connman.iwd.Device";
+const IWD_STATION: &str = "net.connman.iwd.Station";
+const IWD_NETWORK: &str = "net.connman.iwd.Network";
+const IWD_DIAGNOSTIC: &str = "net.connman.iwd.StationDiagnostic";
+
+/// iwd doesn't signal RSSI changes, and plain ethernet without a manager signals
+/// nothing, so the link is also re-read this often
+const RECHECK: Duration = Duration::from_secs(30);
+
+type Properties = HashMap<String, OwnedValue>;
+
+/// The connection the network module shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub interface: String,
+    /// `None` for a wired link
+    pub wifi: Option<Wifi>,
+    /// IPv4 address and default gateway; empty when the backend doesn't know them
+    pub ip: String,
+    pub gateway: String,
+    /// NetworkManager's object for the access point, to pick its signal changes out of scans
+    access_point: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wifi {
+    pub ssid: String,
+    /// Signal quality in percent
+    pub signal: Option<u32>,
+    pub dbm: Option<i32>,
+    /// Channel frequency in MHz
+    pub frequency: Option<u32>,
+}
+
+/// The running watcher's link. It belongs to the `Watch`, so once that is dropped
+/// (watchers aborted on reload) status reads query the bus directly again.
+static CURRENT: Mutex<Weak<Mutex<Option<Link>>>> = Mutex::new(Weak::new());
+
+/// The active link: the watcher's, else a one-off query. NetworkManager is asked
+/// first, then iwd, and without either a wired interface that is up counts.
+pub fn current() -> Option<Link> {
+    if let Some(link) = CURRENT.lock().unwrap().upgrade() {
+        return link.lock().unwrap().clone();
+    }
+    match Connection::system() {
+        Ok(connection) => query(&connection),
+        Err(_) => ethernet(),
+    }
+}
+
+fn query(connection: &Connection) -> Option<Link> {
+    match networkmanager(connection) {
+        Ok(link) => return link,
+        Err(e) => tracing::trace!("NetworkManager unavailable: {:#}", e),
+    }
+    match iwd(connection) {
+        Ok(Some(link)) => return Some(link),
+        Ok(None) => {}
+        Err(e) => tracing::trace!("iwd unavailable: {:#}", e),
+    }
+    ethernet()
+}
+
+fn proxy<'a>(connection: &Connection, service: &'a str, path: &'a str, interface: &'a str) -> Result<Proxy<'a>> {
+    Ok(zbus::blocking::proxy::Builder::<Proxy>::new(connection)
+        .destination(service)?
+        .path(path)?
+        .interface(interface)?
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()?)
+}
+
+/// The first wired or wireless device of the active connections, the primary one first
+fn networkmanager(connection: &Connection) -> Result<Option<Link>> {
+    let manager = proxy(connection, NM, NM_PATH, NM)?;
+    let primary: OwnedObjectPath = manager.get_property("PrimaryConnection")?;
+    let mut active: Vec<OwnedObjectPath> = manager.get_property("ActiveConnections")?;
+    active.sort_by_key(|path| *path != primary);
+    for path in &active {
+        let devices: Vec<OwnedObjectPath> = proxy(connection, NM, path, NM_ACTIVE)?.get_property("Devices")?;
+        for device in &devices {
+            if let Some(link) = nm_device(connection, device)? {
+                return Ok(Some(link));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn nm_device(connection: &Connection, path: &str) -> Result<Option<Link>> {
+    let device = proxy(connection, NM, path, NM_DEVICE)?;
+    let kind: u32 = device.get_property("DeviceType")?;
+    let state: u32 = device.get_property("State")?;
+    if !matches!(kind, NM_ETHERNET | NM_WIFI) || state != NM_ACTIVATED {
+        return Ok(None);
+    }
+    let ip4: OwnedObjectPath = device.get_property("Ip4Config")?;
+    let (ip, gateway) = nm_ip4(connection, &ip4)?;
+    let mut link = Link {
+        interface: device.get_property("Interface")?,
+        wifi: None,
+        ip,
+        gateway,
+        access_point: None,
+    };
+    if kind == NM_WIFI {
+        let access_point: OwnedObjectPath = proxy(connection, NM, path, NM_WIRELESS)?.get_property("ActiveAccessPoint")?;
+        if access_point.as_str() == "/" {
+            return Ok(None);
+        }
+        let ap = proxy(connection, NM, &access_point, NM_ACCESS_POINT)?;
+        let ssid: Vec<u8> = ap.get_property("Ssid")?;
+        let strength: u8 = ap.get_property("Strength")?;
+        let frequency: u32 = ap.get_property("Frequency")?;
+        link.wifi = Some(Wifi {
+            ssid: String::from_utf8_lossy(&ssid).into_owned(),
+            signal: Some(strength.into()),
+            dbm: None,
+            frequency: Some(frequency),
+        });
+        link.access_point = Some(access_point.to_string());
+    }
+    Ok(Some(link))
+}
+
+/// First address and the gateway of an IP4Config object; "/" means none yet
+fn nm_ip4(connection: &Connection, path: &str) -> Result<(String, String)> {
+    if path == "/" {
+        return Ok(Default::default());
+    }
+    let config = proxy(connection, NM, path, NM_IP4)?;
+    let addresses: Vec<Properties> = config.get_property("AddressData")?;
+    let ip = addresses
+        .first()
+        .and_then(|a| a.get("address"))
+        .and_then(|v| v.downcast_ref::<&str>().ok())
+        .unwrap_or("")
+        .to_string();
+    Ok((ip, config.get_property("Gateway")?))
+}
+
+/// The first connected station; iwd knows nothing about addresses
+fn iwd(connection: &Connection) -> Result<Option<Link>> {
+    let objects = zbus::blocking::fdo::ObjectManagerProxy::builder(connection)
+        .destination(IWD)?
+        .path("/")?
+        .build()?
+        .get_managed_objects()?;
+    let text = |path: &ObjectPath, interface: &str, key: &str| -> Option<String> {
+        let value = objects.get(path)?.get(interface)?.get(key)?;
+        value.downcast_ref::<&str>().ok().map(str::to_string)
+    };
+    for (path, interfaces) in &objects {
+        let Some(station) = interfaces.get(IWD_STATION) else {
+            continue;
+        };
+        if text(path, IWD_STATION, "State").as_deref() != Some("connected") {
+            continue;
+        }
+        let network = station
+            .get("ConnectedNetwork")
+            .and_then(|v| v.downcast_ref::<ObjectPath>().ok())
+            .context("Connected station without a network")?;
+        let (dbm, frequency) = iwd_signal(connection, path, &network);
+        return Ok(Some(Link {
+            interface: text(path, IWD_DEVICE, "Name").unwrap_or_default(),
+            wifi: Some(Wifi {
+                ssid: text(&network, IWD_NETWORK, "Name").unwrap_or_default(),
+                signal: dbm.map(dbm_to_percent),
+                dbm,
+                frequency,
+            }),
+            ip: String::new(),
+            gateway: String::new(),
+            access_point: None,
+        }));
+    }
+    Ok(None)
+}
+
+/// RSSI and frequency from the station's diagnostics, which need iwd 1.0 or later
+/// (and may be disabled); else just the RSSI from the scan results
+fn iwd_signal(connection: &Connection, station: &str, network: &ObjectPath) -> (Option<i32>, Option<u32>) {
+    let diagnostics = proxy(connection, IWD, station, IWD_DIAGNOSTIC)
+        .and_then(|p| Ok(p.call::<_, _, Properties>("GetDiagnostics", &())?));
+    if let Ok(diagnostics) = diagnostics {
+        let dbm = diagnostics.get("RSSI").and_then(|v| v.downcast_ref::<i16>().ok());
+        let frequency = diagnostics.get("Frequency").and_then(|v| v.downcast_ref::<u32>().ok());
+        if dbm.is_some() {
+            return (dbm.map(i32::from), frequency);
+        }
+    }
+    // Signal strength in 100 * dBm
+    let networks = proxy(connection, IWD, station, IWD_STATION)
+        .and_then(|p| Ok(p.call::<_, _, Vec<(OwnedObjectPath, i16)>>("GetOrderedNetworks", &())?));
+    let dbm = networks
+        .ok()
+        .and_then(|n| n.into_iter().find(|(path, _)| path.as_str() == network.as_str()))
+        .map(|(_, strength)| i32::from(strength) / 100);
+    (dbm, None)
+}
+
+/// Rough signal quality in percent: -100 dBm or worse is 0, -50 dBm or better is 100
+fn dbm_to_percent(dbm: i32) -> u32 {
+    (2 * (dbm + 100)).clamp(0, 100) as u32
+}
+
+/// The first wired interface that is up, from sysfs: physical (it has a `device`)
+/// and not wireless, so bridges, tunnels and Wi-Fi cards don't count
+fn ethernet() -> Option<Link> {
+    let net = Path::new("/sys/class/net");
+    let mut names: Vec<String> = std::fs::read_dir(net)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    let interface = names.into_iter().find(|name| {
+        let dir = net.join(name);
+        dir.join("device").exists()
+            && !dir.join("wireless").exists()
+            && std::fs::read_to_string(dir.join("operstate")).is_ok_and(|s| s.trim() == "up")
+    })?;
+    Some(Link { interface, wifi: None, ip: String::new(), gateway: String::new(), access_point: None })
+}
+
+/// NetworkManager's and iwd's signals, keeping the link current for as long as this is alive
+pub struct Watch {
+    connection: Connection,
+    messages: futures::stream::SelectAll<zbus::MessageStream>,
+    recheck: tokio::time::Interval,
+    link: Arc<Mutex<Option<Link>>>,
+}
+
+impl Watch {
+    pub async fn start() -> Result<Watch> {
+        let connection = zbus::Connection::system().await?;
+        let properties = |namespace: &'static str| {
+            Ok::<_, zbus::Error>(zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::Signal)
+                .interface("org.freedesktop.DBus.Properties")?
+                .member("PropertiesChanged")?
+                .path_namespace(namespace)?
+                .build())
+        };
+        let owner = |service: &'static str| {
+            Ok::<_, zbus::Error>(zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::Signal)
+                .interface("org.freedesktop.DBus")?
+                .member("NameOwnerChanged")?
+                .arg(0, service)?
+                .build())
+        };
+        let nm = zbus::MessageStream::for_match_rule(properties(NM_PATH)?, &connection, Some(64)).await?;
+        let iwd = zbus::MessageStream::for_match_rule(properties(IWD_PATH)?, &connection, Some(64)).await?;
+        let nm_owner = zbus::MessageStream::for_match_rule(owner(NM)?, &connection, Some(4)).await?;
+        let iwd_owner = zbus::MessageStream::for_match_rule(owner(IWD)?, &connection, Some(4)).await?;
+        let messages = futures::stream::select_all([nm, iwd, nm_owner, iwd_owner]);
+
+        let connection = Connection::from(connection);
+        let link = Arc::new(Mutex::new(reread(&connection).await));
+        *CURRENT.lock().unwrap() = Arc::downgrade(&link);
+
+        let mut recheck = tokio::time::interval(RECHECK);
+        recheck.tick().await;
+        Ok(Watch { connection, messages, recheck, link })
+    }
+
+    /// Wait until the link changes. False once the system bus connection drops.
+    pub async fn changed(&mut self) -> bool {
+        loop {
+            tokio::select! {
+                message = self.messages.next() => match message {
+                    Some(Ok(message)) if self.relevant(&message) => {}
+                    Some(Ok(_)) => continue,
+                    _ => return false,
+                },
+                _ = self.recheck.tick() => {}
+            }
+            let link = reread(&self.connection).await;
+            let mut current = self.link.lock().unwrap();
+            if *current != link {
+                *current = link;
+                return true;
+            }
+        }
+    }
+
+    /// Whether a signal can change the link, leaving out statistics, other devices'
+    /// chatter and the signal of every access point in each scan
+    fn relevant(&self, message: &zbus::Message) -> bool {
+        let header = message.header();
+        if header.member().is_some_and(|m| m.as_str() == "NameOwnerChanged") {
+            return true;
+        }
+        let Ok((interface, _changed, _invalidated)) = message.body().deserialize::<(String, Properties, Vec<String>)>() else {
+            return false;
+        };
+        match interface.as_str() {
+            NM_ACCESS_POINT => {
+                let path = header.path().map(|p| p.as_str()).unwrap_or("");
+                self.link
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .and_then(|link| link.access_point.clone())
+                    .is_some_and(|ap| ap == path)
+            }
+            NM | NM_ACTIVE | NM_DEVICE | NM_WIRELESS | NM_IP4 | IWD_STATION | IWD_DEVICE => true,
+            _ => false,
+        }
+    }
+}
+
+async fn reread(connection: &Connection) -> Option<Link> {
+    let connection = connection.clone();
+    tokio::task::spawn_blocking(move || query(&connection)).await.ok().flatten()
+}
@@ -0,0 +1,129 @@
+use anyhow::{bail, Context, Result};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Stdio};
+use std::sync::Mutex;
+
+use crate::command;
+
+/// Gamma tools to try when `nightlight_command` is unset, with arguments that make
+/// the screen warm whenever they run
+const DEFAULT_COMMANDS: [&str; 3] = ["hyprsunset -t 4000", "wlsunset -S 07:00 -s 19:00", "gammastep"];
+
+/// The gamma tool the daemon started itself, when it manages the process
+static CHILD: Mutex<Option<Child>> = Mutex::new(None);
+
+/// The configured night light command, or the first default whose tool is installed
+pub fn command(setting: Option<&str>) -> Option<String> {
+    match setting {
+        Some(command) => Some(command.to_string()),
+        None => DEFAULT_COMMANDS
+            .iter()
+            .find(|c| c.split_whitespace().next().is_some_and(command::installed))
+            .map(|c| c.to_string()),
+    }
+}
+
+/// The tool a command runs, e.g. "wlsunset" for "~/bin/wlsunset -l 52.5 -L 13.4"
+pub fn program(command: &str) -> String {
+    let first = command.split_whitespace().next().unwrap_or("");
+    first.rsplit('/').next().unwrap_or(first).to_string()
+}
+
+/// Whether the night light is on: our own child is alive when `managed`, else any
+/// process of the tool is running
+pub fn is_active(program: &str, managed: bool) -> bool {
+    if managed {
+        let mut child = CHILD.lock().unwrap();
+        if child.as_mut().is_some_and(|c| !matches!(c.try_wait(), Ok(None))) {
+            *child = None; // exited or crashed
+        }
+        return child.is_some();
+    }
+    !pids(program).is_empty()
+}
+
+/// Start or stop the tool: "on", "off" or "toggle"
+pub fn set(mode: &str, template: &str, managed: bool, shell: bool) -> Result<()> {
+    let program = program(template);
+    let active = is_active(&program, managed);
+    let on = match mode {
+        "on" => true,
+        "off" => false,
+        "toggle" => !active,
+        _ => bail!("Unknown nightlight mode {:?}, expected on, off or toggle", mode),
+    };
+    match (on, active) {
+        (true, false) => start(template, managed, shell),
+        (false, true) => {
+            stop(&program, managed);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn start(template: &str, managed: bool, shell: bool) -> Result<()> {
+    // A managed tool is started directly: its handle, the parent-death signal and the
+    // SIGTERM that stops it have to reach the tool, not a shell around it
+    let mut cmd = if managed {
+        command::direct(template, &[])?
+    } else {
+        command::build(template, &[], shell)?
+    };
+    cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    if managed {
+        // Take the gamma ramp down with the daemon rather than leave it warm forever
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+                Ok(())
+            });
+        }
+        let child = cmd.spawn().with_context(|| format!("Failed to start {}", template))?;
+        *CHILD.lock().unwrap() = Some(child);
+    } else {
+        // Its own process group, so it outlives the daemon like one started by hand
+        let mut child = cmd
+            .process_group(0)
+            .spawn()
+            .with_context(|| format!("Failed to start {}", template))?;
+        std::thread::spawn(move || child.wait());
+    }
+    tracing::info!("Night light on ({})", template);
+    Ok(())
+}
+
+fn stop(program: &str, managed: bool) {
+    if managed {
+        if let Some(mut child) = CHILD.lock().unwrap().take() {
+            unsafe {
+                libc::kill(child.id() as i32, libc::SIGTERM);
+            }
+            std::thread::spawn(move || child.wait());
+        }
+    } else {
+        for pid in pids(program) {
+            unsafe {
+                libc::kill(pid, libc::SIGTERM);
+            }
+        }
+    }
+    tracing::info!("Night light off ({})", program);
+}
+
+/// Pids of running processes named `program` (matched on /proc/<pid>/comm, which
+/// the kernel cuts to 15 bytes)
+fn pids(program: &str) -> Vec<i32> {
+    let name = &program.as_bytes()[..program.len().min(15)];
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|pid| {
+            std::fs::read(format!("/proc/{}/comm", pid))
+                .is_ok_and(|comm| comm.strip_suffix(b"\n").unwrap_or(&comm) == name)
+        })
+        .collect()
+}
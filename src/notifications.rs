@@ -0,0 +1,229 @@
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::process::Command;
+
+use crate::command;
+
+/// A notification daemon the `notifications` module can drive through its control tool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Server {
+    Mako,
+    Dunst,
+    Swaync,
+}
+
+/// What the notification daemon reports
+pub struct State {
+    /// Notifications on screen or waiting to be seen
+    pub count: u32,
+    pub dnd: bool,
+}
+
+impl Server {
+    /// The daemon named by `notification_daemon`, else whichever owns the session
+    /// bus's notification service
+    pub fn find(setting: Option<&str>) -> Option<Server> {
+        match setting {
+            Some(name) => Self::from_name(name).or_else(|| {
+                tracing::warn!("Unknown notification_daemon {:?}, expected mako, dunst or swaync", name);
+                None
+            }),
+            None => Self::from_name(&server_name().ok()?),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Server> {
+        let name = name.to_lowercase();
+        if name.contains("mako") {
+            Some(Server::Mako)
+        } else if name.contains("dunst") {
+            Some(Server::Dunst)
+        } else if name.contains("swaync") || name.contains("swaynotificationcenter") {
+            Some(Server::Swaync)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Server::Mako => "mako",
+            Server::Dunst => "dunst",
+            Server::Swaync => "swaync",
+        }
+    }
+
+    pub fn state(self) -> Result<State> {
+        Ok(match self {
+            Server::Mako => {
+                let modes = ctl("makoctl", &["mode"])?;
+                State {
+                    count: mako_count(&ctl("makoctl", &["list"])?),
+                    dnd: modes.lines().any(|m| m.trim() == MAKO_DND_MODE),
+                }
+            }
+            Server::Dunst => {
+                let count = |kind: &str| -> Result<u32> { Ok(ctl("dunstctl", &["count", kind])?.parse().unwrap_or(0)) };
+                State {
+                    // Notifications held back while paused count as unread
+                    count: count("displayed")? + count("waiting")?,
+                    dnd: ctl("dunstctl", &["is-paused"])? == "true",
+                }
+            }
+            Server::Swaync => State {
+                count: ctl("swaync-client", &["--count", "--skip-wait"])?.parse().unwrap_or(0),
+                dnd: ctl("swaync-client", &["--get-dnd", "--skip-wait"])? == "true",
+            },
+        })
+    }
+
+    /// Turn do-not-disturb "on", "off" or "toggle"
+    pub fn set_dnd(self, mode: &str) -> Result<()> {
+        if !matches!(mode, "on" | "off" | "toggle") {
+            bail!("Unknown notification-dnd mode {:?}, expected on, off or toggle", mode);
+        }
+        match self {
+            Server::Mako => {
+                let flag = match mode {
+                    "on" => "-a",
+                    "off" => "-r",
+                    _ => "-t",
+                };
+                ctl("makoctl", &["mode", flag, MAKO_DND_MODE])?;
+            }
+            Server::Dunst => {
+                let paused = match mode {
+                    "on" => "true",
+                    "off" => "false",
+                    _ => "toggle",
+                };
+                ctl("dunstctl", &["set-paused", paused])?;
+            }
+            Server::Swaync => {
+                let flag = match mode {
+                    "on" => "--dnd-on",
+                    "off" => "--dnd-off",
+                    _ => "--toggle-dnd",
+                };
+                ctl("swaync-client", &[flag, "--skip-wait"])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the daemon has a notification center of its own; only swaync does
+    pub fn has_center(self) -> bool {
+        self == Server::Swaync
+    }
+
+    /// Show the notification center (see `has_center`)
+    pub fn open_center(self) -> Result<()> {
+        match self {
+            Server::Swaync => ctl("swaync-client", &["--open-panel", "--skip-wait"]).map(|_| ()),
+            other => bail!("{} has no notification center", other.name()),
+        }
+    }
+
+    pub fn close_center(self) -> Result<()> {
+        match self {
+            Server::Swaync => ctl("swaync-client", &["--close-panel", "--skip-wait"]).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Past notifications as text, newest first
+    fn history(self) -> Result<String> {
+        let output = match self {
+            Server::Mako => ctl("makoctl", &["history"])?,
+            Server::Dunst => ctl("dunstctl", &["history"])?,
+            Server::Swaync => bail!("swaync keeps its history in the notification center"),
+        };
+        // dunst, and mako before 1.9, print D-Bus style JSON; newer mako prints text
+        if !output.starts_with('{') {
+            return Ok(output);
+        }
+        let json: serde_json::Value = serde_json::from_str(&output)?;
+        let entries = json.pointer("/data/0").and_then(|d| d.as_array()).cloned().unwrap_or_default();
+        let field = |entry: &serde_json::Value, key: &str| {
+            entry.pointer(&format!("/{}/data", key)).and_then(|v| v.as_str()).unwrap_or("").to_string()
+        };
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                let app = [field(entry, "appname"), field(entry, "app-name")].concat();
+                let mut text = format!("{}: {}", app, field(entry, "summary"));
+                let body = field(entry, "body");
+                if !body.is_empty() {
+                    text.push_str(&format!("\n    {}", body.replace('\n', "\n    ")));
+                }
+                text
+            })
+            .collect();
+        Ok(lines.join("\n\n"))
+    }
+
+    /// Long-running command that prints a line on every change, for daemons that have one
+    pub fn subscribe_command(self) -> Option<&'static str> {
+        match self {
+            Server::Swaync => Some("swaync-client --subscribe"),
+            _ => None,
+        }
+    }
+}
+
+/// Print a daemon's notification history and wait for Enter. Run in a terminal as the
+/// menu of mako and dunst, which have no notification center (`--notification-history`).
+pub fn show_history(daemon: &str) -> Result<()> {
+    let history = Server::from_name(daemon)
+        .with_context(|| format!("Unknown notification daemon {:?}", daemon))
+        .and_then(|server| server.history());
+    // The terminal closes with us, so errors wait for Enter too
+    match history {
+        Ok(history) if history.is_empty() => println!("No notifications"),
+        Ok(history) => println!("{}", history),
+        Err(e) => println!("Couldn't read the notification history: {:#}", e),
+    }
+    print!("\nPress Enter to close");
+    std::io::stdout().flush()?;
+    std::io::stdin().read_line(&mut String::new())?;
+    Ok(())
+}
+
+/// The mako mode that hides notifications; it needs `[mode=do-not-disturb] invisible=1`
+/// in mako's config
+const MAKO_DND_MODE: &str = "do-not-disturb";
+
+/// Name the running notification server gives over D-Bus, e.g. "mako" or "dunst"
+fn server_name() -> Result<String> {
+    let connection = zbus::blocking::Connection::session()?;
+    let proxy = zbus::blocking::Proxy::new(
+        &connection,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+    )?;
+    let (name, _vendor, _version, _spec): (String, String, String, String) =
+        proxy.call("GetServerInformation", &())?;
+    Ok(name)
+}
+
+/// Count `makoctl list` output: JSON from mako before 1.9, text blocks after
+fn mako_count(list: &str) -> u32 {
+    if list.starts_with('{') {
+        let json: serde_json::Value = serde_json::from_str(list).unwrap_or_default();
+        return json
+            .pointer("/data/0")
+            .and_then(|d| d.as_array())
+            .map_or(0, |d| d.len() as u32);
+    }
+    list.lines().filter(|l| l.starts_with("Notification ")).count() as u32
+}
+
+/// Run a control tool and return its trimmed output
+fn ctl(program: &str, args: &[&str]) -> Result<String> {
+    let output = command::output(Command::new(program).args(args))?;
+    if !output.status.success() {
+        bail!("{} {} failed: {}", program, args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,85 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use crate::command;
+use crate::config::OsdConfig;
+use crate::dnd;
+use crate::modules::ModuleStatus;
+use crate::overlay;
+
+/// Where the overlay can't be drawn (no wlr-layer-shell), the flash becomes a
+/// notification; mako, dunst and swaync render the value hint as a bar
+const NOTIFY_COMMAND: &str = "notify-send -a waybar-hovermenu -t {timeout} \
+     -h string:x-canonical-private-synchronous:osd-{kind} -h int:value:{value} '{icon} {value}%'";
+
+/// Flash the OSD for a value change (e.g. `kind = "volume"`, `value = 40`): a
+/// layer-shell overlay with the kind's icon and a bar, or the configured command
+pub fn show(config: &OsdConfig, kind: &str, value: u32, icon: &str) {
+    if !config.enabled || dnd::is_active() {
+        return;
+    }
+
+    if config.command.is_none() {
+        let flash = overlay::Flash {
+            kind: kind.to_string(),
+            value,
+            position: config.position.clone(),
+            timeout: Duration::from_millis(config.timeout_ms),
+        };
+        match overlay::show(flash) {
+            Ok(()) => return,
+            Err(e) => tracing::debug!("OSD overlay unavailable, sending a notification: {:#}", e),
+        }
+    }
+
+    let value = value.to_string();
+    let timeout = config.timeout_ms.to_string();
+    let vars = [
+        ("kind", kind),
+        ("value", value.as_str()),
+        ("icon", icon),
+        ("timeout", timeout.as_str()),
+        ("position", config.position.as_str()),
+    ];
+
+    let template = config.command.as_deref().unwrap_or(NOTIFY_COMMAND);
+    if let Err(e) = spawn(template, &vars, config.shell) {
+        tracing::warn!("Failed to show OSD: {}", e);
+    }
+}
+
+/// Run a module's external OSD hook (SwayOSD, avizo, ...) with the fresh status.
+/// Besides the status fields, `{value}` is the module's level (0-100) and `{fraction}` the same as 0.0-1.0.
+pub fn run_hook(template: &str, status: &ModuleStatus, shell: bool) {
+    let value: u32 = ["volume", "brightness"]
+        .iter()
+        .find_map(|key| status.fields.get(*key))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let value = if status.fields.get("muted").is_some_and(|m| m == "yes") {
+        0
+    } else {
+        value
+    };
+
+    let fraction = format!("{:.2}", value as f32 / 100.0);
+    let value = value.to_string();
+    let mut vars = status.vars();
+    vars.push(("value", &value));
+    vars.push(("fraction", &fraction));
+
+    if let Err(e) = spawn(template, &vars, shell) {
+        tracing::warn!("Failed to run OSD hook: {}", e);
+    }
+}
+
+/// Spawn a fire-and-forget command
+fn spawn(template: &str, vars: &[(&str, &str)], shell: bool) -> anyhow::Result<()> {
+    let mut cmd = tokio::process::Command::from(command::build(template, vars, shell)?);
+    // Use tokio::process so the child is auto-reaped (avoids zombies)
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
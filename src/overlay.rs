@@ -0,0 +1,436 @@
+use anyhow::{bail, Context, Result};
+use smithay_client_toolkit::compositor::{CompositorHandler, CompositorState, Region};
+use smithay_client_toolkit::output::{OutputHandler, OutputState};
+use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
+use smithay_client_toolkit::shell::wlr_layer::{
+    Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface, LayerSurfaceConfigure,
+};
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shm::slot::SlotPool;
+use smithay_client_toolkit::shm::{Shm, ShmHandler};
+use smithay_client_toolkit::{
+    delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm, registry_handlers,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+use wayland_client::globals::registry_queue_init;
+use wayland_client::protocol::{wl_output, wl_shm, wl_surface};
+use wayland_client::{Connection, EventQueue, QueueHandle};
+
+const WIDTH: u32 = 280;
+const HEIGHT: u32 = 56;
+const STRIDE: u32 = WIDTH * 4;
+/// Distance from the anchored screen edges
+const MARGIN: i32 = 64;
+
+/// A value change to show: `kind` picks the icon ("volume", "brightness")
+pub struct Flash {
+    pub kind: String,
+    pub value: u32,
+    pub position: String,
+    pub timeout: Duration,
+}
+
+/// The overlay thread, while it runs
+static SENDER: Mutex<Option<mpsc::Sender<Flash>>> = Mutex::new(None);
+/// The compositor can't show the overlay, so don't keep trying
+static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// Show a flash on a layer-shell surface, replacing the one on screen. Fails when
+/// there is no compositor with wlr-layer-shell, so the caller can fall back.
+pub fn show(flash: Flash) -> Result<()> {
+    if UNAVAILABLE.load(Ordering::Relaxed) {
+        bail!("The OSD overlay is unavailable");
+    }
+    let mut sender = SENDER.lock().unwrap();
+    let flash = match sender.as_ref() {
+        Some(tx) => match tx.send(flash) {
+            Ok(()) => return Ok(()),
+            // The thread ended (the compositor went away); start another
+            Err(mpsc::SendError(flash)) => flash,
+        },
+        None => flash,
+    };
+
+    let overlay = match Overlay::connect() {
+        Ok(overlay) => overlay,
+        Err(e) => {
+            UNAVAILABLE.store(true, Ordering::Relaxed);
+            return Err(e);
+        }
+    };
+    let (tx, rx) = mpsc::channel();
+    tx.send(flash)?;
+    std::thread::spawn(move || run(overlay, rx));
+    *sender = Some(tx);
+    Ok(())
+}
+
+/// Show flashes as they come and hide the surface once the last one times out
+fn run(mut overlay: Overlay, flashes: mpsc::Receiver<Flash>) {
+    let mut hide_at: Option<Instant> = None;
+    loop {
+        let flash = match hide_at {
+            Some(at) => match flashes.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                Ok(flash) => Some(flash),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            },
+            None => match flashes.recv() {
+                Ok(flash) => Some(flash),
+                Err(_) => return,
+            },
+        };
+        let result = match flash {
+            Some(flash) => {
+                hide_at = Some(Instant::now() + flash.timeout);
+                overlay.show(&flash)
+            }
+            None => {
+                hide_at = None;
+                overlay.hide()
+            }
+        };
+        if let Err(e) = result {
+            tracing::debug!("OSD overlay stopped: {:#}", e);
+            *SENDER.lock().unwrap() = None;
+            return;
+        }
+    }
+}
+
+/// The layer surface on screen
+struct Shown {
+    layer: LayerSurface,
+    position: String,
+    configured: bool,
+}
+
+struct Overlay {
+    queue: EventQueue<Osd>,
+    osd: Osd,
+}
+
+/// Protocol state, and what the event handlers update
+struct Osd {
+    registry: RegistryState,
+    outputs: OutputState,
+    compositor: CompositorState,
+    layer_shell: LayerShell,
+    shm: Shm,
+    /// Shared memory the frames are drawn into
+    pool: SlotPool,
+    shown: Option<Shown>,
+}
+
+impl Overlay {
+    /// Connect and bind the compositor, shm and layer-shell globals
+    fn connect() -> Result<Overlay> {
+        let connection = Connection::connect_to_env()?;
+        let (globals, queue) = registry_queue_init::<Osd>(&connection)?;
+        let qh = queue.handle();
+
+        let layer_shell = LayerShell::bind(&globals, &qh)
+            .context("The compositor doesn't support wlr-layer-shell")?;
+        let compositor = CompositorState::bind(&globals, &qh).context("The compositor has no wl_compositor")?;
+        let shm = Shm::bind(&globals, &qh).context("The compositor has no wl_shm")?;
+        // Room for two frames, so one can be drawn while the compositor still shows the other
+        let pool = SlotPool::new((2 * STRIDE * HEIGHT) as usize, &shm)?;
+
+        let osd = Osd {
+            registry: RegistryState::new(&globals),
+            outputs: OutputState::new(&globals, &qh),
+            compositor,
+            layer_shell,
+            shm,
+            pool,
+            shown: None,
+        };
+        Ok(Overlay { queue, osd })
+    }
+
+    fn show(&mut self, flash: &Flash) -> Result<()> {
+        self.queue.roundtrip(&mut self.osd)?;
+        if self.osd.shown.as_ref().is_some_and(|shown| shown.position != flash.position) {
+            self.hide()?;
+        }
+        if self.osd.shown.is_none() {
+            self.map(&flash.position)?;
+        }
+
+        // Wait for the first configure before attaching anything
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while !self.osd.shown.as_ref().is_some_and(|shown| shown.configured) {
+            if self.osd.shown.is_none() {
+                return Ok(()); // closed by the compositor
+            }
+            if Instant::now() > deadline {
+                bail!("The compositor didn't configure the OSD surface");
+            }
+            self.queue.blocking_dispatch(&mut self.osd)?;
+        }
+
+        let (buffer, canvas) = self.osd.pool.create_buffer(
+            WIDTH as i32,
+            HEIGHT as i32,
+            STRIDE as i32,
+            wl_shm::Format::Argb8888,
+        )?;
+        canvas.copy_from_slice(&draw(&flash.kind, flash.value));
+        if let Some(shown) = &self.osd.shown {
+            let surface = shown.layer.wl_surface();
+            surface.damage_buffer(0, 0, WIDTH as i32, HEIGHT as i32);
+            buffer.attach_to(surface)?;
+            shown.layer.commit();
+        }
+        self.queue.flush()?;
+        Ok(())
+    }
+
+    /// Create the surface and give it the layer-shell role at `position`
+    fn map(&mut self, position: &str) -> Result<()> {
+        let qh = self.queue.handle();
+        let surface = self.osd.compositor.create_surface(&qh);
+        let layer = self.osd.layer_shell.create_layer_surface(
+            &qh,
+            surface,
+            Layer::Overlay,
+            Some("hovermenu-osd"),
+            None, // the compositor picks the output
+        );
+
+        // An empty input region, so clicks go through to whatever is underneath
+        let region = Region::new(&self.osd.compositor)?;
+        layer.wl_surface().set_input_region(Some(region.wl_region()));
+        layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+
+        let (anchor, [top, right, bottom, left]) = placement(position);
+        layer.set_size(WIDTH, HEIGHT);
+        layer.set_anchor(anchor);
+        layer.set_margin(top, right, bottom, left);
+        // The initial commit, without a buffer, asks for a configure
+        layer.commit();
+        self.queue.flush()?;
+
+        self.osd.shown = Some(Shown {
+            layer,
+            position: position.to_string(),
+            configured: false,
+        });
+        Ok(())
+    }
+
+    fn hide(&mut self) -> Result<()> {
+        // Dropping the layer surface destroys it along with its wl_surface
+        if self.osd.shown.take().is_some() {
+            self.queue.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl LayerShellHandler for Osd {
+    fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, layer: &LayerSurface) {
+        // The compositor took the surface away (e.g. its output went)
+        if self.shown.as_ref().is_some_and(|shown| shown.layer == *layer) {
+            self.shown = None;
+        }
+    }
+
+    fn configure(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        layer: &LayerSurface,
+        _: LayerSurfaceConfigure,
+        _: u32,
+    ) {
+        if let Some(shown) = self.shown.as_mut().filter(|shown| shown.layer == *layer) {
+            shown.configured = true;
+        }
+    }
+}
+
+impl CompositorHandler for Osd {
+    fn scale_factor_changed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: i32) {}
+
+    fn transform_changed(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) {}
+
+    fn surface_enter(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: &wl_output::WlOutput) {}
+
+    fn surface_leave(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: &wl_output::WlOutput) {}
+}
+
+impl OutputHandler for Osd {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.outputs
+    }
+
+    fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+}
+
+impl ShmHandler for Osd {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.shm
+    }
+}
+
+impl ProvidesRegistryState for Osd {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry
+    }
+
+    registry_handlers![OutputState];
+}
+
+delegate_compositor!(Osd);
+delegate_output!(Osd);
+delegate_shm!(Osd);
+delegate_layer!(Osd);
+delegate_registry!(Osd);
+
+/// Anchor edges and (top, right, bottom, left) margins for a position such as
+/// "bottom-center", "top-right" or "center"
+fn placement(position: &str) -> (Anchor, [i32; 4]) {
+    let mut anchor = Anchor::empty();
+    let mut margins = [0; 4];
+    for part in position.split('-') {
+        let (edge, side) = match part {
+            "top" => (Anchor::TOP, 0),
+            "right" => (Anchor::RIGHT, 1),
+            "bottom" => (Anchor::BOTTOM, 2),
+            "left" => (Anchor::LEFT, 3),
+            _ => continue,
+        };
+        anchor |= edge;
+        margins[side] = MARGIN;
+    }
+    (anchor, margins)
+}
+
+/// Premultiplied ARGB, as wl_shm's argb8888 wants it
+#[derive(Clone, Copy)]
+struct Color {
+    a: f32,
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+const BACKGROUND: Color = Color { a: 0.85, r: 0.1, g: 0.1, b: 0.1 };
+const TRACK: Color = Color { a: 0.35, r: 0.5, g: 0.5, b: 0.5 };
+const FOREGROUND: Color = Color { a: 1.0, r: 0.95, g: 0.95, b: 0.95 };
+
+/// The OSD frame: a rounded panel with the kind's icon and a bar filled to `value` percent
+fn draw(kind: &str, value: u32) -> Vec<u8> {
+    let mut canvas = vec![Color { a: 0.0, r: 0.0, g: 0.0, b: 0.0 }; (WIDTH * HEIGHT) as usize];
+    let (w, h) = (WIDTH as f32, HEIGHT as f32);
+    paint(&mut canvas, BACKGROUND, |x, y| inside_rounded(x, y, 0.0, 0.0, w, h, 14.0));
+
+    // The icon in a 28px square on the left
+    let (ix, iy) = (16.0, (h - 28.0) / 2.0);
+    let level = value.min(100) as f32 / 100.0;
+    match kind {
+        "volume" => paint(&mut canvas, FOREGROUND, |x, y| speaker(x - ix, y - iy, value)),
+        "brightness" => paint(&mut canvas, FOREGROUND, |x, y| sun(x - ix, y - iy)),
+        _ => {}
+    }
+
+    let (bx, by, bw, bh) = (60.0, (h - 8.0) / 2.0, w - 80.0, 8.0);
+    paint(&mut canvas, TRACK, |x, y| inside_rounded(x, y, bx, by, bw, bh, 4.0));
+    paint(&mut canvas, FOREGROUND, |x, y| {
+        x < bx + bw * level && inside_rounded(x, y, bx, by, bw, bh, 4.0)
+    });
+
+    canvas
+        .iter()
+        .flat_map(|c| {
+            let byte = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+            // Little-endian argb8888
+            [byte(c.b), byte(c.g), byte(c.r), byte(c.a)]
+        })
+        .collect()
+}
+
+/// Blend `color` over the canvas where `inside` holds, 4x4 supersampled for smooth edges
+fn paint(canvas: &mut [Color], color: Color, inside: impl Fn(f32, f32) -> bool) {
+    for py in 0..HEIGHT {
+        for px in 0..WIDTH {
+            let mut hits = 0;
+            for sy in 0..4 {
+                for sx in 0..4 {
+                    let (x, y) = (px as f32 + (sx as f32 + 0.5) / 4.0, py as f32 + (sy as f32 + 0.5) / 4.0);
+                    hits += inside(x, y) as u32;
+                }
+            }
+            if hits == 0 {
+                continue;
+            }
+            let coverage = hits as f32 / 16.0;
+            let dst = &mut canvas[(py * WIDTH + px) as usize];
+            let keep = 1.0 - color.a * coverage;
+            dst.a = color.a * coverage + dst.a * keep;
+            dst.r = color.r * color.a * coverage + dst.r * keep;
+            dst.g = color.g * color.a * coverage + dst.g * keep;
+            dst.b = color.b * color.a * coverage + dst.b * keep;
+        }
+    }
+}
+
+fn inside_rounded(x: f32, y: f32, left: f32, top: f32, width: f32, height: f32, radius: f32) -> bool {
+    if x < left || y < top || x > left + width || y > top + height {
+        return false;
+    }
+    let cx = x.clamp(left + radius, left + width - radius);
+    let cy = y.clamp(top + radius, top + height - radius);
+    (x - cx).powi(2) + (y - cy).powi(2) <= radius * radius
+}
+
+/// A speaker in a 28px square: waves for the level, a cross when silent
+fn speaker(x: f32, y: f32, value: u32) -> bool {
+    let dy = (y - 14.0).abs();
+    let body = (2.0..=8.0).contains(&x) && dy <= 4.0;
+    let cone = (8.0..=15.0).contains(&x) && dy <= 4.0 + (x - 8.0) * 6.0 / 7.0;
+    if body || cone {
+        return true;
+    }
+    if value == 0 {
+        let (cx, cy) = (x - 22.5, y - 14.0);
+        return cx.abs() <= 4.5 && cy.abs() <= 4.5 && ((cx - cy).abs() <= 1.3 || (cx + cy).abs() <= 1.3);
+    }
+    let (cx, cy) = (x - 15.0, y - 14.0);
+    let distance = (cx * cx + cy * cy).sqrt();
+    let facing = cx > 0.0 && cy.abs() <= cx;
+    let near = (4.5..=6.5).contains(&distance);
+    let far = value > 50 && (9.0..=11.0).contains(&distance);
+    facing && (near || far)
+}
+
+/// A sun in a 28px square: a disc with eight rays
+fn sun(x: f32, y: f32) -> bool {
+    let (cx, cy) = (x - 14.0, y - 14.0);
+    let distance = (cx * cx + cy * cy).sqrt();
+    if distance <= 6.0 {
+        return true;
+    }
+    if !(8.5..=13.0).contains(&distance) {
+        return false;
+    }
+    let step = std::f32::consts::FRAC_PI_4;
+    let angle = cy.atan2(cx).rem_euclid(step);
+    angle.min(step - angle) * distance <= 1.2
+}
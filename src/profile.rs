@@ -0,0 +1,267 @@
+use anyhow::Result;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command as TokioCommand;
+use tokio::sync::broadcast;
+use zbus::zvariant::OwnedValue;
+
+use crate::config::Config;
+use crate::menu::MenuManager;
+use crate::watchers::refresh_all;
+
+/// The profile in effect, if any
+static ACTIVE: Mutex<Option<String>> = Mutex::new(None);
+
+const LOGIND: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_MANAGER: &str = "org.freedesktop.login1.Manager";
+
+/// How often to re-check the lid without logind, whose signals report it otherwise
+const RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether the active profile turns a module off
+pub fn is_disabled(config: &Config, module: &str) -> bool {
+    let active = ACTIVE.lock().unwrap();
+    active
+        .as_ref()
+        .and_then(|name| config.profiles.get(name))
+        .is_some_and(|p| p.disable.iter().any(|m| m == module))
+}
+
+/// `daemon.waybar_height`, unless the active profile overrides it
+pub fn waybar_height(config: &Config) -> u32 {
+    let active = ACTIVE.lock().unwrap();
+    active
+        .as_ref()
+        .and_then(|name| config.profiles.get(name))
+        .and_then(|p| p.waybar_height)
+        .unwrap_or(config.daemon.waybar_height)
+}
+
+/// Machine state profiles are chosen by
+struct Machine {
+    lid_closed: bool,
+    ac: bool,
+    external_monitor: bool,
+}
+
+impl Machine {
+    fn probe(logind: Option<&zbus::blocking::Connection>) -> Self {
+        let external_monitor = external_monitor_connected();
+        Self {
+            lid_closed: lid_closed(logind),
+            ac: on_ac(),
+            external_monitor,
+        }
+    }
+
+    /// Whether a `when` condition holds; a leading `!` negates it
+    fn satisfies(&self, condition: &str) -> bool {
+        let (negate, name) = match condition.strip_prefix('!') {
+            Some(name) => (true, name),
+            None => (false, condition),
+        };
+        let value = match name {
+            "lid-closed" => self.lid_closed,
+            "ac" => self.ac,
+            "external-monitor" => self.external_monitor,
+            // Lid shut with a screen attached, or simply plugged into one on AC
+            "docked" => self.external_monitor && (self.lid_closed || self.ac),
+            _ => {
+                tracing::warn!("Unknown profile condition: {}", condition);
+                return false;
+            }
+        };
+        value != negate
+    }
+}
+
+/// The profile whose conditions all hold, preferring the most specific one
+fn select(config: &Config, machine: &Machine) -> Option<String> {
+    config
+        .profiles
+        .iter()
+        .filter(|(_, p)| p.when.iter().all(|c| machine.satisfies(c)))
+        // Most conditions wins; among equals, the first name alphabetically
+        .max_by(|(a_name, a), (b_name, b)| a.when.len().cmp(&b.when.len()).then(b_name.cmp(a_name)))
+        .map(|(name, _)| name.clone())
+}
+
+/// Logind's view of the lid, falling back to ACPI
+fn lid_closed(logind: Option<&zbus::blocking::Connection>) -> bool {
+    let logind = logind.and_then(|connection| {
+        zbus::blocking::proxy::Builder::<zbus::blocking::Proxy>::new(connection)
+            .destination(LOGIND)
+            .ok()?
+            .path(LOGIND_PATH)
+            .ok()?
+            .interface(LOGIND_MANAGER)
+            .ok()?
+            .cache_properties(zbus::proxy::CacheProperties::No)
+            .build()
+            .ok()?
+            .get_property::<bool>("LidClosed")
+            .ok()
+    });
+    logind.unwrap_or_else(|| {
+        // "state:      closed"
+        std::fs::read_dir("/proc/acpi/button/lid")
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .any(|e| {
+                std::fs::read_to_string(e.path().join("state")).is_ok_and(|s| s.contains("closed"))
+            })
+    })
+}
+
+/// Whether any mains adapter is online
+fn on_ac() -> bool {
+    std::fs::read_dir("/sys/class/power_supply")
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .any(|e| read(&e.path(), "type") == "Mains" && read(&e.path(), "online") == "1")
+}
+
+/// Whether a display other than the built-in panel is connected
+fn external_monitor_connected() -> bool {
+    std::fs::read_dir("/sys/class/drm")
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .any(|e| {
+            // Connectors are named like "card1-HDMI-A-1"; the panel is eDP, LVDS or DSI
+            let name = e.file_name().to_string_lossy().into_owned();
+            let Some((_, connector)) = name.split_once('-') else {
+                return false;
+            };
+            let internal = ["eDP", "LVDS", "DSI"].iter().any(|p| connector.starts_with(p));
+            !internal && read(&e.path(), "status") == "connected"
+        })
+}
+
+fn read(path: &Path, file: &str) -> String {
+    std::fs::read_to_string(path.join(file))
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Re-evaluate profiles now, returning whether the active one changed
+fn update(config: &Config, logind: Option<&zbus::blocking::Connection>) -> bool {
+    let selected = select(config, &Machine::probe(logind));
+    let mut active = ACTIVE.lock().unwrap();
+    if *active == selected {
+        return false;
+    }
+    tracing::info!("Switching profile: {:?} -> {:?}", *active, selected);
+    *active = selected;
+    true
+}
+
+/// logind's property changes on the system bus, which include the lid's
+async fn lid_changes() -> Result<(zbus::Connection, zbus::MessageStream)> {
+    let connection = zbus::Connection::system().await?;
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(LOGIND)?
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path(LOGIND_PATH)?
+        .arg(0, LOGIND_MANAGER)?
+        .build();
+    let stream = zbus::MessageStream::for_match_rule(rule, &connection, Some(8)).await?;
+    Ok((connection, stream))
+}
+
+/// Whether a logind PropertiesChanged signal is about the lid
+fn mentions_lid(message: &zbus::Message) -> bool {
+    message
+        .body()
+        .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+        .is_ok_and(|(_, changed, invalidated)| {
+            changed.contains_key("LidClosed") || invalidated.iter().any(|p| p == "LidClosed")
+        })
+}
+
+/// Switch profiles as the lid, power and displays change, refreshing every widget on a switch
+pub async fn run(
+    config: Arc<Config>,
+    menu_manager: Arc<MenuManager>,
+    tx: broadcast::Sender<(String, String)>,
+) {
+    // One system bus connection for the lid, both its signals and reading it
+    let (logind, mut lid) = match lid_changes().await {
+        Ok((connection, stream)) => (Some(zbus::blocking::Connection::from(connection)), Some(stream)),
+        Err(e) => {
+            tracing::debug!("logind unavailable, re-checking the lid every {:?}: {:#}", RECHECK_INTERVAL, e);
+            (None, None)
+        }
+    };
+    let reevaluate = || async {
+        let cfg = Arc::clone(&config);
+        let logind = logind.clone();
+        if tokio::task::spawn_blocking(move || update(&cfg, logind.as_ref())).await.unwrap_or(false) {
+            refresh_all(&config, &menu_manager, &tx).await;
+        }
+    };
+    reevaluate().await;
+
+    loop {
+        let mut child = TokioCommand::new("udevadm")
+            .args(["monitor", "--udev", "-s", "power_supply", "-s", "drm"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .ok();
+        let mut lines = child
+            .as_mut()
+            .and_then(|c| c.stdout.take())
+            .map(|stdout| BufReader::new(stdout).lines());
+
+        loop {
+            let event = async {
+                match lines.as_mut() {
+                    Some(lines) => lines.next_line().await.ok().flatten().is_some(),
+                    None => std::future::pending().await,
+                }
+            };
+            // The lid doesn't go through udev: logind reports it, else it's re-checked now and then
+            let lid_event = async {
+                let Some(stream) = lid.as_mut() else {
+                    tokio::time::sleep(RECHECK_INTERVAL).await;
+                    return true;
+                };
+                loop {
+                    match stream.next().await {
+                        Some(Ok(message)) if mentions_lid(&message) => return true,
+                        Some(_) => {}
+                        None => return false,
+                    }
+                }
+            };
+            let (alive, lid_alive) = tokio::select! {
+                alive = event => (alive, true),
+                lid_alive = lid_event => (true, lid_alive),
+            };
+            if !lid_alive {
+                tracing::debug!("Lost logind's signals, re-checking the lid every {:?}", RECHECK_INTERVAL);
+                lid = None;
+            }
+
+            reevaluate().await;
+            if !alive {
+                break;
+            }
+        }
+
+        // Reconnect after a short delay if udevadm exits
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,119 @@
+use notify::{RecursiveMode, Watcher};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinSet;
+
+use crate::announce;
+use crate::command;
+use crate::config::Config;
+use crate::dnd;
+use crate::icons;
+use crate::menu::MenuManager;
+use crate::watchers;
+
+/// Editors write a file in several steps; wait for them to settle before reading it
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Reload the config on SIGHUP and whenever config.toml changes, for the life of the daemon.
+/// Takes over the running watchers so they can be restarted with the new config.
+pub async fn run(
+    menu_manager: Arc<MenuManager>,
+    status_tx: broadcast::Sender<(String, String)>,
+    mut running: JoinSet<()>,
+) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => Some(hangup),
+        Err(e) => {
+            tracing::warn!("Can't listen for SIGHUP: {}", e);
+            None
+        }
+    };
+    let (changed_tx, mut changed) = mpsc::channel(1);
+    // Kept alive for as long as we reload on file changes
+    let _watcher = match watch_file(changed_tx) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            tracing::warn!("Not watching the config file, reload with SIGHUP: {}", e);
+            None
+        }
+    };
+
+    loop {
+        let hup = async {
+            match hangup.as_mut() {
+                Some(hangup) => hangup.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = hup => {}
+            Some(()) = changed.recv() => {
+                tokio::time::sleep(SETTLE).await;
+                while changed.try_recv().is_ok() {}
+            }
+        }
+        if let Some(watchers) = reload(&menu_manager, &status_tx) {
+            running.abort_all();
+            running = watchers;
+        }
+    }
+}
+
+/// Signal `changed` whenever config.toml is written, created, replaced or removed.
+/// Watches the directory, since editors often save by renaming a new file over the old one.
+fn watch_file(changed: mpsc::Sender<()>) -> notify::Result<notify::RecommendedWatcher> {
+    let path = Config::config_path();
+    let name = path.file_name().map(|n| n.to_os_string());
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if event.kind.is_access() {
+            return;
+        }
+        if event.paths.iter().any(|p| p.file_name() == name.as_deref()) {
+            // A full channel already has a reload queued
+            let _ = changed.try_send(());
+        }
+    })?;
+    let dir = path.parent().unwrap_or(&path);
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+/// Load the config and switch everything over to it, returning the new watchers.
+/// A config that doesn't load is reported and the daemon keeps running as is.
+fn reload(
+    menu_manager: &Arc<MenuManager>,
+    status_tx: &broadcast::Sender<(String, String)>,
+) -> Option<JoinSet<()>> {
+    let config = match Config::load() {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            tracing::error!("Not reloading, config has errors: {:#}", e);
+            return None;
+        }
+    };
+    tracing::info!("Reloaded config with {} modules", config.modules.len());
+
+    command::init(config.daemon.command_timeout_ms);
+    dnd::init(config.daemon.dnd_schedule.as_deref());
+    announce::init(config.daemon.announce_cmd.as_deref());
+    icons::init(&config.icons);
+    menu_manager.set_config(Arc::clone(&config));
+
+    let watchers = watchers::start_watchers(
+        Arc::clone(&config),
+        Arc::clone(menu_manager),
+        status_tx.clone(),
+    );
+    // Push every module's status under the new config (new formats, new modules)
+    let menu_manager = Arc::clone(menu_manager);
+    let status_tx = status_tx.clone();
+    tokio::spawn(async move {
+        watchers::refresh_all(&config, &menu_manager, &status_tx).await;
+    });
+    Some(watchers)
+}
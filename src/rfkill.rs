@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+
+const RFKILL_CLASS: &str = "/sys/class/rfkill";
+/// Event device: reads report radio changes, writes change block state
+pub const RFKILL_DEVICE: &str = "/dev/rfkill";
+
+/// `RFKILL_TYPE_ALL` and `RFKILL_OP_CHANGE_ALL` from linux/rfkill.h
+const TYPE_ALL: u8 = 0;
+const OP_CHANGE_ALL: u8 = 3;
+
+/// A radio the kernel can block
+pub struct Radio {
+    pub name: String,
+    /// "wlan", "bluetooth", "wwan", ...
+    pub kind: String,
+    /// Blocked in software (rfkill, airplane mode)
+    pub soft: bool,
+    /// Blocked by a hardware switch
+    pub hard: bool,
+}
+
+impl Radio {
+    pub fn is_blocked(&self) -> bool {
+        self.soft || self.hard
+    }
+}
+
+/// Every radio, from /sys/class/rfkill
+pub fn radios() -> Vec<Radio> {
+    let Ok(entries) = std::fs::read_dir(RFKILL_CLASS) else {
+        return Vec::new();
+    };
+    let mut radios: Vec<Radio> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| {
+            let path = e.path();
+            Radio {
+                name: read(&path, "name"),
+                kind: read(&path, "type"),
+                soft: read(&path, "soft") == "1",
+                hard: read(&path, "hard") == "1",
+            }
+        })
+        .collect();
+    radios.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.name.cmp(&b.name)));
+    radios
+}
+
+fn read(path: &Path, file: &str) -> String {
+    std::fs::read_to_string(path.join(file))
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Soft-block (or unblock) every radio at once
+pub fn block_all(blocked: bool) -> Result<()> {
+    // struct rfkill_event { __u32 idx; __u8 type; __u8 op; __u8 soft; __u8 hard; }
+    let mut event = [0u8; 8];
+    event[4] = TYPE_ALL;
+    event[5] = OP_CHANGE_ALL;
+    event[6] = u8::from(blocked);
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(RFKILL_DEVICE)
+        .and_then(|mut dev| dev.write_all(&event))
+        .with_context(|| format!("Failed to write {}", RFKILL_DEVICE))
+}
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+/// Secrets (API tokens, passwords) live in their own file so config.toml can be shared.
+/// Values are referenced from config strings as `{secret:name}`.
+pub fn secrets_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("~/.config"))
+        .join("waybar-hovermenu")
+        .join("secrets.toml")
+}
+
+/// Load all secrets. A missing or unreadable file yields no secrets.
+pub fn load() -> HashMap<String, String> {
+    let path = secrets_path();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(_) => return HashMap::new(),
+    };
+
+    if let Ok(meta) = std::fs::metadata(&path) {
+        if meta.permissions().mode() & 0o077 != 0 {
+            tracing::warn!("{:?} is readable by other users, consider chmod 600", path);
+        }
+    }
+
+    toml::from_str(&content).unwrap_or_else(|e| {
+        tracing::error!("Failed to parse {:?}: {}", path, e);
+        HashMap::new()
+    })
+}
+
+/// Replace `{secret:name}` placeholders with their values
+pub fn expand(s: &str, secrets: &HashMap<String, String>) -> String {
+    let mut out = s.to_string();
+    for (name, value) in secrets {
+        out = out.replace(&format!("{{secret:{}}}", name), value);
+    }
+    out
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use crate::modules::ModuleStatus;
+
+/// Whether synthetic events are accepted (`--debug`)
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Statuses that stand in for what a module's tools would report
+static STATUSES: OnceLock<Mutex<HashMap<String, ModuleStatus>>> = OnceLock::new();
+/// Cursor position reported instead of the real one
+static CURSOR: Mutex<Option<(i32, i32)>> = Mutex::new(None);
+
+fn statuses() -> &'static Mutex<HashMap<String, ModuleStatus>> {
+    STATUSES.get_or_init(Default::default)
+}
+
+pub fn init(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if enabled {
+        tracing::info!("Debug mode: accepting simulate commands");
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// The simulated status of a module, if one is set
+pub fn status(module: &str) -> Option<ModuleStatus> {
+    statuses().lock().unwrap().get(module).cloned()
+}
+
+/// Report `status` for a module until cleared (`None`)
+pub fn set_status(module: &str, status: Option<ModuleStatus>) {
+    let mut statuses = statuses().lock().unwrap();
+    match status {
+        Some(status) => statuses.insert(module.to_string(), status),
+        None => statuses.remove(module),
+    };
+}
+
+/// The simulated cursor position, if one is set
+pub fn cursor() -> Option<(i32, i32)> {
+    *CURSOR.lock().unwrap()
+}
+
+/// Report the cursor at `position` until cleared (`None`)
+pub fn set_cursor(position: Option<(i32, i32)>) {
+    *CURSOR.lock().unwrap() = position;
+}
+
+/// Drop every simulated status and the cursor, returning the modules that had one
+pub fn clear() -> Vec<String> {
+    set_cursor(None);
+    statuses().lock().unwrap().drain().map(|(module, _)| module).collect()
+}
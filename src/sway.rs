@@ -0,0 +1,258 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+
+use crate::compositor::{Compositor, Monitor, Window};
+use crate::geometry::Geometry;
+
+/// Requests that take longer than this are abandoned
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// i3-ipc message header: magic, then payload length and type (native endian)
+const MAGIC: &[u8] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const SUBSCRIBE: u32 = 2;
+const GET_OUTPUTS: u32 = 3;
+const GET_TREE: u32 = 4;
+
+/// Path of the IPC socket Sway (or i3) advertises to its clients
+fn socket_path() -> Result<PathBuf> {
+    std::env::var("SWAYSOCK")
+        .or_else(|_| std::env::var("I3SOCK"))
+        .map(PathBuf::from)
+        .context("Neither SWAYSOCK nor I3SOCK is set")
+}
+
+async fn send(stream: &mut UnixStream, kind: u32, payload: &str) -> Result<()> {
+    let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message).await?;
+    Ok(())
+}
+
+/// Read one message, returning its type and JSON payload
+async fn receive(stream: &mut UnixStream) -> Result<(u32, serde_json::Value)> {
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header).await?;
+    if &header[..6] != MAGIC {
+        bail!("Not an i3-ipc message");
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into()?) as usize;
+    let kind = u32::from_ne_bytes(header[10..14].try_into()?);
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+    Ok((kind, serde_json::from_slice(&payload)?))
+}
+
+/// Send one request and return its reply
+async fn request(kind: u32, payload: &str) -> Result<serde_json::Value> {
+    let exchange = async {
+        let mut stream = UnixStream::connect(socket_path()?).await?;
+        send(&mut stream, kind, payload).await?;
+        let (_, reply) = receive(&mut stream).await?;
+        anyhow::Ok(reply)
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .context("Sway didn't answer")?
+}
+
+/// Run commands, e.g. "[con_id=12] floating enable, move up 60 px"
+async fn run(commands: &str) -> Result<()> {
+    let replies = request(RUN_COMMAND, commands).await?;
+    // One {"success": ..., "error": ...} per command
+    for reply in replies.as_array().into_iter().flatten() {
+        if reply.get("success").and_then(|s| s.as_bool()) != Some(true) {
+            let error = reply.get("error").and_then(|e| e.as_str()).unwrap_or("command failed");
+            bail!("Sway: {}", error);
+        }
+    }
+    Ok(())
+}
+
+fn rect(node: &serde_json::Value) -> Option<Geometry> {
+    let rect = node.get("rect")?;
+    let int = |key: &str| rect.get(key).and_then(|v| v.as_i64());
+    Some(Geometry {
+        x: int("x")? as i32,
+        y: int("y")? as i32,
+        width: int("width")? as u32,
+        height: int("height")? as u32,
+    })
+}
+
+/// Every view (a container holding a client) in the tree, tiled or floating
+fn views(node: &serde_json::Value) -> Vec<&serde_json::Value> {
+    let children = ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node.get(key).and_then(|n| n.as_array()))
+        .flatten();
+    let mut views: Vec<_> = children.flat_map(views).collect();
+    if node.get("pid").is_some_and(|p| p.is_i64()) {
+        views.push(node);
+    }
+    views
+}
+
+fn window(view: &serde_json::Value) -> Window {
+    let str_at = |pointer: &str| view.pointer(pointer).and_then(|v| v.as_str());
+    Window {
+        id: view.get("id").and_then(|i| i.as_i64()).unwrap_or(0).to_string(),
+        pid: view.get("pid").and_then(|p| p.as_i64()).unwrap_or(0) as i32,
+        title: str_at("/name").unwrap_or("").to_string(),
+        // Native Wayland clients have an app_id, Xwayland ones an X11 class
+        class: str_at("/app_id")
+            .or_else(|| str_at("/window_properties/class"))
+            .unwrap_or("")
+            .to_string(),
+        geometry: rect(view),
+        focused: view.get("focused").and_then(|f| f.as_bool()) == Some(true),
+    }
+}
+
+async fn focused_view() -> Result<Option<serde_json::Value>> {
+    let tree = request(GET_TREE, "").await?;
+    Ok(views(&tree)
+        .into_iter()
+        .find(|v| v.get("focused").and_then(|f| f.as_bool()) == Some(true))
+        .cloned())
+}
+
+/// Sway (or i3), over the i3-ipc protocol
+pub struct Sway;
+
+#[async_trait]
+impl Compositor for Sway {
+    async fn windows(&self) -> Result<Vec<Window>> {
+        let tree = request(GET_TREE, "").await?;
+        Ok(views(&tree).into_iter().map(window).collect())
+    }
+
+    async fn fullscreen_focused(&self) -> Result<bool> {
+        let view = focused_view().await?;
+        Ok(view.and_then(|v| v.get("fullscreen_mode")?.as_i64()).unwrap_or(0) != 0)
+    }
+
+    /// Sway doesn't report the cursor position over IPC. With `focus_follows_mouse`
+    /// (its default) the pointer is over the focused window, so use that window's center.
+    async fn cursor_position(&self) -> Result<(i32, i32)> {
+        let g = focused_view().await?
+            .as_ref()
+            .and_then(rect)
+            .context("No focused window to place the cursor in")?;
+        Ok((g.x + g.width as i32 / 2, g.y + g.height as i32 / 2))
+    }
+
+    fn reports_cursor(&self) -> bool {
+        false
+    }
+
+    async fn monitors(&self) -> Result<Vec<Monitor>> {
+        let outputs = request(GET_OUTPUTS, "").await?;
+        Ok(outputs.as_array()
+            .into_iter()
+            .flatten()
+            // Disabled outputs are listed too
+            .filter(|o| o.get("active").and_then(|a| a.as_bool()) != Some(false))
+            .filter_map(|o| Some(Monitor {
+                name: o.get("name")?.as_str()?.to_string(),
+                geometry: rect(o)?,
+                focused: o.get("focused").and_then(|f| f.as_bool()) == Some(true),
+            }))
+            .collect())
+    }
+
+    async fn place_window(&self, id: &str, g: Geometry) -> Result<()> {
+        run(&format!(
+            "[con_id={}] floating enable, resize set width {} px height {} px, move absolute position {} px {} px",
+            id, g.width, g.height, g.x, g.y
+        )).await
+    }
+
+    async fn move_windows(&self, ids: &[String], dx: i32, dy: i32) -> Result<()> {
+        let mut moves = Vec::new();
+        if dx != 0 {
+            moves.push(format!("move {} {} px", if dx < 0 { "left" } else { "right" }, dx.abs()));
+        }
+        if dy != 0 {
+            moves.push(format!("move {} {} px", if dy < 0 { "up" } else { "down" }, dy.abs()));
+        }
+        if ids.is_empty() || moves.is_empty() {
+            return Ok(());
+        }
+        let commands: Vec<String> = ids.iter()
+            .map(|id| format!("[con_id={}] {}", id, moves.join(", ")))
+            .collect();
+        run(&commands.join("; ")).await
+    }
+
+    async fn set_props(&self, id: &str, props: &[(&str, String)]) -> Result<()> {
+        // Sway only has a per-window equivalent for opacity
+        let mut commands = Vec::new();
+        for (prop, value) in props {
+            match *prop {
+                "alpha" => commands.push(format!("[con_id={}] opacity {}", id, value)),
+                _ => tracing::debug!("Sway has no window prop {:?}, skipping", prop),
+            }
+        }
+        if commands.is_empty() {
+            return Ok(());
+        }
+        run(&commands.join("; ")).await
+    }
+
+    async fn run_window_commands(&self, id: &str, commands: &[String]) -> Result<()> {
+        // Entries are Sway commands; without an {address} placeholder they target the menu
+        let commands: Vec<String> = commands.iter()
+            .map(|raw| if raw.contains("{address}") {
+                raw.replace("{address}", id)
+            } else {
+                format!("[con_id={}] {}", id, raw)
+            })
+            .collect();
+        if commands.is_empty() {
+            return Ok(());
+        }
+        run(&commands.join("; ")).await
+    }
+
+    async fn close_window(&self, id: &str) -> Result<()> {
+        run(&format!("[con_id={}] kill", id)).await
+    }
+
+    async fn jiggle_pointer(&self) -> Result<()> {
+        run("seat - cursor move 1 0; seat - cursor move -1 0").await
+    }
+
+    async fn focus_changes(&self) -> Result<mpsc::Receiver<String>> {
+        let mut stream = UnixStream::connect(socket_path()?).await?;
+        send(&mut stream, SUBSCRIBE, r#"["window"]"#).await?;
+        let (_, reply) = receive(&mut stream).await?;
+        if reply.get("success").and_then(|s| s.as_bool()) != Some(true) {
+            bail!("Sway refused the window event subscription");
+        }
+
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            while let Ok((_, event)) = receive(&mut stream).await {
+                // {"change": "focus", "container": {"id": 12, ...}}
+                if event.get("change").and_then(|c| c.as_str()) != Some("focus") {
+                    continue;
+                }
+                let id = event.pointer("/container/id").and_then(|i| i.as_i64());
+                let id = id.map(|i| i.to_string()).unwrap_or_default();
+                if tx.send(id).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(rx)
+    }
+}
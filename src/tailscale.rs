@@ -0,0 +1,108 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::process::Command;
+
+use crate::command;
+
+/// A tailnet device as `tailscale status --json` reports it
+pub struct Peer {
+    pub host_name: String,
+    pub ip: String,
+    pub os: String,
+    pub online: bool,
+    /// Whether this peer is our current exit node
+    pub exit_node: bool,
+    /// Whether this peer offers itself as an exit node
+    pub exit_node_option: bool,
+    /// "City, Country" for exit nodes that report a location
+    pub location: Option<String>,
+}
+
+pub struct Status {
+    /// "Running", "Stopped", "NeedsLogin", ...
+    pub backend_state: String,
+    pub tailnet: String,
+    /// This machine
+    pub own: Option<Peer>,
+    pub peers: Vec<Peer>,
+}
+
+impl Status {
+    pub fn is_running(&self) -> bool {
+        self.backend_state == "Running"
+    }
+
+    pub fn exit_node(&self) -> Option<&Peer> {
+        self.peers.iter().find(|p| p.exit_node)
+    }
+}
+
+pub fn status() -> Result<Status> {
+    let output = command::output(Command::new("tailscale").args(["status", "--json"]))?;
+    let json: Value = serde_json::from_slice(&output.stdout).context("Bad JSON from tailscale status")?;
+    let mut peers: Vec<Peer> = json
+        .get("Peer")
+        .and_then(Value::as_object)
+        .map(|peers| peers.values().map(parse_peer).collect())
+        .unwrap_or_default();
+    peers.sort_by_key(|p| p.host_name.to_lowercase());
+    Ok(Status {
+        backend_state: text(&json, "/BackendState"),
+        tailnet: text(&json, "/CurrentTailnet/Name"),
+        own: json.get("Self").map(parse_peer),
+        peers,
+    })
+}
+
+fn parse_peer(peer: &Value) -> Peer {
+    let flag = |key: &str| peer.get(key).and_then(Value::as_bool).unwrap_or(false);
+    let location = peer.get("Location").and_then(|l| {
+        let city = l.get("City").and_then(Value::as_str)?;
+        match l.get("Country").and_then(Value::as_str) {
+            Some(country) => Some(format!("{}, {}", city, country)),
+            None => Some(city.to_string()),
+        }
+    });
+    Peer {
+        host_name: text(peer, "/HostName"),
+        ip: text(peer, "/TailscaleIPs/0"),
+        os: text(peer, "/OS"),
+        online: flag("Online"),
+        exit_node: flag("ExitNode"),
+        exit_node_option: flag("ExitNodeOption"),
+        location,
+    }
+}
+
+fn text(json: &Value, pointer: &str) -> String {
+    json.pointer(pointer).and_then(Value::as_str).unwrap_or("").to_string()
+}
+
+/// Connect ("up"), disconnect ("down"), or "toggle"
+pub fn set_connected(mode: &str) -> Result<()> {
+    let up = match mode {
+        "up" => true,
+        "down" => false,
+        "toggle" => !status()?.is_running(),
+        _ => bail!("Unknown tailscale command {:?}, expected up, down, toggle or exit-node", mode),
+    };
+    run(&[if up { "up" } else { "down" }])
+}
+
+/// Stop using the exit node if one is in use, else route through `preferred`
+/// (a peer's host name, MagicDNS name or IP)
+pub fn toggle_exit_node(preferred: Option<&str>) -> Result<()> {
+    if status()?.exit_node().is_some() {
+        return run(&["set", "--exit-node="]);
+    }
+    let node = preferred.context("No exit_node configured for the tailscale module")?;
+    run(&["set", &format!("--exit-node={}", node)])
+}
+
+fn run(args: &[&str]) -> Result<()> {
+    let output = command::output(Command::new("tailscale").args(args))?;
+    if !output.status.success() {
+        bail!("tailscale {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
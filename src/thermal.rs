@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+const HWMON_DIR: &str = "/sys/class/hwmon";
+
+/// CPU sensor drivers, most useful first, for when no `sensor` is configured
+const PREFERRED_CHIPS: [&str; 6] = ["coretemp", "k10temp", "zenpower", "cpu_thermal", "soc_thermal", "acpitz"];
+
+/// One temperature input of a hwmon chip, e.g. `temp1_input` of "coretemp"
+pub struct Sensor {
+    /// Chip name from hwmon's `name` file
+    pub chip: String,
+    /// The input's label, e.g. "Package id 0", else "tempN"
+    pub label: String,
+    /// Degrees Celsius
+    pub celsius: f64,
+    /// The chip's own high and critical marks, if it reports them
+    pub max: Option<f64>,
+    pub crit: Option<f64>,
+    /// The chip's hwmon directory
+    dir: PathBuf,
+}
+
+/// The sensor a `sensor` setting names: a path to a `temp*_input` file or a hwmon
+/// directory, a chip name ("k10temp"), or a chip and input label ("coretemp:Package id 0").
+/// Without one, the first input of the first CPU chip found.
+pub fn find(setting: Option<&str>) -> Option<Sensor> {
+    match setting {
+        Some(path) if path.contains('/') => {
+            let path = PathBuf::from(shellexpand::tilde(path).as_ref());
+            if path.is_dir() {
+                inputs(&path).into_iter().next()
+            } else {
+                read_input(&path)
+            }
+        }
+        Some(setting) => {
+            let (chip, label) = match setting.split_once(':') {
+                Some((chip, label)) => (chip, Some(label)),
+                None => (setting, None),
+            };
+            chips()
+                .into_iter()
+                .filter(|(_, name)| name == chip)
+                .flat_map(|(dir, _)| inputs(&dir))
+                .find(|s| label.is_none_or(|l| s.label == l))
+        }
+        None => {
+            let chips = chips();
+            PREFERRED_CHIPS
+                .iter()
+                .find_map(|preferred| {
+                    chips
+                        .iter()
+                        .filter(|(_, name)| name == preferred)
+                        .find_map(|(dir, _)| inputs(dir).into_iter().next())
+                })
+                .or_else(|| chips.iter().find_map(|(dir, _)| inputs(dir).into_iter().next()))
+        }
+    }
+}
+
+/// Every input of the chip a sensor belongs to, for the tooltip
+pub fn siblings(sensor: &Sensor) -> Vec<Sensor> {
+    inputs(&sensor.dir)
+}
+
+/// hwmon chip directories with their names, in directory order
+fn chips() -> Vec<(PathBuf, String)> {
+    let Ok(entries) = std::fs::read_dir(HWMON_DIR) else {
+        return Vec::new();
+    };
+    let mut chips: Vec<(PathBuf, String)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter_map(|dir| {
+            let name = std::fs::read_to_string(dir.join("name")).ok()?.trim().to_string();
+            Some((dir, name))
+        })
+        .collect();
+    chips.sort();
+    chips
+}
+
+/// A chip's temperature inputs, in index order
+fn inputs(dir: &Path) -> Vec<Sensor> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(u32, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let index = name.strip_prefix("temp")?.strip_suffix("_input")?.parse().ok()?;
+            Some((index, e.path()))
+        })
+        .collect();
+    files.sort();
+    files.iter().filter_map(|(_, path)| read_input(path)).collect()
+}
+
+/// Read `tempN_input` and its `_label`, `_max` and `_crit` neighbours
+fn read_input(input: &Path) -> Option<Sensor> {
+    let file = input.file_name()?.to_str()?;
+    let prefix = file.strip_suffix("_input")?;
+    let dir = input.parent()?;
+    let millidegrees = |suffix: &str| -> Option<f64> {
+        let raw = std::fs::read_to_string(dir.join(format!("{}_{}", prefix, suffix))).ok()?;
+        raw.trim().parse::<f64>().ok().map(|m| m / 1000.0)
+    };
+    let label = std::fs::read_to_string(dir.join(format!("{}_label", prefix)))
+        .map(|l| l.trim().to_string())
+        .unwrap_or_else(|_| prefix.to_string());
+    let chip = std::fs::read_to_string(dir.join("name"))
+        .map(|n| n.trim().to_string())
+        .unwrap_or_default();
+    Some(Sensor {
+        chip,
+        label,
+        celsius: millidegrees("input")?,
+        // Some drivers report 0 or absurd values for marks they don't have
+        max: millidegrees("max").filter(|t| *t > 0.0 && *t < 200.0),
+        crit: millidegrees("crit").filter(|t| *t > 0.0 && *t < 200.0),
+        dir: dir.to_path_buf(),
+    })
+}
@@ -0,0 +1,178 @@
+use anyhow::{bail, Result};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::command;
+
+/// Seconds between checks when the module sets no `poll_interval`
+pub const DEFAULT_INTERVAL: u64 = 3600;
+
+/// How long a checker may run; syncing package databases is slow
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Known checkers: (name, program it needs, command that installs its updates)
+const CHECKERS: [(&str, &str, &str); 4] = [
+    ("pacman", "checkupdates", "sudo pacman -Syu"),
+    ("apt", "apt", "sudo apt upgrade"),
+    ("dnf", "dnf", "sudo dnf upgrade"),
+    ("flatpak", "flatpak", "flatpak update"),
+];
+
+/// A package with a newer version available
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub name: String,
+    /// Installed version, when the checker reports it
+    pub from: String,
+    pub to: String,
+    /// Checker that found it, e.g. "pacman"
+    pub source: &'static str,
+}
+
+/// Result of the last check and when it finished
+static LAST: Mutex<Option<(Instant, Vec<Update>)>> = Mutex::new(None);
+
+/// The configured checkers, or every known one whose program is installed
+pub fn checkers(configured: &[String]) -> Vec<&'static str> {
+    if configured.is_empty() {
+        return CHECKERS
+            .iter()
+            .filter(|(_, program, _)| command::installed(program))
+            .map(|(name, ..)| *name)
+            .collect();
+    }
+    configured
+        .iter()
+        .filter_map(|name| match CHECKERS.iter().find(|(n, ..)| n == name) {
+            Some((name, ..)) => Some(*name),
+            None => {
+                tracing::warn!("Unknown update checker {:?}, expected pacman, apt, dnf or flatpak", name);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The command that installs a checker's updates, e.g. "sudo pacman -Syu"
+pub fn upgrade_command(checker: &str) -> Option<&'static str> {
+    CHECKERS.iter().find(|(name, ..)| *name == checker).map(|(.., upgrade)| *upgrade)
+}
+
+/// Run the checkers and remember what they found. A checker that fails (offline,
+/// database locked) is skipped; if all of them fail, the last result is kept.
+pub fn check(checkers: &[&'static str]) -> Result<()> {
+    let mut updates = Vec::new();
+    let mut failed = 0;
+    for checker in checkers {
+        match run(checker) {
+            Ok(found) => updates.extend(found),
+            Err(e) => {
+                tracing::warn!("Update check with {} failed: {:#}", checker, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 && failed == checkers.len() {
+        bail!("Every update checker failed");
+    }
+    *LAST.lock().unwrap() = Some((Instant::now(), updates));
+    Ok(())
+}
+
+/// Updates found by the last successful check and how long ago it ran; `None` before one has
+pub fn pending() -> Option<(Duration, Vec<Update>)> {
+    LAST.lock()
+        .unwrap()
+        .as_ref()
+        .map(|(checked, updates)| (checked.elapsed(), updates.clone()))
+}
+
+fn run(checker: &'static str) -> Result<Vec<Update>> {
+    let (program, args): (&str, &[&str]) = match checker {
+        // checkupdates syncs a private copy of the database, so it needs no root
+        "pacman" => ("checkupdates", &[]),
+        "apt" => ("apt", &["list", "--upgradable"]),
+        "dnf" => ("dnf", &["check-update", "--quiet"]),
+        "flatpak" => ("flatpak", &["remote-ls", "--updates", "--columns=application,version"]),
+        _ => bail!("Unknown update checker {:?}", checker),
+    };
+    let output = command::output(Command::new(program).args(args))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // checkupdates exits 2 and dnf 100 to say "no updates" and "updates" respectively
+    let ok = match checker {
+        "pacman" => output.status.success() || output.status.code() == Some(2),
+        "dnf" => output.status.success() || output.status.code() == Some(100),
+        _ => output.status.success(),
+    };
+    if !ok {
+        bail!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(match checker {
+        "pacman" => parse_checkupdates(&stdout),
+        "apt" => parse_apt(&stdout),
+        "dnf" => parse_dnf(&stdout),
+        _ => parse_flatpak(&stdout),
+    })
+}
+
+/// Lines like "linux 6.9.1.arch1-1 -> 6.9.2.arch1-1"
+fn parse_checkupdates(output: &str) -> Vec<Update> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let name = words.next()?;
+            let from = words.next()?;
+            let to = words.nth(1)?;
+            Some(Update { name: name.to_string(), from: from.to_string(), to: to.to_string(), source: "pacman" })
+        })
+        .collect()
+}
+
+/// Lines like "curl/noble-updates 8.5.0-2ubuntu10.2 amd64 [upgradable from: 8.5.0-2ubuntu10.1]"
+/// after a "Listing..." header
+fn parse_apt(output: &str) -> Vec<Update> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, rest) = line.split_once('/')?;
+            let to = rest.split_whitespace().nth(1)?;
+            let from = line
+                .split_once("[upgradable from: ")
+                .map(|(_, from)| from.trim_end_matches(']'))
+                .unwrap_or("");
+            Some(Update { name: name.to_string(), from: from.to_string(), to: to.to_string(), source: "apt" })
+        })
+        .collect()
+}
+
+/// Lines like "kernel.x86_64  6.9.2-200.fc40  updates", then possibly an
+/// "Obsoleting Packages" section after a blank line
+fn parse_dnf(output: &str) -> Vec<Update> {
+    output
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let package = words.next()?;
+            let to = words.next()?;
+            words.next()?; // repository
+            let name = package.rsplit_once('.').map_or(package, |(name, _arch)| name);
+            Some(Update { name: name.to_string(), from: String::new(), to: to.to_string(), source: "dnf" })
+        })
+        .collect()
+}
+
+/// Tab-separated "org.mozilla.firefox\t128.0" lines
+fn parse_flatpak(output: &str) -> Vec<Update> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (name, to) = line.split_once('\t').unwrap_or((line, ""));
+            Update { name: name.trim().to_string(), from: String::new(), to: to.trim().to_string(), source: "flatpak" }
+        })
+        .collect()
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+use crate::announce;
+use crate::config::ModuleConfig;
+use crate::dnd;
+use crate::modules::ModuleStatus;
+
+#[derive(Default)]
+struct UrgentState {
+    /// Condition was urgent on the last status
+    active: bool,
+    /// Menu was opened since the condition started
+    acknowledged: bool,
+}
+
+fn states() -> &'static Mutex<HashMap<String, UrgentState>> {
+    static STATES: OnceLock<Mutex<HashMap<String, UrgentState>>> = OnceLock::new();
+    STATES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Mark a module's urgent condition as seen (its menu was opened)
+pub fn acknowledge(module: &str) {
+    if let Ok(mut states) = states().lock() {
+        if let Some(state) = states.get_mut(module) {
+            state.acknowledged = state.active;
+        }
+    }
+}
+
+/// Add the `urgent` class while the module's condition holds and hasn't been acknowledged.
+/// The condition is the status' own urgent flag or a `critical` threshold class.
+pub fn apply(mut status: ModuleStatus, module: &str, config: &ModuleConfig) -> ModuleStatus {
+    if !config.urgent || !condition(&status) || dnd::is_active() {
+        return status;
+    }
+    let acknowledged = states()
+        .lock()
+        .is_ok_and(|states| states.get(module).is_some_and(|state| state.active && state.acknowledged));
+    if !acknowledged {
+        status.class.push("urgent".to_string());
+    }
+    status
+}
+
+/// Follow a module's condition as its status goes out to the bar: notify and announce
+/// when it starts, and re-arm once it resolves
+pub fn observe(module: &str, status: &ModuleStatus, config: &ModuleConfig) {
+    if !config.urgent {
+        return;
+    }
+    let Ok(mut states) = states().lock() else {
+        return;
+    };
+    let state = states.entry(module.to_string()).or_default();
+
+    if !condition(status) {
+        // Resolved - the next occurrence alerts again
+        *state = UrgentState::default();
+        return;
+    }
+
+    let rising = !state.active;
+    state.active = true;
+    if !rising || state.acknowledged || dnd::is_active() {
+        return;
+    }
+
+    if config.urgent_notify {
+        notify(module, status);
+    }
+    announce::say(&announce::urgent_message(&announce::label(module, Some(config)), status));
+}
+
+fn condition(status: &ModuleStatus) -> bool {
+    status.urgent || status.class.iter().any(|c| c == "critical")
+}
+
+fn notify(module: &str, status: &ModuleStatus) {
+    let body = if status.tooltip.is_empty() {
+        status.text.clone()
+    } else {
+        status.plain_tooltip()
+    };
+    let result = Command::new("notify-send")
+        .args(["-a", "waybar-hovermenu", "-u", "critical", module, &body])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match result {
+        // Reap in the background so the child doesn't linger as a zombie
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => tracing::warn!("Failed to send urgent notification: {}", e),
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::command;
+use crate::tailscale;
+
+const NET_CLASS: &str = "/sys/class/net";
+
+/// `IFF_UP` from linux/if.h
+const IFF_UP: u32 = 0x1;
+
+/// `IFF_TUN` from linux/if_tun.h: a layer 3 tun device, which OpenVPN and most other
+/// VPN clients create. Tap devices are mostly virtual machine NICs (libvirt's `vnet*`).
+const IFF_TUN: u32 = 0x1;
+
+/// Tun devices of tools that aren't a VPN, or that have a `vpn_provider` of their own
+const NOT_OPENVPN: [&str; 1] = ["tailscale"];
+
+/// How long a failed exit lookup is remembered before it is tried again
+const EXIT_RETRY: Duration = Duration::from_secs(60);
+
+/// A VPN tunnel that is up
+pub struct Tunnel {
+    /// Interface name, or the relay/exit node for providers that report one
+    pub name: String,
+    /// "wireguard", "openvpn", "tailscale" or "mullvad"; "tunnel" for a listed interface of another kind
+    pub provider: &'static str,
+    /// Network interface carrying the tunnel, when known
+    pub interface: Option<String>,
+    /// Where traffic leaves the tunnel, e.g. "Gothenburg, Sweden"
+    pub exit: Option<String>,
+}
+
+/// Tunnels that are up. `interfaces` names the ones to look at; otherwise `provider`
+/// picks a backend, and with neither every WireGuard and tun/tap interface counts.
+pub fn tunnels(provider: Option<&str>, interfaces: &[String]) -> Result<Vec<Tunnel>> {
+    if !interfaces.is_empty() {
+        return Ok(interfaces
+            .iter()
+            .filter(|name| is_up(name))
+            .map(|name| interface_tunnel(name, kind(name).unwrap_or("tunnel")))
+            .collect());
+    }
+    match provider {
+        None => Ok(up_interfaces(None)),
+        Some("wireguard") => Ok(up_interfaces(Some("wireguard"))),
+        Some("openvpn") => Ok(up_interfaces(Some("openvpn"))),
+        Some("tailscale") => tailscale(),
+        Some("mullvad") => mullvad(),
+        Some(other) => bail!("Unknown vpn_provider {:?}, expected wireguard, openvpn, tailscale or mullvad", other),
+    }
+}
+
+fn interface_tunnel(name: &str, provider: &'static str) -> Tunnel {
+    Tunnel { name: name.to_string(), provider, interface: Some(name.to_string()), exit: None }
+}
+
+/// Up WireGuard ("wireguard") and tun ("openvpn") interfaces, optionally of one kind
+fn up_interfaces(only: Option<&str>) -> Vec<Tunnel> {
+    let Ok(entries) = std::fs::read_dir(NET_CLASS) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+        .iter()
+        .filter_map(|name| {
+            let kind = kind(name)?;
+            (only.is_none_or(|k| k == kind) && is_up(name)).then(|| interface_tunnel(name, kind))
+        })
+        .collect()
+}
+
+/// "wireguard" for WireGuard interfaces, "openvpn" for tun ones that no other tool
+/// claims, `None` for the rest
+fn kind(interface: &str) -> Option<&'static str> {
+    let dir = Path::new(NET_CLASS).join(interface);
+    let uevent = std::fs::read_to_string(dir.join("uevent")).unwrap_or_default();
+    if uevent.lines().any(|l| l == "DEVTYPE=wireguard") {
+        Some("wireguard")
+    } else if hex_file(&dir.join("tun_flags")).is_some_and(|flags| flags & IFF_TUN != 0)
+        && !NOT_OPENVPN.iter().any(|owner| interface.starts_with(owner))
+    {
+        Some("openvpn")
+    } else {
+        None
+    }
+}
+
+/// Whether an interface exists and is administratively up
+fn is_up(interface: &str) -> bool {
+    hex_file(&Path::new(NET_CLASS).join(interface).join("flags")).is_some_and(|flags| flags & IFF_UP != 0)
+}
+
+/// A sysfs attribute holding a hex number, e.g. "0x1003"
+fn hex_file(path: &Path) -> Option<u32> {
+    let text = std::fs::read_to_string(path).ok()?;
+    u32::from_str_radix(text.trim().trim_start_matches("0x"), 16).ok()
+}
+
+/// Tailscale, when connected, with the exit node if one is in use
+fn tailscale() -> Result<Vec<Tunnel>> {
+    let status = tailscale::status()?;
+    if !status.is_running() {
+        return Ok(Vec::new());
+    }
+    let exit_node = status.exit_node();
+    let name = match exit_node {
+        Some(peer) => peer.host_name.clone(),
+        None if status.tailnet.is_empty() => "tailnet".to_string(),
+        None => status.tailnet.clone(),
+    };
+    let exit = exit_node.and_then(|peer| peer.location.clone());
+    Ok(vec![Tunnel { name, provider: "tailscale", interface: Some("tailscale0".to_string()), exit }])
+}
+
+/// Mullvad, when connected: "Connected to se-got-wg-001 in Gothenburg, Sweden" from older
+/// CLIs, or a "Connected" line followed by "Relay:" and "Visible location:" lines
+fn mullvad() -> Result<Vec<Tunnel>> {
+    let output = command::output(Command::new("mullvad").arg("status"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim);
+    let Some(first) = lines.next().filter(|l| l.starts_with("Connected")) else {
+        return Ok(Vec::new());
+    };
+    let (mut name, mut exit) = match first.strip_prefix("Connected to ").and_then(|r| r.split_once(" in ")) {
+        Some((relay, location)) => (relay.to_string(), Some(location.to_string())),
+        None => ("mullvad".to_string(), None),
+    };
+    for line in lines {
+        if let Some(relay) = line.strip_prefix("Relay:") {
+            name = relay.trim().to_string();
+        } else if let Some(location) = line.strip_prefix("Visible location:") {
+            // "Sweden, Gothenburg. IPv4: 185.65.134.1"
+            exit = Some(location.split(". ").next().unwrap_or(location).trim().to_string());
+        }
+    }
+    Ok(vec![Tunnel { name, provider: "mullvad", interface: None, exit }])
+}
+
+/// Last public exit looked up, keyed by the tunnels that were up at the time.
+/// A failed lookup is kept too, with when it failed, so it is retried after `EXIT_RETRY`.
+static EXIT: Mutex<Option<(String, Result<String, Instant>)>> = Mutex::new(None);
+
+/// Where traffic appears to come from, e.g. "Amsterdam, Netherlands (185.65.134.1)",
+/// looked up once per set of up tunnels
+pub fn public_exit(tunnels: &[Tunnel]) -> Option<String> {
+    let key: Vec<&str> = tunnels.iter().map(|t| t.name.as_str()).collect();
+    let key = key.join(",");
+    if let Some((cached, exit)) = EXIT.lock().unwrap().as_ref() {
+        match exit {
+            Ok(exit) if *cached == key => return Some(exit.clone()),
+            Err(failed) if *cached == key && failed.elapsed() < EXIT_RETRY => return None,
+            _ => {}
+        }
+    }
+    let exit = lookup_exit().map_err(|e| {
+        tracing::debug!("Exit location lookup failed: {:#}", e);
+        Instant::now()
+    });
+    *EXIT.lock().unwrap() = Some((key, exit.clone()));
+    exit.ok()
+}
+
+fn lookup_exit() -> Result<String> {
+    let client = reqwest::blocking::Client::builder().timeout(command::current_timeout()).build()?;
+    let reply: Value = client.get("https://am.i.mullvad.net/json").send()?.error_for_status()?.json()?;
+    let text = |key: &str| reply.get(key).and_then(Value::as_str).unwrap_or("");
+    let place = [text("city"), text("country")]
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(format!("{} ({})", place, text("ip")))
+}
@@ -1,178 +1,579 @@
-use anyhow::Result;
-use std::path::Path;
-use std::process::{Command, Stdio};
+use anyhow::{Context, Result};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 use tokio::process::Command as TokioCommand;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, Semaphore};
+use tokio::task::JoinSet;
 
-use crate::config::Config;
+use crate::backlight::Backlight;
+use crate::battery;
+use crate::bluez;
+use crate::config::{Config, ModuleConfig};
+use crate::hardware;
+use crate::icons;
+use crate::idle;
+use crate::mail;
+use crate::media;
 use crate::menu::MenuManager;
-use crate::modules::get_status;
+use crate::network;
+use crate::notifications;
+use crate::modules::{get_status, ModuleStatus};
+use crate::osd;
+use crate::profile;
+use crate::pulse;
+use crate::rfkill;
+use crate::updates;
+use crate::urgent;
+use crate::weather;
 
-/// Start all watchers for real-time status updates
-pub async fn start_watchers(
+/// Start all watchers for real-time status updates.
+/// Dropping (or aborting) the returned set stops them, e.g. to restart them on reload.
+pub fn start_watchers(
     config: Arc<Config>,
     menu_manager: Arc<MenuManager>,
     status_tx: broadcast::Sender<(String, String)>,
-) {
+) -> JoinSet<()> {
+    let mut watchers = JoinSet::new();
+    
+    // Idle monitor (logind), so pollers can pause while nobody is looking
+    if config.daemon.pause_when_idle {
+        watchers.spawn(async move {
+            if let Err(e) = idle::run().await {
+                tracing::warn!("Idle monitor unavailable, pollers keep running: {}", e);
+            }
+        });
+    }
+    
     // Audio watcher (PulseAudio)
+    let cfg = Arc::clone(&config);
     let tx = status_tx.clone();
     let mm = Arc::clone(&menu_manager);
-    tokio::spawn(async move {
-        if let Err(e) = watch_audio(tx, mm).await {
+    watchers.spawn(async move {
+        if let Err(e) = watch_audio(cfg, tx, mm).await {
             tracing::error!("Audio watcher error: {}", e);
         }
     });
     
-    // Bluetooth watcher (dbus-monitor)
+    // Bluetooth watcher (BlueZ signals on the system bus)
+    let cfg = Arc::clone(&config);
     let tx = status_tx.clone();
     let mm = Arc::clone(&menu_manager);
-    tokio::spawn(async move {
-        if let Err(e) = watch_bluetooth(tx, mm).await {
+    watchers.spawn(async move {
+        if let Err(e) = watch_bluetooth(cfg, tx, mm).await {
             tracing::error!("Bluetooth watcher error: {}", e);
         }
     });
     
-    // Network watcher (dbus-monitor)
+    // Network watcher (NetworkManager and iwd signals)
+    let cfg = Arc::clone(&config);
     let tx = status_tx.clone();
     let mm = Arc::clone(&menu_manager);
-    tokio::spawn(async move {
-        if let Err(e) = watch_network(tx, mm).await {
+    watchers.spawn(async move {
+        if let Err(e) = watch_network(cfg, tx, mm).await {
             tracing::error!("Network watcher error: {}", e);
         }
     });
     
     // CPU poller
+    let cfg = Arc::clone(&config);
     let tx = status_tx.clone();
     let mm = Arc::clone(&menu_manager);
     let interval = config.modules.get("cpu")
         .and_then(|m| m.poll_interval)
         .unwrap_or(3);
-    tokio::spawn(async move {
-        poll_module("cpu", Duration::from_secs(interval), tx, mm).await;
+    watchers.spawn(async move {
+        poll_module(cfg, "cpu", Duration::from_secs(interval), tx, mm).await;
     });
     
+    // Memory poller
+    if config.modules.contains_key("memory") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = config.modules.get("memory")
+            .and_then(|m| m.poll_interval)
+            .unwrap_or(5);
+        watchers.spawn(async move {
+            poll_module(cfg, "memory", Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // Disk poller
+    if config.modules.contains_key("disk") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = config.modules.get("disk")
+            .and_then(|m| m.poll_interval)
+            .unwrap_or(60);
+        watchers.spawn(async move {
+            poll_module(cfg, "disk", Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // Temperature poller
+    if config.modules.contains_key("temperature") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = config.modules.get("temperature")
+            .and_then(|m| m.poll_interval)
+            .unwrap_or(5);
+        watchers.spawn(async move {
+            poll_module(cfg, "temperature", Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // Night light poller, to notice the gamma tool being started, stopped or crashing elsewhere
+    if config.modules.contains_key("nightlight") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = config.modules.get("nightlight")
+            .and_then(|m| m.poll_interval)
+            .unwrap_or(5);
+        watchers.spawn(async move {
+            poll_module(cfg, "nightlight", Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // VPN pollers ("surfshark" is the vpn module under another name)
+    for module in ["vpn", "surfshark"] {
+        let Some(module_config) = config.modules.get(module) else {
+            continue;
+        };
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = module_config.poll_interval.unwrap_or(5);
+        watchers.spawn(async move {
+            poll_module(cfg, module, Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // Tailscale poller
+    if config.modules.contains_key("tailscale") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = config.modules.get("tailscale")
+            .and_then(|m| m.poll_interval)
+            .unwrap_or(10);
+        watchers.spawn(async move {
+            poll_module(cfg, "tailscale", Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // Weather fetcher (status reads only see what it fetched, so hovers don't hit the network)
+    if config.modules.contains_key("weather") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        watchers.spawn(async move {
+            watch_weather(cfg, tx, mm).await;
+        });
+    }
+    
     // Battery watcher (UPower) + fallback poller
+    let cfg = Arc::clone(&config);
     let tx = status_tx.clone();
     let mm = Arc::clone(&menu_manager);
-    tokio::spawn(async move {
-        if let Err(e) = watch_battery(tx, mm).await {
+    watchers.spawn(async move {
+        if let Err(e) = watch_battery(cfg, tx, mm).await {
             tracing::error!("Battery watcher error: {}", e);
         }
     });
     
     // Mail watcher (inotify)
+    if config.modules.contains_key("mail") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let mail_dir = config.modules.get("mail")
+            .and_then(|m| m.watch_dir.clone())
+            .unwrap_or_else(|| "~/.local/share/mail".to_string());
+        watchers.spawn(async move {
+            if let Err(e) = watch_mail(cfg, &mail_dir, tx, mm).await {
+                tracing::error!("Mail watcher error: {}", e);
+            }
+        });
+    }
+    
+    // Calendar/clock poller (every 30 seconds - updates on the minute)
+    let cfg = Arc::clone(&config);
     let tx = status_tx.clone();
     let mm = Arc::clone(&menu_manager);
-    let mail_dir = config.modules.get("mail")
-        .and_then(|m| m.watch_dir.clone())
-        .unwrap_or_else(|| "~/.local/share/mail".to_string());
-    tokio::spawn(async move {
-        if let Err(e) = watch_mail(&mail_dir, tx, mm).await {
-            tracing::error!("Mail watcher error: {}", e);
-        }
+    watchers.spawn(async move {
+        poll_module(cfg, "calendar", Duration::from_secs(30), tx, mm).await;
     });
     
-    // Calendar/clock poller (every 30 seconds - updates on the minute)
+    // Failed systemd units poller
+    if config.modules.contains_key("systemd") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = config.modules.get("systemd")
+            .and_then(|m| m.poll_interval)
+            .unwrap_or(60);
+        watchers.spawn(async move {
+            poll_module(cfg, "systemd", Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // Brightness watcher (inotify on the backlight's sysfs node)
+    if config.modules.contains_key("brightness") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        watchers.spawn(async move {
+            if let Err(e) = watch_brightness(cfg, tx, mm).await {
+                tracing::warn!("Brightness watcher unavailable: {}", e);
+            }
+        });
+    }
+    
+    // Hotplug watcher (udev), so hidden hardware modules appear/disappear
+    let cfg = Arc::clone(&config);
     let tx = status_tx.clone();
     let mm = Arc::clone(&menu_manager);
-    tokio::spawn(async move {
-        poll_module("calendar", Duration::from_secs(30), tx, mm).await;
+    watchers.spawn(async move {
+        if let Err(e) = watch_hotplug(cfg, tx, mm).await {
+            tracing::error!("Hotplug watcher error: {}", e);
+        }
     });
+    
+    // Blinkers for modules with transient attention states
+    for (name, module) in &config.modules {
+        if module.enabled && !module.blink_on.is_empty() {
+            let tx = status_tx.clone();
+            let name = name.clone();
+            let module = module.clone();
+            watchers.spawn(async move {
+                blink_module(name, module, tx).await;
+            });
+        }
+    }
+    
+    // Hotspot poller (client counts aren't signalled)
+    if config.modules.contains_key("hotspot") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = config.modules.get("hotspot")
+            .and_then(|m| m.poll_interval)
+            .unwrap_or(15);
+        watchers.spawn(async move {
+            poll_module(cfg, "hotspot", Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // Profile switcher (lid, AC, external monitors)
+    if !config.profiles.is_empty() {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        watchers.spawn(profile::run(cfg, mm, tx));
+    }
+    
+    // rfkill watcher (/dev/rfkill events)
+    if config.modules.contains_key("airplane") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        watchers.spawn(async move {
+            if let Err(e) = watch_rfkill(cfg, tx, mm).await {
+                tracing::error!("rfkill watcher error: {}", e);
+            }
+        });
+    }
+    
+    // Custom modules: refreshed by their watch_command, else polled
+    for (name, module) in &config.modules {
+        if !module.enabled || module.status_command.is_none() {
+            continue;
+        }
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let name = name.clone();
+        match module.watch_command.clone() {
+            Some(watch) => {
+                watchers.spawn(async move {
+                    watch_custom(cfg, name, watch, tx, mm).await;
+                });
+            }
+            None => {
+                let interval = module.poll_interval.unwrap_or(10);
+                watchers.spawn(async move {
+                    poll_module(cfg, &name, Duration::from_secs(interval), tx, mm).await;
+                });
+            }
+        }
+    }
+    
+    // Notifications: swaync reports changes as they happen, mako and dunst are polled
+    if let Some(module) = config.modules.get("notifications") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let setting = module.notification_daemon.clone();
+        let interval = module.poll_interval.unwrap_or(5);
+        watchers.spawn(async move {
+            let server = tokio::task::spawn_blocking(move || notifications::Server::find(setting.as_deref()))
+                .await
+                .ok()
+                .flatten();
+            match server.and_then(|s| s.subscribe_command()) {
+                Some(watch) => watch_custom(cfg, "notifications".to_string(), watch.to_string(), tx, mm).await,
+                None => poll_module(cfg, "notifications", Duration::from_secs(interval), tx, mm).await,
+            }
+        });
+    }
+    
+    // Updates checker (slow, so it runs here rather than on each status read)
+    if config.modules.contains_key("updates") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        let interval = config.modules.get("updates")
+            .and_then(|m| m.poll_interval)
+            .unwrap_or(updates::DEFAULT_INTERVAL);
+        watchers.spawn(async move {
+            watch_updates(cfg, Duration::from_secs(interval), tx, mm).await;
+        });
+    }
+    
+    // Media watcher (MPRIS signals on the session bus)
+    if config.modules.contains_key("media") {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        watchers.spawn(async move {
+            if let Err(e) = watch_media(cfg, tx, mm).await {
+                tracing::error!("Media watcher error: {}", e);
+            }
+        });
+    }
+    
+    // DND poller, so scheduled transitions reach the bar
+    if config.daemon.dnd_schedule.is_some() {
+        let cfg = Arc::clone(&config);
+        let tx = status_tx.clone();
+        let mm = Arc::clone(&menu_manager);
+        watchers.spawn(async move {
+            poll_module(cfg, "dnd", Duration::from_secs(30), tx, mm).await;
+        });
+    }
+    
+    watchers
+}
+
+/// Compute every enabled module's status concurrently, at most
+/// `daemon.status_concurrency` at a time. Each module gets twice its command
+/// timeout; one that overruns is left out rather than holding up the rest.
+pub fn gather(
+    config: &Arc<Config>,
+    menu_manager: &Arc<MenuManager>,
+) -> JoinSet<Option<(String, ModuleStatus)>> {
+    let permits = Arc::new(Semaphore::new(config.daemon.status_concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    for (name, module) in &config.modules {
+        if !module.enabled {
+            continue;
+        }
+        let deadline = Duration::from_millis(
+            module.timeout_ms.unwrap_or(config.daemon.command_timeout_ms) * 2,
+        );
+        let cfg = Arc::clone(config);
+        let mm = Arc::clone(menu_manager);
+        let permits = Arc::clone(&permits);
+        let name = name.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await.ok()?;
+            let highlighted = if mm.hover_enabled() {
+                mm.is_pinned(&name).await
+            } else {
+                mm.is_menu_open(&name).await
+            };
+            let module_name = name.clone();
+            let read = tokio::task::spawn_blocking(move || get_status(&cfg, &module_name, highlighted));
+            match tokio::time::timeout(deadline, read).await {
+                Ok(status) => Some((name, status.unwrap_or_else(|_| ModuleStatus::new("error")))),
+                Err(_) => {
+                    tracing::warn!("{} status took longer than {:?}, skipping", name, deadline);
+                    None
+                }
+            }
+        });
+    }
+    tasks
+}
+
+/// Compute and broadcast every enabled module's status, each as soon as it's ready
+pub async fn refresh_all(
+    config: &Arc<Config>,
+    menu_manager: &Arc<MenuManager>,
+    tx: &broadcast::Sender<(String, String)>,
+) {
+    let mut tasks = gather(config, menu_manager);
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(Some((name, status))) = result {
+            publish(config, tx, &name, &status);
+        }
+    }
+}
+
+/// Send a module's status to the bar. Urgent conditions and the unread mail count are
+/// followed here, as statuses go out, so reading a status has no side effects.
+pub fn publish(
+    config: &Config,
+    tx: &broadcast::Sender<(String, String)>,
+    module: &str,
+    status: &ModuleStatus,
+) {
+    if let Some(module_config) = config.get_module(module) {
+        urgent::observe(module, status, module_config);
+    }
+    if module == "mail" {
+        if let Some(unread) = status.fields.get("unread").and_then(|n| n.parse().ok()) {
+            mail::observe(unread);
+        }
+    }
+    let _ = tx.send((module.to_string(), status.to_json()));
 }
 
-/// Watch for PulseAudio changes
+/// Refresh audio whenever the sound server reports a change to the default sink or streams
 async fn watch_audio(
+    config: Arc<Config>,
     tx: broadcast::Sender<(String, String)>,
     menu_manager: Arc<MenuManager>,
 ) -> Result<()> {
+    // Last seen (volume, muted), so the OSD only flashes on actual changes
+    let mut last: Option<(String, String)> = None;
+
     loop {
-        let mut child = TokioCommand::new("pactl")
-            .args(["subscribe"])
-            .stdout(Stdio::piped())
-            .spawn()?;
-        
-        let stdout = child.stdout.take().expect("stdout");
-        let mut reader = BufReader::new(stdout).lines();
-        
-        while let Ok(Some(line)) = reader.next_line().await {
-            if line.contains("'change' on sink") {
-                let pinned = menu_manager.is_pinned("audio").await;
-                let status = tokio::task::spawn_blocking(move || {
-                    get_status("audio", pinned)
-                }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
-                let _ = tx.send(("audio".to_string(), status.to_json()));
+        let mut watch = match pulse::Watch::start().await {
+            Ok(watch) => watch,
+            Err(e) => {
+                tracing::debug!("Audio server unavailable: {:#}", e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+        while watch.changed().await {
+            let pinned = menu_manager.is_pinned("audio").await;
+            let cfg = Arc::clone(&config);
+            let status = tokio::task::spawn_blocking(move || {
+                get_status(&cfg, "audio", pinned)
+            }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+
+            let volume = status.fields.get("volume").cloned().unwrap_or_default();
+            let muted = status.fields.get("muted").cloned().unwrap_or_default();
+            let current = (volume, muted);
+            if last.as_ref().is_some_and(|prev| *prev != current) {
+                if current.1 == "yes" {
+                    osd::show(&config.osd, "volume", 0, &icons::get("volume-muted"));
+                } else {
+                    let value = current.0.parse().unwrap_or(0);
+                    osd::show(&config.osd, "volume", value, &icons::get("volume-high"));
+                }
             }
+            last = Some(current);
+
+            publish(&config, &tx, "audio", &status);
         }
-        
-        // Reconnect after a short delay if pactl exits
+
+        // Reconnect after a short delay if the server went away
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
 }
 
-/// Watch for Bluetooth changes via dbus-monitor
+/// Refresh the bluetooth module when BlueZ reports a change it shows
 async fn watch_bluetooth(
+    config: Arc<Config>,
     tx: broadcast::Sender<(String, String)>,
     menu_manager: Arc<MenuManager>,
 ) -> Result<()> {
     loop {
-        let mut child = TokioCommand::new("dbus-monitor")
-            .args(["--system", "type='signal',sender='org.bluez'"])
-            .stdout(Stdio::piped())
-            .spawn()?;
-        
-        let stdout = child.stdout.take().expect("stdout");
-        let mut reader = BufReader::new(stdout).lines();
-        
-        while let Ok(Some(_)) = reader.next_line().await {
-            let pinned = menu_manager.is_pinned("bluetooth").await;
-            let status = tokio::task::spawn_blocking(move || {
-                get_status("bluetooth", pinned)
-            }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
-            let _ = tx.send(("bluetooth".to_string(), status.to_json()));
+        match bluez::Watch::start().await {
+            Ok(mut watch) => {
+                // The model was just (re)loaded, so show it
+                refresh(&config, "bluetooth", &tx, &menu_manager).await;
+                while watch.changed().await {
+                    refresh(&config, "bluetooth", &tx, &menu_manager).await;
+                }
+            }
+            Err(e) => tracing::debug!("BlueZ signals unavailable: {:#}", e),
         }
-        
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        tokio::time::sleep(Duration::from_secs(5)).await;
     }
 }
 
-/// Watch for NetworkManager changes via dbus-monitor
+/// Refresh network whenever NetworkManager or iwd reports a change to the active link
 async fn watch_network(
+    config: Arc<Config>,
     tx: broadcast::Sender<(String, String)>,
     menu_manager: Arc<MenuManager>,
 ) -> Result<()> {
     loop {
-        let mut child = TokioCommand::new("dbus-monitor")
-            .args(["--system", "type='signal',interface='org.freedesktop.NetworkManager'"])
-            .stdout(Stdio::piped())
-            .spawn()?;
-        
-        let stdout = child.stdout.take().expect("stdout");
-        let mut reader = BufReader::new(stdout).lines();
-        
-        while let Ok(Some(_)) = reader.next_line().await {
-            let pinned = menu_manager.is_pinned("network").await;
-            let status = tokio::task::spawn_blocking(move || {
-                get_status("network", pinned)
-            }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
-            let _ = tx.send(("network".to_string(), status.to_json()));
+        match network::Watch::start().await {
+            Ok(mut watch) => {
+                refresh(&config, "network", &tx, &menu_manager).await;
+                while watch.changed().await {
+                    refresh(&config, "network", &tx, &menu_manager).await;
+                }
+            }
+            Err(e) => tracing::debug!("Network signals unavailable: {:#}", e),
         }
-        
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        tokio::time::sleep(Duration::from_secs(5)).await;
     }
 }
 
-/// Watch for battery changes via UPower
+/// How often battery is read from sysfs while UPower is unavailable
+const BATTERY_POLL: Duration = Duration::from_secs(30);
+
+/// Refresh battery when UPower's display device changes in a way the module shows,
+/// polling sysfs while UPower is unavailable
 async fn watch_battery(
+    config: Arc<Config>,
     tx: broadcast::Sender<(String, String)>,
     menu_manager: Arc<MenuManager>,
 ) -> Result<()> {
     loop {
-        let mut child = TokioCommand::new("upower")
-            .args(["--monitor"])
+        match battery::Watch::start().await {
+            Ok(mut watch) => {
+                refresh(&config, "battery", &tx, &menu_manager).await;
+                while watch.changed().await {
+                    refresh(&config, "battery", &tx, &menu_manager).await;
+                }
+            }
+            Err(e) => {
+                tracing::debug!("UPower signals unavailable: {:#}", e);
+                refresh(&config, "battery", &tx, &menu_manager).await;
+                tokio::time::sleep(BATTERY_POLL).await;
+                continue;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+/// Watch udev for bluetooth/backlight hotplug and refresh the affected modules
+async fn watch_hotplug(
+    config: Arc<Config>,
+    tx: broadcast::Sender<(String, String)>,
+    menu_manager: Arc<MenuManager>,
+) -> Result<()> {
+    loop {
+        let mut child = TokioCommand::new("udevadm")
+            .args(["monitor", "--udev", "-s", "bluetooth", "-s", "backlight"])
             .stdout(Stdio::piped())
+            .kill_on_drop(true)
             .stderr(Stdio::null())
             .spawn()?;
 
@@ -180,72 +581,357 @@ async fn watch_battery(
         let mut reader = BufReader::new(stdout).lines();
 
         while let Ok(Some(line)) = reader.next_line().await {
-            if line.contains("battery") || line.contains("line_power") || line.contains("DisplayDevice") {
-                let pinned = menu_manager.is_pinned("battery").await;
+            // Line format: "UDEV  [1234.5678] add      /devices/... (bluetooth)"
+            if line.contains(" change ") && line.ends_with("(backlight)") {
+                // Firmware brightness keys change the level without writing the sysfs node
+                refresh(&config, "brightness", &tx, &menu_manager).await;
+                continue;
+            }
+            if !(line.contains(" add ") || line.contains(" remove ")) {
+                continue;
+            }
+            for module in hardware::hotplug_modules() {
+                if !config.modules.contains_key(module) {
+                    continue;
+                }
+                let pinned = menu_manager.is_pinned(module).await;
+                let cfg = Arc::clone(&config);
                 let status = tokio::task::spawn_blocking(move || {
-                    get_status("battery", pinned)
+                    get_status(&cfg, module, pinned)
                 }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
-                let _ = tx.send(("battery".to_string(), status.to_json()));
+                publish(&config, &tx, module, &status);
             }
         }
 
-        // Reconnect after a short delay if upower exits
+        // Reconnect after a short delay if udevadm exits
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
 }
 
+/// Refresh the brightness module whenever something (brightnessctl, another
+/// daemon, us) writes the backlight level
+async fn watch_brightness(
+    config: Arc<Config>,
+    tx: broadcast::Sender<(String, String)>,
+    menu_manager: Arc<MenuManager>,
+) -> Result<()> {
+    let file = Backlight::find().context("No backlight")?.brightness_file();
+    let (changed_tx, mut changed) = tokio::sync::mpsc::channel(1);
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|e| e.kind.is_modify()) {
+            // A full channel already has a refresh queued
+            let _ = changed_tx.try_send(());
+        }
+    })?;
+    watcher.watch(&file, RecursiveMode::NonRecursive)?;
+
+    while changed.recv().await.is_some() {
+        refresh(&config, "brightness", &tx, &menu_manager).await;
+    }
+    Ok(())
+}
+
+/// Read a module's status and broadcast it
+async fn refresh(
+    config: &Arc<Config>,
+    module: &'static str,
+    tx: &broadcast::Sender<(String, String)>,
+    menu_manager: &Arc<MenuManager>,
+) {
+    let pinned = menu_manager.is_pinned(module).await;
+    let cfg = Arc::clone(config);
+    let status = tokio::task::spawn_blocking(move || {
+        get_status(&cfg, module, pinned)
+    }).await.unwrap_or_else(|_| ModuleStatus::new("error"));
+    publish(config, tx, module, &status);
+}
+
+/// Watch /dev/rfkill, which reports every radio added, removed or (un)blocked
+async fn watch_rfkill(
+    config: Arc<Config>,
+    tx: broadcast::Sender<(String, String)>,
+    menu_manager: Arc<MenuManager>,
+) -> Result<()> {
+    let mut device = tokio::fs::File::open(rfkill::RFKILL_DEVICE).await?;
+    // Events are 8 or 9 bytes depending on the kernel; each read returns one
+    let mut event = [0u8; 64];
+    loop {
+        if device.read(&mut event).await? == 0 {
+            return Ok(());
+        }
+        let pinned = menu_manager.is_pinned("airplane").await;
+        let cfg = Arc::clone(&config);
+        let status = tokio::task::spawn_blocking(move || {
+            get_status(&cfg, "airplane", pinned)
+        }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+        publish(&config, &tx, "airplane", &status);
+    }
+}
+
+/// Refresh a custom module whenever its `watch_command` prints a line,
+/// restarting the command if it exits
+async fn watch_custom(
+    config: Arc<Config>,
+    module: String,
+    watch: String,
+    tx: broadcast::Sender<(String, String)>,
+    menu_manager: Arc<MenuManager>,
+) {
+    loop {
+        let child = crate::command::build(&watch, &[], config.use_shell(&module))
+            .map(TokioCommand::from)
+            .and_then(|mut cmd| {
+                Ok(cmd.stdin(Stdio::null()).stdout(Stdio::piped()).kill_on_drop(true).spawn()?)
+            });
+        match child {
+            Ok(mut child) => {
+                let stdout = child.stdout.take().expect("stdout");
+                let mut reader = BufReader::new(stdout).lines();
+                while let Ok(Some(_)) = reader.next_line().await {
+                    let pinned = menu_manager.is_pinned(&module).await;
+                    let cfg = Arc::clone(&config);
+                    let name = module.clone();
+                    let status = tokio::task::spawn_blocking(move || {
+                        get_status(&cfg, &name, pinned)
+                    }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+                    publish(&config, &tx, &module, &status);
+                }
+                tracing::warn!("{} watch_command exited, restarting", module);
+            }
+            Err(e) => tracing::error!("{} watch_command: {}", module, e),
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+/// Check for package updates every `interval`, skipping checks while the session is idle
+async fn watch_updates(
+    config: Arc<Config>,
+    interval: Duration,
+    tx: broadcast::Sender<(String, String)>,
+    menu_manager: Arc<MenuManager>,
+) {
+    let checkers = updates::checkers(&config.modules["updates"].checkers);
+    if checkers.is_empty() {
+        tracing::warn!("No update checker found (checkupdates, apt, dnf or flatpak)");
+        return;
+    }
+    // After a reload, the last check may still be recent enough
+    let mut wait = updates::pending().map_or(Duration::ZERO, |(age, _)| interval.saturating_sub(age));
+    let mut idle = idle::subscribe();
+    loop {
+        tokio::time::sleep(wait).await;
+        wait = interval;
+        while *idle.borrow_and_update() {
+            if idle.changed().await.is_err() {
+                break;
+            }
+        }
+        let checkers = checkers.clone();
+        let checked = tokio::task::spawn_blocking(move || {
+            crate::command::with_timeout(Some(updates::CHECK_TIMEOUT), || updates::check(&checkers)).0
+        }).await;
+        if let Ok(Err(e)) = checked {
+            tracing::warn!("Update check failed: {:#}", e);
+        }
+        refresh(&config, "updates", &tx, &menu_manager).await;
+    }
+}
+
+/// Refresh the media module when a player changes state or track, or comes and goes
+async fn watch_media(
+    config: Arc<Config>,
+    tx: broadcast::Sender<(String, String)>,
+    menu_manager: Arc<MenuManager>,
+) -> Result<()> {
+    let mut changes = media::changes().await?;
+    while changes.recv().await.is_some() {
+        let pinned = menu_manager.is_pinned("media").await;
+        let cfg = Arc::clone(&config);
+        let status = tokio::task::spawn_blocking(move || {
+            get_status(&cfg, "media", pinned)
+        }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
+        publish(&config, &tx, "media", &status);
+    }
+    Ok(())
+}
+
 /// Poll a module at a fixed interval
 async fn poll_module(
+    config: Arc<Config>,
     module: &str,
     interval: Duration,
     tx: broadcast::Sender<(String, String)>,
     menu_manager: Arc<MenuManager>,
 ) {
     let module = module.to_string();
+    let mut idle = idle::subscribe();
     loop {
-        tokio::time::sleep(interval).await;
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = idle.changed() => {}
+        }
+        // Don't poll while the session is idle; poll right away once it's back
+        while *idle.borrow_and_update() {
+            if idle.changed().await.is_err() {
+                break;
+            }
+        }
         let pinned = menu_manager.is_pinned(&module).await;
         let module_clone = module.clone();
+        let cfg = Arc::clone(&config);
         let status = tokio::task::spawn_blocking(move || {
-            get_status(&module_clone, pinned)
+            get_status(&cfg, &module_clone, pinned)
         }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
-        let _ = tx.send((module.clone(), status.to_json()));
+        publish(&config, &tx, &module, &status);
+    }
+}
+
+/// Fetch the weather right away and then every `poll_interval`, refreshing the widget
+/// after each fetch. Like the pollers, it pauses while the session is idle.
+async fn watch_weather(
+    config: Arc<Config>,
+    tx: broadcast::Sender<(String, String)>,
+    menu_manager: Arc<MenuManager>,
+) {
+    let Some(module_config) = config.get_module("weather").cloned() else {
+        return;
+    };
+    let interval = Duration::from_secs(module_config.poll_interval.unwrap_or(weather::DEFAULT_INTERVAL));
+    let mut idle = idle::subscribe();
+    loop {
+        weather::update(&module_config).await;
+        refresh(&config, "weather", &tx, &menu_manager).await;
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = idle.changed() => {}
+        }
+        while *idle.borrow_and_update() {
+            if idle.changed().await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Alternate a module's widget between its real status and a "blink" frame
+/// while its status carries one of the `blink_on` classes
+async fn blink_module(
+    module: String,
+    config: ModuleConfig,
+    tx: broadcast::Sender<(String, String)>,
+) {
+    let mut rx = tx.subscribe();
+    // Rate-limit so a typo can't flood every follow client
+    let period = Duration::from_millis(config.blink_interval_ms.max(250));
+    let mut ticker = tokio::time::interval(period);
+
+    // Latest real status, and the last frame we broadcast ourselves (to ignore its echo)
+    let mut base: Option<serde_json::Value> = None;
+    let mut last_frame: Option<String> = None;
+    let mut phase = false;
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Ok((update_module, json)) => {
+                    if update_module != module || last_frame.as_ref() == Some(&json) {
+                        continue;
+                    }
+                    base = serde_json::from_str(&json).ok();
+                    phase = false;
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = ticker.tick() => {
+                let Some(status) = &base else {
+                    continue;
+                };
+                let classes = status.get("class").and_then(|c| c.as_array());
+                let blinking = classes.is_some_and(|classes| {
+                    classes.iter().any(|c| config.blink_on.iter().any(|b| c == b))
+                });
+                if !blinking {
+                    continue;
+                }
+
+                phase = !phase;
+                let mut frame = status.clone();
+                if phase {
+                    if let Some(classes) = frame.get_mut("class").and_then(|c| c.as_array_mut()) {
+                        classes.push(serde_json::json!("blink"));
+                    }
+                    if let Some(text) = &config.blink_text {
+                        frame["text"] = serde_json::json!(text);
+                    }
+                }
+                let json = frame.to_string();
+                last_frame = Some(json.clone());
+                let _ = tx.send((module.clone(), json));
+            }
+        }
     }
 }
 
-/// Watch mail directory for changes
+/// How long mail waits for a burst of deliveries (a sync fetching a hundred
+/// messages, say) to settle before counting
+const MAIL_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The maildir itself once it exists, else its nearest existing parent
+fn mail_watch_target(root: &Path) -> Option<PathBuf> {
+    root.ancestors().find(|p| p.is_dir()).map(Path::to_path_buf)
+}
+
+/// Watch the maildir recursively, including account directories created later, and
+/// refresh mail once a burst of deliveries settles. Until the maildir exists (or after
+/// it is removed), its nearest existing parent is watched for it to appear.
 async fn watch_mail(
+    config: Arc<Config>,
     mail_dir: &str,
     tx: broadcast::Sender<(String, String)>,
     menu_manager: Arc<MenuManager>,
 ) -> Result<()> {
-    let expanded = shellexpand::tilde(mail_dir).to_string();
-    let path = Path::new(&expanded);
-    
-    if !path.exists() {
-        tracing::warn!("Mail directory does not exist: {}", expanded);
-        return Ok(());
-    }
-    
-    // Use inotifywait for recursive watching
+    let root = PathBuf::from(shellexpand::tilde(mail_dir).as_ref());
+    let (changed_tx, mut changed) = tokio::sync::mpsc::channel(1);
+    let watched = root.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        // Deliveries, deletions and renames (which is how flags and new -> cur change)
+        let relevant = matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+        );
+        // Under the maildir, or on the way to it while waiting for it to appear
+        let ours = event.paths.iter().any(|p| p.starts_with(&watched) || watched.starts_with(p));
+        if relevant && ours {
+            // A full channel already has a refresh queued
+            let _ = changed_tx.try_send(());
+        }
+    })?;
+
     loop {
-        let mut child = TokioCommand::new("inotifywait")
-            .args(["-m", "-r", "-e", "create,delete,moved_to,moved_from", &expanded])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .spawn()?;
-        
-        let stdout = child.stdout.take().expect("stdout");
-        let mut reader = BufReader::new(stdout).lines();
-        
-        while let Ok(Some(_)) = reader.next_line().await {
-            let pinned = menu_manager.is_pinned("mail").await;
-            let status = tokio::task::spawn_blocking(move || {
-                get_status("mail", pinned)
-            }).await.unwrap_or_else(|_| crate::modules::ModuleStatus::new("error"));
-            let _ = tx.send(("mail".to_string(), status.to_json()));
+        let target = mail_watch_target(&root).context("No existing parent of the mail directory")?;
+        if target == root {
+            watcher.watch(&target, RecursiveMode::Recursive)?;
+        } else {
+            tracing::warn!("Mail directory does not exist yet: {}", root.display());
+            watcher.watch(&target, RecursiveMode::NonRecursive)?;
         }
-        
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        refresh(&config, "mail", &tx, &menu_manager).await;
+
+        while changed.recv().await.is_some() {
+            tokio::time::sleep(MAIL_DEBOUNCE).await;
+            while changed.try_recv().is_ok() {}
+            if mail_watch_target(&root).as_ref() != Some(&target) {
+                break;
+            }
+            refresh(&config, "mail", &tx, &menu_manager).await;
+        }
+        // The maildir appeared or went away, or a parent on the way to it did
+        let _ = watcher.unwatch(&target);
     }
 }
@@ -0,0 +1,309 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::command;
+use crate::config::ModuleConfig;
+
+/// Seconds between fetches when the module sets no `poll_interval`
+pub const DEFAULT_INTERVAL: u64 = 900;
+
+/// Current conditions at the configured location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conditions {
+    /// Place name as the provider knows it, if it says
+    pub place: String,
+    pub temperature: f64,
+    pub feels_like: f64,
+    /// Relative humidity in percent
+    pub humidity: f64,
+    /// Wind speed in km/h, or mph for imperial units
+    pub wind: f64,
+    pub description: String,
+    /// Icon role: "weather-clear", "weather-cloudy", "weather-fog", "weather-rain",
+    /// "weather-snow" or "weather-storm"
+    pub sky: String,
+}
+
+/// The last conditions fetched for a location
+#[derive(Clone, Serialize, Deserialize)]
+struct Report {
+    /// When, in seconds since the epoch
+    fetched: u64,
+    conditions: Conditions,
+    /// The latest fetch failed, so these are older than they should be
+    #[serde(skip)]
+    failed: bool,
+}
+
+/// A place name looked up with Open-Meteo's geocoding API
+#[derive(Clone, Serialize, Deserialize)]
+struct Place {
+    latitude: f64,
+    longitude: f64,
+    name: String,
+}
+
+/// What is kept across restarts, so the widget has conditions before the first fetch
+#[derive(Default, Serialize, Deserialize)]
+struct Store {
+    /// Per provider, location and units
+    #[serde(default)]
+    reports: HashMap<String, Report>,
+    /// Per place name
+    #[serde(default)]
+    places: HashMap<String, Place>,
+}
+
+/// The store, read from disk on first use
+static STORE: Mutex<Option<Store>> = Mutex::new(None);
+
+fn with_store<T>(f: impl FnOnce(&mut Store) -> T) -> T {
+    let mut store = STORE.lock().unwrap();
+    f(store.get_or_insert_with(load))
+}
+
+/// Where the store is kept
+fn store_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("waybar-hovermenu")
+        .join("weather.json")
+}
+
+/// A missing or unreadable file yields an empty store
+fn load() -> Store {
+    std::fs::read_to_string(store_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save(store: &Store) {
+    let path = store_path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match serde_json::to_string_pretty(store) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&path, json) {
+                tracing::warn!("Failed to save weather to {}: {}", path.display(), e);
+            }
+        }
+        Err(e) => tracing::warn!("Failed to serialize weather: {}", e),
+    }
+}
+
+/// Which report a module's settings pick
+fn report_key(config: &ModuleConfig) -> String {
+    format!(
+        "{} {} {}",
+        config.weather_provider,
+        config.location.as_deref().unwrap_or("").trim(),
+        config.units
+    )
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// The last conditions fetched for the configured location, with their age when they
+/// are stale: the latest fetch failed, or they are long overdue (from an earlier run).
+/// `None` until a fetch has worked.
+pub fn current(config: &ModuleConfig) -> Option<(Conditions, Option<Duration>)> {
+    let report = with_store(|store| store.reports.get(&report_key(config)).cloned())?;
+    let interval = config.poll_interval.unwrap_or(DEFAULT_INTERVAL);
+    let age = now().saturating_sub(report.fetched);
+    let stale = report.failed || age > 2 * interval;
+    Some((report.conditions, stale.then(|| Duration::from_secs(age))))
+}
+
+/// Fetch the conditions at the configured location and keep them. When the fetch fails
+/// (offline, provider down) the last ones stay, marked stale.
+pub async fn update(config: &ModuleConfig) {
+    let key = report_key(config);
+    match fetch(config).await {
+        Ok(conditions) => with_store(|store| {
+            store.reports.insert(key, Report { fetched: now(), conditions, failed: false });
+            save(store);
+        }),
+        Err(e) => {
+            tracing::debug!("Weather fetch failed, keeping last known: {:#}", e);
+            with_store(|store| {
+                if let Some(report) = store.reports.get_mut(&key) {
+                    report.failed = true;
+                }
+            });
+        }
+    }
+}
+
+async fn fetch(config: &ModuleConfig) -> Result<Conditions> {
+    let location = config.location.as_deref().unwrap_or("").trim();
+    let imperial = config.units == "imperial";
+    let timeout = config.timeout_ms.map(Duration::from_millis).unwrap_or_else(command::current_timeout);
+    let client = reqwest::Client::builder().timeout(timeout).build()?;
+    match config.weather_provider.as_str() {
+        "open-meteo" => open_meteo(&client, location, imperial).await,
+        "wttr" | "wttr.in" => wttr(&client, location, imperial).await,
+        other => bail!("Unknown weather_provider {:?}, expected open-meteo or wttr", other),
+    }
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value> {
+    let response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        bail!("{} returned HTTP {}", url, response.status().as_u16());
+    }
+    let body = response.text().await?;
+    serde_json::from_str(&body).with_context(|| format!("Bad JSON from {}", url))
+}
+
+/// Open-Meteo needs coordinates; a place name is looked up with its geocoding API first
+async fn open_meteo(client: &reqwest::Client, location: &str, imperial: bool) -> Result<Conditions> {
+    let coordinates = location.split_once(',').and_then(|(lat, lon)| {
+        Some((lat.trim().parse::<f64>().ok()?, lon.trim().parse::<f64>().ok()?))
+    });
+    let (latitude, longitude, place) = match coordinates {
+        Some((lat, lon)) => (lat, lon, String::new()),
+        None if location.is_empty() => bail!("Open-Meteo needs a location, e.g. \"52.52,13.41\" or \"Berlin\""),
+        None => {
+            let place = geocode(client, location).await?;
+            (place.latitude, place.longitude, place.name)
+        }
+    };
+
+    let mut url = format!(
+        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}\
+         &current=temperature_2m,apparent_temperature,relative_humidity_2m,weather_code,wind_speed_10m",
+        latitude, longitude
+    );
+    if imperial {
+        url.push_str("&temperature_unit=fahrenheit&wind_speed_unit=mph");
+    }
+    let reply = get_json(client, &url).await?;
+    let current = reply.get("current").context("Open-Meteo reply without current conditions")?;
+    let number = |key: &str| current.get(key).and_then(Value::as_f64).unwrap_or(0.0);
+    let code = number("weather_code") as u32;
+    Ok(Conditions {
+        place,
+        temperature: number("temperature_2m"),
+        feels_like: number("apparent_temperature"),
+        humidity: number("relative_humidity_2m"),
+        wind: number("wind_speed_10m"),
+        description: wmo_description(code).to_string(),
+        sky: wmo_sky(code).to_string(),
+    })
+}
+
+/// A place name's coordinates, looked up once and then kept
+async fn geocode(client: &reqwest::Client, location: &str) -> Result<Place> {
+    if let Some(place) = with_store(|store| store.places.get(location).cloned()) {
+        return Ok(place);
+    }
+    let url = format!(
+        "https://geocoding-api.open-meteo.com/v1/search?count=1&name={}",
+        urlencode(location)
+    );
+    let found = get_json(client, &url).await?;
+    let place = found.pointer("/results/0").with_context(|| format!("No place called {:?}", location))?;
+    let coordinate = |key: &str| place.get(key).and_then(Value::as_f64).context("Place without coordinates");
+    let place = Place {
+        latitude: coordinate("latitude")?,
+        longitude: coordinate("longitude")?,
+        name: place.get("name").and_then(Value::as_str).unwrap_or(location).to_string(),
+    };
+    with_store(|store| store.places.insert(location.to_string(), place.clone()));
+    Ok(place)
+}
+
+/// wttr.in takes a place name, coordinates or nothing (locate by IP)
+async fn wttr(client: &reqwest::Client, location: &str, imperial: bool) -> Result<Conditions> {
+    let url = format!("https://wttr.in/{}?format=j1", urlencode(location));
+    let reply = get_json(client, &url).await?;
+    let current = reply.pointer("/current_condition/0").context("wttr.in reply without current conditions")?;
+    // wttr.in reports every number as a string
+    let number = |key: &str| {
+        current.get(key).and_then(Value::as_str).and_then(|v| v.parse::<f64>().ok()).unwrap_or(0.0)
+    };
+    let (temperature, feels_like, wind) = if imperial {
+        (number("temp_F"), number("FeelsLikeF"), number("windspeedMiles"))
+    } else {
+        (number("temp_C"), number("FeelsLikeC"), number("windspeedKmph"))
+    };
+    let place = reply
+        .pointer("/nearest_area/0/areaName/0/value")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string();
+    Ok(Conditions {
+        place,
+        temperature,
+        feels_like,
+        humidity: number("humidity"),
+        wind,
+        description: current
+            .pointer("/weatherDesc/0/value")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .trim()
+            .to_string(),
+        sky: wwo_sky(number("weatherCode") as u32).to_string(),
+    })
+}
+
+/// Percent-encode a place name for a URL
+fn urlencode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b',' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Icon role for a WMO weather code (Open-Meteo)
+fn wmo_sky(code: u32) -> &'static str {
+    match code {
+        0 => "weather-clear",
+        1..=3 => "weather-cloudy",
+        45 | 48 => "weather-fog",
+        71..=77 | 85 | 86 => "weather-snow",
+        95..=99 => "weather-storm",
+        _ => "weather-rain",
+    }
+}
+
+fn wmo_description(code: u32) -> &'static str {
+    match code {
+        0 => "Clear sky",
+        1 => "Mainly clear",
+        2 => "Partly cloudy",
+        3 => "Overcast",
+        45 | 48 => "Fog",
+        51..=57 => "Drizzle",
+        61..=67 => "Rain",
+        71..=77 => "Snow",
+        80..=82 => "Rain showers",
+        85 | 86 => "Snow showers",
+        95..=99 => "Thunderstorm",
+        _ => "Unknown",
+    }
+}
+
+/// Icon role for a World Weather Online condition code (wttr.in)
+fn wwo_sky(code: u32) -> &'static str {
+    match code {
+        113 => "weather-clear",
+        116 | 119 | 122 => "weather-cloudy",
+        143 | 248 | 260 => "weather-fog",
+        200 | 386 | 389 | 392 | 395 => "weather-storm",
+        179 | 182 | 185 | 227 | 230 | 317 | 320 | 323..=338 | 350 | 362..=377 => "weather-snow",
+        _ => "weather-rain",
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::{bail, Context, Result};
+use std::time::Duration;
+
+use crate::config::WebhookConfig;
+use crate::modules::ModuleStatus;
+use crate::secrets;
+
+/// Perform a webhook request, rendering module fields into the URL and body.
+/// Secrets are expanded in the templates before fields are substituted, so a field
+/// value can never pull a secret in.
+pub async fn send(hook: &WebhookConfig, status: &ModuleStatus) -> Result<()> {
+    let secrets = secrets::load();
+    let url = status.render_url(&secrets::expand(&hook.url, &secrets));
+    let method = reqwest::Method::from_bytes(hook.method.to_uppercase().as_bytes())
+        .with_context(|| format!("Invalid HTTP method {:?}", hook.method))?;
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(hook.timeout_secs))
+        .build()?;
+    let mut request = client.request(method, &url);
+    for (name, value) in &hook.headers {
+        request = request.header(name, secrets::expand(value, &secrets));
+    }
+    if let Some(body) = &hook.body {
+        request = request
+            .header("Content-Type", "application/json")
+            .body(status.render_json(&secrets::expand(body, &secrets)));
+    }
+
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        bail!("{} {} returned HTTP {}", hook.method, url, response.status().as_u16());
+    }
+    Ok(())
+}
@@ -0,0 +1,296 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry::WlRegistry;
+use wayland_client::{delegate_noop, event_created_child, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_handle_v1::{
+    self, ZwlrForeignToplevelHandleV1,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::{
+    self, ZwlrForeignToplevelManagerV1,
+};
+use wayland_protocols_wlr::virtual_pointer::v1::client::zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1;
+use wayland_protocols_wlr::virtual_pointer::v1::client::zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1;
+
+use crate::compositor::{Compositor, Monitor, Window};
+use crate::geometry::Geometry;
+
+/// A window as the foreign-toplevel protocol describes it: no pid, no geometry
+#[derive(Debug, Clone, Default)]
+struct Toplevel {
+    title: String,
+    app_id: String,
+    activated: bool,
+    fullscreen: bool,
+}
+
+#[derive(Default)]
+struct State {
+    /// Toplevels by handle id, as of their last `done` event
+    toplevels: HashMap<u32, Toplevel>,
+    /// Changes not yet committed by `done`
+    pending: HashMap<u32, Toplevel>,
+    /// Handles by id, to close their windows
+    handles: HashMap<u32, ZwlrForeignToplevelHandleV1>,
+    /// Focus watchers (see `focus_changes`)
+    focus: Vec<mpsc::Sender<String>>,
+    /// The virtual pointer, if the compositor gave us one
+    pointer: Option<ZwlrVirtualPointerV1>,
+    /// The connection requests go out on, while it lasts
+    connection: Option<Connection>,
+    /// The compositor stopped sending toplevels
+    finished: bool,
+}
+
+/// Fallback for wlroots compositors without a rich IPC (river, labwc, ...): tracks
+/// windows through wlr-foreign-toplevel-management over a plain Wayland connection.
+/// It can find, focus-check and close menu windows (and nudge the pointer through
+/// wlr-virtual-pointer), but not place them or read the cursor position, so the
+/// compositor's window rules decide where menus go.
+pub struct Wlroots {
+    state: Arc<Mutex<State>>,
+}
+
+impl Wlroots {
+    /// Start tracking toplevels on a thread of its own, reconnecting if the connection drops
+    pub fn new() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || loop {
+                if let Err(e) = track(&state) {
+                    tracing::debug!("Foreign toplevel tracking stopped: {:#}", e);
+                }
+                {
+                    let mut state = state.lock().unwrap();
+                    state.toplevels.clear();
+                    state.pending.clear();
+                    state.handles.clear();
+                    state.pointer = None;
+                    state.connection = None;
+                    state.finished = false;
+                    // Closing the channels tells focus watchers the connection dropped
+                    state.focus.clear();
+                }
+                std::thread::sleep(Duration::from_secs(1));
+            });
+        }
+        Self { state }
+    }
+
+    /// Send what `request` queued to the compositor
+    fn flush(&self) -> Result<()> {
+        let connection = self.state.lock().unwrap().connection.clone();
+        connection.context("Not connected to the Wayland compositor")?.flush()?;
+        Ok(())
+    }
+}
+
+/// Connect, bind the toplevel manager and follow its events until the connection ends
+fn track(state: &Arc<Mutex<State>>) -> Result<()> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<Tracker>(&connection)?;
+    let qh = queue.handle();
+
+    globals
+        .bind::<ZwlrForeignToplevelManagerV1, _, _>(&qh, 1..=3, ())
+        .context("The compositor doesn't support wlr-foreign-toplevel-management")?;
+    let pointer = globals
+        .bind::<ZwlrVirtualPointerManagerV1, _, _>(&qh, 1..=2, ())
+        .ok()
+        .map(|manager| manager.create_virtual_pointer(None, &qh, ()));
+    {
+        let mut state = state.lock().unwrap();
+        state.pointer = pointer;
+        state.connection = Some(connection.clone());
+    }
+
+    let mut tracker = Tracker { state: Arc::clone(state) };
+    loop {
+        queue.blocking_dispatch(&mut tracker)?;
+        if state.lock().unwrap().finished {
+            bail!("Toplevel manager finished");
+        }
+    }
+}
+
+/// Applies protocol events to the shared state
+struct Tracker {
+    state: Arc<Mutex<State>>,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for Tracker {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for Tracker {
+    fn event(
+        tracker: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let mut state = tracker.state.lock().unwrap();
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                let id = toplevel.id().protocol_id();
+                state.pending.insert(id, Toplevel::default());
+                state.handles.insert(id, toplevel);
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => state.finished = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(Tracker, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for Tracker {
+    fn event(
+        tracker: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let id = handle.id().protocol_id();
+        let mut guard = tracker.state.lock().unwrap();
+        let state = &mut *guard;
+
+        if let zwlr_foreign_toplevel_handle_v1::Event::Closed = event {
+            // Forget it and destroy our handle
+            state.toplevels.remove(&id);
+            state.pending.remove(&id);
+            state.handles.remove(&id);
+            handle.destroy();
+            return;
+        }
+
+        let current = state.toplevels.get(&id).cloned().unwrap_or_default();
+        let toplevel = state.pending.entry(id).or_insert(current);
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => toplevel.title = title,
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => toplevel.app_id = app_id,
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: states } => {
+                let states: Vec<u32> = states
+                    .chunks_exact(4)
+                    .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect();
+                let has = |s: zwlr_foreign_toplevel_handle_v1::State| states.contains(&(s as u32));
+                toplevel.activated = has(zwlr_foreign_toplevel_handle_v1::State::Activated);
+                toplevel.fullscreen = has(zwlr_foreign_toplevel_handle_v1::State::Fullscreen);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                // The pending changes are now current
+                let toplevel = state.pending.remove(&id).unwrap_or_default();
+                let was_active = state.toplevels.get(&id).is_some_and(|t| t.activated);
+                if toplevel.activated && !was_active {
+                    let focused = id.to_string();
+                    state.focus.retain(|tx| tx.try_send(focused.clone()).is_ok() || !tx.is_closed());
+                }
+                state.toplevels.insert(id, toplevel);
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(Tracker: ignore ZwlrVirtualPointerManagerV1);
+delegate_noop!(Tracker: ignore ZwlrVirtualPointerV1);
+
+#[async_trait]
+impl Compositor for Wlroots {
+    async fn windows(&self) -> Result<Vec<Window>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.toplevels.iter()
+            .map(|(handle, t)| Window {
+                id: handle.to_string(),
+                pid: 0,
+                title: t.title.clone(),
+                class: t.app_id.clone(),
+                geometry: None,
+                focused: t.activated,
+            })
+            .collect())
+    }
+
+    async fn fullscreen_focused(&self) -> Result<bool> {
+        let state = self.state.lock().unwrap();
+        Ok(state.toplevels.values().any(|t| t.activated && t.fullscreen))
+    }
+
+    async fn cursor_position(&self) -> Result<(i32, i32)> {
+        bail!("The foreign-toplevel protocol has no cursor position")
+    }
+
+    async fn monitors(&self) -> Result<Vec<Monitor>> {
+        bail!("The foreign-toplevel protocol has no output geometry")
+    }
+
+    async fn place_window(&self, _id: &str, _geometry: Geometry) -> Result<()> {
+        bail!("The foreign-toplevel protocol can't move windows")
+    }
+
+    async fn move_windows(&self, _ids: &[String], _dx: i32, _dy: i32) -> Result<()> {
+        // No closing animation; the windows just close
+        Ok(())
+    }
+
+    async fn set_props(&self, _id: &str, _props: &[(&str, String)]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn run_window_commands(&self, _id: &str, commands: &[String]) -> Result<()> {
+        if !commands.is_empty() {
+            tracing::debug!("window_props need Hyprland or Sway, skipping");
+        }
+        Ok(())
+    }
+
+    async fn close_window(&self, id: &str) -> Result<()> {
+        let id: u32 = id.parse().context("Not a toplevel handle")?;
+        {
+            let state = self.state.lock().unwrap();
+            state.handles.get(&id).context("No such toplevel")?.close();
+        }
+        self.flush()
+    }
+
+    async fn jiggle_pointer(&self) -> Result<()> {
+        {
+            let state = self.state.lock().unwrap();
+            let pointer = state
+                .pointer
+                .as_ref()
+                .context("The compositor doesn't support wlr-virtual-pointer")?;
+            for dx in [1.0, -1.0] {
+                pointer.motion(0, dx, 0.0);
+                pointer.frame();
+            }
+        }
+        self.flush()
+    }
+
+    async fn focus_changes(&self) -> Result<mpsc::Receiver<String>> {
+        let (tx, rx) = mpsc::channel(16);
+        self.state.lock().unwrap().focus.push(tx);
+        Ok(rx)
+    }
+}